@@ -0,0 +1,349 @@
+//! SVG export module for the SIR chart.
+//!
+//! Draws the stacked S/I/R areas of the recorded series like the chart in the UI,
+//! with the ODE overlay when it is enabled, as a vector image for reports and papers.
+//! The image has axis labels, a legend and a caption with the run parameters.
+
+use std::fmt::Write as _;
+use std::fs;
+use std::path::Path;
+
+use eframe::egui;
+
+use crate::export::ExportError;
+use crate::simulation::Simulation;
+
+/// Default chart file shown in the UI.
+pub const DEFAULT_CHART_PATH: &str = "sir_chart.svg";
+
+/// Size (in pixels) of the whole image.
+const WIDTH: f32 = 800.0;
+const HEIGHT: f32 = 520.0;
+
+/// Margins (in pixels) around the plot area, with room for the labels, legend and caption.
+const MARGIN_LEFT: f32 = 70.0;
+const MARGIN_RIGHT: f32 = 190.0;
+const MARGIN_TOP: f32 = 30.0;
+const MARGIN_BOTTOM: f32 = 120.0;
+
+/// Approximate number of ticks on the time axis.
+const TIME_TICKS: f32 = 8.0;
+
+/// Returns the color as an SVG `rgb(...)` value.
+fn svg_color(color: egui::Color32) -> String {
+    format!("rgb({},{},{})", color.r(), color.g(), color.b())
+}
+
+/// Returns a tick spacing of 1, 2 or 5 times a power of ten close to `range / count`.
+///
+/// # Parameters
+/// - `range`: Length of the axis
+/// - `count`: Approximate number of ticks
+fn tick_step(range: f32, count: f32) -> f32 {
+    let rough = (range / count).max(f32::EPSILON);
+    let magnitude = 10f32.powf(rough.log10().floor());
+    let normalized = rough / magnitude;
+    let nice = if normalized <= 1.0 {
+        1.0
+    } else if normalized <= 2.0 {
+        2.0
+    } else if normalized <= 5.0 {
+        5.0
+    } else {
+        10.0
+    };
+    nice * magnitude
+}
+
+/// Writes the SIR chart of a simulation as SVG.
+///
+/// # Parameters
+/// - `simulation`: The simulation whose recorded series is drawn
+/// - `out`: Where the SVG is written
+pub fn write_svg(simulation: &Simulation, out: &mut String) -> Result<(), ExportError> {
+    let write_error = |error: std::fmt::Error| ExportError::Serialize(error.to_string());
+    let plot_width = WIDTH - MARGIN_LEFT - MARGIN_RIGHT;
+    let plot_height = HEIGHT - MARGIN_TOP - MARGIN_BOTTOM;
+    let end_time = simulation
+        .total_time
+        .last()
+        .copied()
+        .unwrap_or(0.0)
+        .max(1.0);
+    let x = |time: f32| MARGIN_LEFT + time / end_time * plot_width;
+    let y = |percentage: f32| MARGIN_TOP + (1.0 - percentage / 100.0) * plot_height;
+
+    writeln!(
+        out,
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="{WIDTH}" height="{HEIGHT}" viewBox="0 0 {WIDTH} {HEIGHT}" font-family="sans-serif" font-size="12">"#
+    )
+    .map_err(write_error)?;
+    writeln!(
+        out,
+        r#"<rect width="{WIDTH}" height="{HEIGHT}" fill="white"/>"#
+    )
+    .map_err(write_error)?;
+
+    // Stacked areas, drawn from the top so each band covers the one below it
+    let times = &simulation.total_time;
+    let stacks: [(egui::Color32, Vec<f32>); 3] = [
+        (
+            egui::Color32::GRAY,
+            (0..times.len())
+                .map(|i| {
+                    simulation.infected_chart[i]
+                        + simulation.susceptible_chart[i]
+                        + simulation.recovered_chart[i]
+                })
+                .collect(),
+        ),
+        (
+            egui::Color32::BLUE,
+            (0..times.len())
+                .map(|i| simulation.infected_chart[i] + simulation.susceptible_chart[i])
+                .collect(),
+        ),
+        (egui::Color32::RED, simulation.infected_chart.clone()),
+    ];
+    for (color, values) in &stacks {
+        let mut points = format!("{:.2},{:.2}", x(0.0), y(0.0));
+        for (time, value) in times.iter().zip(values) {
+            write!(points, " {:.2},{:.2}", x(*time), y(*value)).map_err(write_error)?;
+        }
+        write!(
+            points,
+            " {:.2},{:.2}",
+            x(times.last().copied().unwrap_or(0.0)),
+            y(0.0)
+        )
+        .map_err(write_error)?;
+        writeln!(
+            out,
+            r#"<polygon points="{}" fill="{}" fill-opacity="0.6" stroke="{}" stroke-width="1.5"/>"#,
+            points,
+            svg_color(*color),
+            svg_color(*color)
+        )
+        .map_err(write_error)?;
+    }
+
+    // Legend entries of the areas with their latest percentage
+    let mut legend: Vec<(String, egui::Color32, bool)> = [
+        ("Infected", egui::Color32::RED, &simulation.infected_chart),
+        (
+            "Susceptible",
+            egui::Color32::BLUE,
+            &simulation.susceptible_chart,
+        ),
+        (
+            "Recovered",
+            egui::Color32::GRAY,
+            &simulation.recovered_chart,
+        ),
+    ]
+    .into_iter()
+    .map(|(name, color, chart)| {
+        let last = chart.last().copied().unwrap_or(0.0);
+        (format!("{} ({:.1}%)", name, last), color, false)
+    })
+    .collect();
+    // ODE overlay, stacked like the areas
+    if simulation.ode_overlay_enabled {
+        let solution = simulation.ode_solution();
+        let overlays = [
+            ("ODE Infected", egui::Color32::from_rgb(200, 160, 0), false),
+            ("ODE Susceptible", egui::Color32::LIGHT_BLUE, true),
+        ];
+        for (name, color, add_susceptible) in overlays {
+            let points: Vec<String> = solution
+                .iter()
+                .map(|(time, state)| {
+                    let value = if add_susceptible {
+                        state.infected + state.susceptible
+                    } else {
+                        state.infected
+                    };
+                    format!("{:.2},{:.2}", x(*time), y(value * 100.0))
+                })
+                .collect();
+            writeln!(
+                out,
+                r#"<polyline points="{}" fill="none" stroke="{}" stroke-width="2" stroke-dasharray="6 4"/>"#,
+                points.join(" "),
+                svg_color(color)
+            )
+            .map_err(write_error)?;
+            legend.push((String::from(name), color, true));
+        }
+    }
+
+    // Axes with ticks and labels
+    let bottom = MARGIN_TOP + plot_height;
+    writeln!(
+        out,
+        r#"<rect x="{MARGIN_LEFT}" y="{MARGIN_TOP}" width="{plot_width}" height="{plot_height}" fill="none" stroke="black"/>"#
+    )
+    .map_err(write_error)?;
+    for percentage in (0..=100).step_by(20) {
+        let tick_y = y(percentage as f32);
+        writeln!(
+            out,
+            r#"<line x1="{:.2}" y1="{tick_y:.2}" x2="{MARGIN_LEFT}" y2="{tick_y:.2}" stroke="black"/><text x="{:.2}" y="{:.2}" text-anchor="end">{percentage}</text>"#,
+            MARGIN_LEFT - 5.0,
+            MARGIN_LEFT - 8.0,
+            tick_y + 4.0
+        )
+        .map_err(write_error)?;
+    }
+    let step = tick_step(end_time, TIME_TICKS);
+    let decimals = (-step.log10().floor()).max(0.0) as usize;
+    let tick_count = (end_time / step + 1e-3).floor() as usize;
+    for i in 0..=tick_count {
+        let tick = i as f32 * step;
+        let tick_x = x(tick);
+        writeln!(
+            out,
+            r#"<line x1="{tick_x:.2}" y1="{bottom:.2}" x2="{tick_x:.2}" y2="{:.2}" stroke="black"/><text x="{tick_x:.2}" y="{:.2}" text-anchor="middle">{tick:.decimals$}</text>"#,
+            bottom + 5.0,
+            bottom + 18.0,
+        )
+        .map_err(write_error)?;
+    }
+    writeln!(
+        out,
+        r#"<text x="{:.2}" y="{:.2}" text-anchor="middle" font-size="14">Time (s)</text>"#,
+        MARGIN_LEFT + plot_width / 2.0,
+        bottom + 38.0
+    )
+    .map_err(write_error)?;
+    writeln!(
+        out,
+        r#"<text transform="translate(20 {:.2}) rotate(-90)" text-anchor="middle" font-size="14">Percentage of people</text>"#,
+        MARGIN_TOP + plot_height / 2.0
+    )
+    .map_err(write_error)?;
+
+    // Legend
+    let legend_x = MARGIN_LEFT + plot_width + 15.0;
+    for (i, (name, color, dashed)) in legend.iter().enumerate() {
+        let entry_y = MARGIN_TOP + 10.0 + i as f32 * 22.0;
+        if *dashed {
+            writeln!(
+                out,
+                r#"<line x1="{legend_x:.2}" y1="{entry_y:.2}" x2="{:.2}" y2="{entry_y:.2}" stroke="{}" stroke-width="2" stroke-dasharray="6 4"/>"#,
+                legend_x + 24.0,
+                svg_color(*color)
+            )
+            .map_err(write_error)?;
+        } else {
+            writeln!(
+                out,
+                r#"<rect x="{legend_x:.2}" y="{:.2}" width="24" height="12" fill="{}" fill-opacity="0.6" stroke="{}"/>"#,
+                entry_y - 6.0,
+                svg_color(*color),
+                svg_color(*color)
+            )
+            .map_err(write_error)?;
+        }
+        writeln!(
+            out,
+            r#"<text x="{:.2}" y="{:.2}">{}</text>"#,
+            legend_x + 30.0,
+            entry_y + 4.0,
+            name
+        )
+        .map_err(write_error)?;
+    }
+
+    // Caption with the run parameters
+    let settings = simulation.run_settings();
+    let on_off = |enabled: bool| if enabled { "on" } else { "off" };
+    let caption = [
+        format!(
+            "Seed {}, {} people, {} initially infected, infected radius {}, infection probability {}, recovery after {} s.",
+            simulation.seed,
+            settings.community_size,
+            settings.initial_infected_people,
+            settings.infected_radius,
+            settings.infection_probability,
+            settings.recovered_day
+        ),
+        format!(
+            "Social distancing {} (radius {}), quarantine {} (after {} s), superspreading {} (k = {}), variants {}.",
+            on_off(settings.social_distancing_enabled),
+            settings.social_distancing_radius,
+            on_off(settings.quarantine_enabled),
+            settings.infection_time_before_quarantine,
+            on_off(settings.superspreading_enabled),
+            settings.dispersion_k,
+            on_off(settings.variants_enabled)
+        ),
+    ];
+    for (i, line) in caption.iter().enumerate() {
+        writeln!(
+            out,
+            r#"<text x="{MARGIN_LEFT}" y="{:.2}" font-size="11" fill="rgb(60,60,60)">{}</text>"#,
+            bottom + 64.0 + i as f32 * 16.0,
+            line
+        )
+        .map_err(write_error)?;
+    }
+    writeln!(out, "</svg>").map_err(write_error)
+}
+
+/// Saves the SIR chart of a simulation to an SVG file.
+///
+/// # Parameters
+/// - `path`: Path of an `.svg` file
+/// - `simulation`: The simulation whose recorded series is drawn
+pub fn save_svg(path: impl AsRef<Path>, simulation: &Simulation) -> Result<(), ExportError> {
+    let path = path.as_ref();
+    if path.extension().and_then(|extension| extension.to_str()) != Some("svg") {
+        return Err(ExportError::UnsupportedFormat(
+            path.display().to_string(),
+            ".svg",
+        ));
+    }
+    let mut text = String::new();
+    write_svg(simulation, &mut text)?;
+    fs::write(path, text).map_err(|error| ExportError::Io(path.display().to_string(), error))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::settings::Settings;
+
+    /// Tests tick spacings of 1, 2 or 5 times a power of ten.
+    #[test]
+    fn test_tick_step() {
+        assert_eq!(tick_step(60.0, 8.0), 10.0);
+        assert_eq!(tick_step(10.0, 8.0), 2.0);
+        assert_eq!(tick_step(3.0, 8.0), 0.5);
+    }
+
+    /// Tests that the SVG has the three areas, the overlay, the legend and the caption.
+    #[test]
+    fn test_write_svg() {
+        let mut simulation = Simulation::with_settings(Settings {
+            seed: Some(8),
+            ..Settings::default()
+        });
+        simulation.ode_overlay_enabled = true;
+        for _ in 0..60 {
+            simulation.step(simulation.settings.time_step);
+        }
+        // Settings changed in the UI but not applied are not in the caption
+        simulation.community_size = 120;
+        let mut svg = String::new();
+        write_svg(&simulation, &mut svg).unwrap();
+        assert!(svg.starts_with("<svg"));
+        assert!(svg.trim_end().ends_with("</svg>"));
+        assert_eq!(svg.matches("<polygon").count(), 3);
+        assert_eq!(svg.matches("<polyline").count(), 2);
+        assert!(svg.contains("Time (s)"));
+        assert!(svg.contains("ODE Infected"));
+        assert!(svg.contains("Seed 8, 80 people"));
+        assert!(save_svg("chart.png", &simulation).is_err());
+    }
+}
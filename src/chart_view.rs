@@ -0,0 +1,407 @@
+//! Chart view module for the different ways of showing the epidemic curve.
+//!
+//! The SIR chart can show:
+//! - The stacked areas of infected, susceptible and recovered people
+//! - One unstacked line per compartment
+//! - The incidence: new infections in each second (a "day" of the simulation)
+//! - The cumulative number of cases
+//!
+//! Every view can show absolute counts instead of percentages of the community and,
+//! except the stacked areas, use a logarithmic y-axis. The early exponential growth is
+//! a straight line on a logarithmic axis.
+
+use eframe::egui::Color32;
+
+use crate::infection_log::InfectionLog;
+use crate::ode::SirState;
+use crate::simulation::Simulation;
+
+/// Width (in seconds) of the time bins of the incidence view.
+pub const INCIDENCE_BIN: f32 = 1.0;
+
+/// What the SIR chart shows.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ChartView {
+    #[default]
+    Stacked,
+    Lines,
+    Incidence,
+    Cumulative,
+}
+
+impl ChartView {
+    /// All views in the order they are shown in the UI.
+    pub const ALL: [ChartView; 4] = [
+        ChartView::Stacked,
+        ChartView::Lines,
+        ChartView::Incidence,
+        ChartView::Cumulative,
+    ];
+
+    /// Returns the name shown in the UI.
+    pub fn name(self) -> &'static str {
+        match self {
+            ChartView::Stacked => "Stacked",
+            ChartView::Lines => "Lines",
+            ChartView::Incidence => "Incidence",
+            ChartView::Cumulative => "Cumulative",
+        }
+    }
+
+    /// Returns `true` when the view can use a logarithmic y-axis.
+    ///
+    /// Stacked areas have no meaning on a logarithmic axis.
+    pub fn supports_log_scale(self) -> bool {
+        self != ChartView::Stacked
+    }
+}
+
+/// One line of the SIR chart.
+///
+/// # Fields
+/// - `name`: Name shown in the legend, with the latest value
+/// - `color`: Color of the line
+/// - `points`: Time and value of each point, with the value already on the y-axis scale
+/// - `fill`: Determine whether the area under the line is filled
+/// - `dashed`: Determine whether the line is dashed (used for the ODE overlay)
+#[derive(Clone, Debug, PartialEq)]
+pub struct ChartSeries {
+    pub name: String,
+    pub color: Color32,
+    pub points: Vec<[f64; 2]>,
+    pub fill: bool,
+    pub dashed: bool,
+}
+
+/// Counts the infections in each complete time bin.
+///
+/// # Parameters
+/// - `log`: The infection log of the run
+/// - `end_time`: Current simulation time (in seconds)
+///
+/// # Returns
+/// The start time and number of infections of each bin (infections in the current bin
+/// are left out until it is complete)
+pub fn incidence(log: &InfectionLog, end_time: f32) -> Vec<(f32, usize)> {
+    let bin_count = (end_time / INCIDENCE_BIN).floor() as usize;
+    let mut counts = vec![0; bin_count];
+    for event in &log.events {
+        if let Some(count) = counts.get_mut((event.time / INCIDENCE_BIN) as usize) {
+            *count += 1;
+        }
+    }
+    counts
+        .into_iter()
+        .enumerate()
+        .map(|(bin, count)| (bin as f32 * INCIDENCE_BIN, count))
+        .collect()
+}
+
+/// Counts the infections until each time.
+///
+/// Reinfections are counted again, so the count can exceed the community size.
+///
+/// # Parameters
+/// - `log`: The infection log of the run, in the order the infections happened
+/// - `times`: Times (in seconds) to count at, in ascending order
+///
+/// # Returns
+/// The number of infections at or before each time
+pub fn cumulative_cases(log: &InfectionLog, times: &[f32]) -> Vec<usize> {
+    let mut count = 0;
+    times
+        .iter()
+        .map(|&time| {
+            count += log.events[count..]
+                .iter()
+                .take_while(|event| event.time <= time)
+                .count();
+            count
+        })
+        .collect()
+}
+
+/// Formats a mark of a logarithmic y-axis.
+///
+/// # Parameters
+/// - `exponent`: The decimal logarithm of the value
+///
+/// # Returns
+/// The value with as many decimals as needed below one
+pub fn log_axis_label(exponent: f64) -> String {
+    let decimals = (-exponent.floor()).max(0.0) as usize;
+    format!("{:.*}", decimals, 10f64.powf(exponent))
+}
+
+/// Builds the lines of the SIR chart for the selected view.
+///
+/// # Parameters
+/// - `simulation`: The simulation with the chart history and the view settings
+/// - `ode_solution`: The SIR ODE solution to overlay, if enabled
+///
+/// # Returns
+/// The lines to draw, in drawing order
+pub fn chart_series(
+    simulation: &Simulation,
+    ode_solution: Option<&[(f32, SirState)]>,
+) -> Vec<ChartSeries> {
+    let counts = simulation.chart_counts;
+    let log_scale = simulation.chart_log_scale && simulation.chart_view.supports_log_scale();
+    let total_people = simulation.community.len().max(1) as f32;
+    // Percentages are converted to counts and counts to percentages with this factor
+    let people_per_percent = total_people / 100.0;
+    let scale = |count: f32| {
+        if counts {
+            count
+        } else {
+            count / people_per_percent
+        }
+    };
+    let format_value = |value: f32| {
+        if counts {
+            format!("{:.0}", value)
+        } else {
+            format!("{:.1}%", value)
+        }
+    };
+    let series = |name: String, color: Color32, values: Vec<(f32, f32)>, fill: bool| {
+        let points = values
+            .into_iter()
+            .filter_map(|(time, value)| {
+                let value = if log_scale {
+                    (value > 0.0).then(|| value.log10())?
+                } else {
+                    value
+                };
+                Some([time as f64, value as f64])
+            })
+            .collect();
+        ChartSeries {
+            name,
+            color,
+            points,
+            fill,
+            dashed: false,
+        }
+    };
+
+    let times = &simulation.total_time;
+    let compartment = |index: usize| -> Vec<f32> {
+        if counts {
+            simulation
+                .state_counts
+                .iter()
+                .map(|&(susceptible, infected, recovered)| {
+                    [susceptible, infected, recovered][index] as f32
+                })
+                .collect()
+        } else {
+            [
+                &simulation.susceptible_chart,
+                &simulation.infected_chart,
+                &simulation.recovered_chart,
+            ][index]
+                .clone()
+        }
+    };
+
+    let mut lines = Vec::new();
+    match simulation.chart_view {
+        ChartView::Stacked | ChartView::Lines => {
+            let stacked = simulation.chart_view == ChartView::Stacked;
+            let (susceptible, infected, recovered) =
+                (compartment(0), compartment(1), compartment(2));
+            let mut below = vec![0.0; times.len()];
+            for (values, color, label) in [
+                (infected, Color32::RED, "infected"),
+                (susceptible, Color32::BLUE, "Susceptible"),
+                (recovered, Color32::GRAY, "Recovered"),
+            ] {
+                let last = values.last().copied().unwrap_or(0.0);
+                let values: Vec<(f32, f32)> = times
+                    .iter()
+                    .zip(values.iter().zip(below.iter_mut()))
+                    .map(|(&time, (&value, below))| {
+                        if stacked {
+                            *below += value;
+                            (time, *below)
+                        } else {
+                            (time, value)
+                        }
+                    })
+                    .collect();
+                lines.push(series(
+                    format!("{} {}", format_value(last), label),
+                    color,
+                    values,
+                    stacked,
+                ));
+            }
+
+            if let Some(solution) = ode_solution {
+                let ode_value = |fraction: f32| scale(fraction * total_people);
+                let ode_infected = solution
+                    .iter()
+                    .map(|(time, state)| (*time, ode_value(state.infected)))
+                    .collect();
+                // Stacked like the areas: infected, then susceptible above it
+                let ode_susceptible = solution
+                    .iter()
+                    .map(|(time, state)| {
+                        let below = if stacked { state.infected } else { 0.0 };
+                        (*time, ode_value(below + state.susceptible))
+                    })
+                    .collect();
+                for (name, color, values) in [
+                    ("ODE Infected", Color32::YELLOW, ode_infected),
+                    ("ODE Susceptible", Color32::LIGHT_BLUE, ode_susceptible),
+                ] {
+                    let mut line = series(name.to_string(), color, values, false);
+                    line.dashed = true;
+                    lines.push(line);
+                }
+            }
+        }
+        ChartView::Incidence => {
+            let bins = incidence(&simulation.infection_log, simulation.elapsed_time);
+            let last = bins.last().map_or(0.0, |&(_, count)| scale(count as f32));
+            let values = bins
+                .into_iter()
+                .map(|(start, count)| (start + INCIDENCE_BIN / 2.0, scale(count as f32)))
+                .collect();
+            lines.push(series(
+                format!("{} new infections per second", format_value(last)),
+                Color32::RED,
+                values,
+                false,
+            ));
+        }
+        ChartView::Cumulative => {
+            let cases = cumulative_cases(&simulation.infection_log, times);
+            let last = cases.last().map_or(0.0, |&count| scale(count as f32));
+            let values = times
+                .iter()
+                .zip(cases)
+                .map(|(&time, count)| (time, scale(count as f32)))
+                .collect();
+            lines.push(series(
+                format!("{} cumulative cases", format_value(last)),
+                Color32::DARK_RED,
+                values,
+                true,
+            ));
+        }
+    }
+    lines
+}
+
+/// Returns the y-axis label of the SIR chart for the selected view.
+///
+/// # Parameters
+/// - `simulation`: The simulation with the view settings
+pub fn y_axis_label(simulation: &Simulation) -> String {
+    let unit = if simulation.chart_counts {
+        "People"
+    } else {
+        "Percentage"
+    };
+    let scale = if simulation.chart_log_scale && simulation.chart_view.supports_log_scale() {
+        " (log scale)"
+    } else {
+        ""
+    };
+    match simulation.chart_view {
+        ChartView::Incidence => format!(
+            "New infections per second ({}){}",
+            unit.to_lowercase(),
+            scale
+        ),
+        _ => format!("{}{}", unit, scale),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::infection_log::{InfectionEvent, InfectionLocation};
+    use crate::settings::Settings;
+
+    fn log(times: &[f32]) -> InfectionLog {
+        InfectionLog {
+            events: times
+                .iter()
+                .enumerate()
+                .map(|(person, &time)| InfectionEvent {
+                    person,
+                    infector: None,
+                    time,
+                    strain: 0,
+                    location: InfectionLocation::Community,
+                    infector_infection_age: None,
+                })
+                .collect(),
+        }
+    }
+
+    /// Tests that incidence counts infections in complete bins and cumulative cases add up.
+    #[test]
+    fn test_incidence_and_cumulative() {
+        let log = log(&[0.0, 0.0, 0.5, 1.2, 2.9, 3.1]);
+        assert_eq!(incidence(&log, 3.05), vec![(0.0, 3), (1.0, 1), (2.0, 1)]);
+        assert_eq!(
+            cumulative_cases(&log, &[0.0, 1.0, 2.0, 3.0, 4.0]),
+            vec![2, 3, 4, 5, 6]
+        );
+    }
+
+    /// Tests that log-scale marks are formatted with the needed decimals.
+    #[test]
+    fn test_log_axis_label() {
+        assert_eq!(log_axis_label(2.0), "100");
+        assert_eq!(log_axis_label(0.0), "1");
+        assert_eq!(log_axis_label(-1.0), "0.1");
+        assert_eq!(log_axis_label(-2.0), "0.01");
+    }
+
+    /// Tests that the views give the expected lines in counts and on a log scale.
+    #[test]
+    fn test_chart_series() {
+        let mut simulation = Simulation::with_settings(Settings {
+            seed: Some(6),
+            ..Settings::default()
+        });
+        for _ in 0..200 {
+            simulation.step(simulation.settings.time_step);
+        }
+
+        // Stacked percentages add up to 100
+        let stacked = chart_series(&simulation, None);
+        assert_eq!(stacked.len(), 3);
+        let top = stacked[2].points.last().unwrap()[1];
+        assert!((top - 100.0).abs() < 1e-3);
+
+        // Unstacked counts match the state counts
+        simulation.chart_view = ChartView::Lines;
+        simulation.chart_counts = true;
+        let lines = chart_series(&simulation, None);
+        let (_, infected, _) = *simulation.state_counts.last().unwrap();
+        assert_eq!(lines[0].points.last().unwrap()[1], infected as f64);
+        assert!(lines.iter().all(|line| !line.fill));
+
+        // Cumulative cases on a log scale
+        simulation.chart_view = ChartView::Cumulative;
+        simulation.chart_log_scale = true;
+        let cumulative = chart_series(&simulation, None);
+        let cases = simulation.infection_log.events.len() as f64;
+        assert!((cumulative[0].points.last().unwrap()[1] - cases.log10()).abs() < 1e-4);
+
+        // Incidence has one point per complete second
+        simulation.chart_view = ChartView::Incidence;
+        simulation.chart_log_scale = false;
+        let incidence = chart_series(&simulation, None);
+        assert_eq!(
+            incidence[0].points.len(),
+            simulation.elapsed_time.floor() as usize
+        );
+    }
+}
@@ -0,0 +1,347 @@
+//! Command line module for the simulation.
+//!
+//! Parses the command line arguments which choose the configuration file, the
+//! scenario file and whether the simulation runs without a window.
+
+use std::path::PathBuf;
+
+use crate::frame_export;
+use crate::sweep::{self, SweepAxis};
+
+/// Usage text printed with `--help` and after an invalid argument.
+pub const USAGE: &str = "\
+Usage: Epidemic-Spread-Simulation [OPTIONS]
+
+Options:
+  --config <path>      Load the settings from a TOML or JSON file
+  --scenario <path>    Play back a TOML or JSON scenario file
+  --headless           Run without a window and print CSV to standard output
+  --duration <secs>    Length of a headless run (default: scenario duration or 60)
+  --seed <seed>        Seed of the random number generator
+  --export <path>      Save the S/I/R data of a headless run to a .csv or .json file
+  --export-log <path>  Save the infection log of a headless run to a .csv or .json file
+                       (not with --ensemble or --sweep)
+  --export-chart <path>
+                       Save the S/I/R chart of a headless run to an .svg file
+  --ensemble <runs>    With --headless, run an ensemble with seeds from --seed upwards,
+                       print the median and bands as CSV and save the summary of each
+                       run with --export
+  --sweep <name=start:end:steps>
+                       With --headless, sweep a setting over evenly spaced values
+                       (give it twice for a grid of two settings), print the attack
+                       rate and peak prevalence of each cell as CSV and save the
+                       table with --export
+  --seeds <n>          Number of seeds in each cell of a sweep (default: 5)
+  --frames <dir>       Save PNG frames of the community view of a headless run
+  --gif <path>         Save an animated GIF of the community view of a headless run
+  --frame-interval <secs>
+                       Simulation time between two frames (default: 0.1)
+  --snapshot <path>    Resume the simulation from a .json snapshot
+  --save-snapshot <path>
+                       Save a snapshot at the end of a headless run
+  --help               Print this help";
+
+/// Default length (in seconds) of a headless run without a scenario duration.
+pub const DEFAULT_HEADLESS_DURATION: f32 = 60.0;
+
+/// Options given on the command line.
+///
+/// # Fields
+/// - `config`: Path of the configuration file, if given
+/// - `scenario`: Path of the scenario file, if given
+/// - `headless`: Determine whether the simulation runs without a window
+/// - `duration`: Length (in seconds) of a headless run, if given
+/// - `seed`: Seed of the random number generator, if given
+/// - `export`: Path of the file the S/I/R data of a headless run is saved to, if given
+/// - `export_log`: Path of the file the infection log of a headless run is saved to, if given
+/// - `export_chart`: Path of the SVG file the chart of a headless run is saved to, if given
+/// - `ensemble`: Number of runs of a headless ensemble, if given
+/// - `sweep`: Swept parameters of a headless sweep (empty without a sweep)
+/// - `seeds`: Number of seeds in each cell of a sweep
+/// - `frames`: Directory the PNG frames of a headless run are saved to, if given
+/// - `gif`: Path of the animated GIF of a headless run, if given
+/// - `frame_interval`: Simulation time (in seconds) between two frames
+/// - `snapshot`: Path of the snapshot the simulation resumes from, if given
+/// - `save_snapshot`: Path of the file a snapshot is saved to after a headless run, if given
+/// - `help`: Determine whether only the usage is printed
+#[derive(Debug, PartialEq)]
+pub struct CliOptions {
+    pub config: Option<PathBuf>,
+    pub scenario: Option<PathBuf>,
+    pub headless: bool,
+    pub duration: Option<f32>,
+    pub seed: Option<u64>,
+    pub export: Option<PathBuf>,
+    pub export_log: Option<PathBuf>,
+    pub export_chart: Option<PathBuf>,
+    pub ensemble: Option<usize>,
+    pub sweep: Vec<SweepAxis>,
+    pub seeds: usize,
+    pub frames: Option<PathBuf>,
+    pub gif: Option<PathBuf>,
+    pub frame_interval: f32,
+    pub snapshot: Option<PathBuf>,
+    pub save_snapshot: Option<PathBuf>,
+    pub help: bool,
+}
+
+impl Default for CliOptions {
+    fn default() -> Self {
+        CliOptions {
+            config: None,
+            scenario: None,
+            headless: false,
+            duration: None,
+            seed: None,
+            export: None,
+            export_log: None,
+            export_chart: None,
+            ensemble: None,
+            sweep: Vec::new(),
+            seeds: sweep::SWEEP_SEEDS,
+            frames: None,
+            gif: None,
+            frame_interval: frame_export::FRAME_INTERVAL,
+            snapshot: None,
+            save_snapshot: None,
+            help: false,
+        }
+    }
+}
+
+/// Parses the command line arguments (without the program name).
+///
+/// # Parameters
+/// - `args`: The arguments to parse
+///
+/// # Returns
+/// The parsed options, or an error message for a missing, invalid or unknown argument
+pub fn parse_args(args: impl IntoIterator<Item = String>) -> Result<CliOptions, String> {
+    let mut args = args.into_iter();
+    let mut options = CliOptions::default();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--config" => options.config = Some(PathBuf::from(value(&mut args, &arg)?)),
+            "--scenario" => options.scenario = Some(PathBuf::from(value(&mut args, &arg)?)),
+            "--headless" => options.headless = true,
+            "--duration" => {
+                let duration = value(&mut args, &arg)?;
+                match duration.parse::<f32>() {
+                    Ok(duration) if duration > 0.0 => options.duration = Some(duration),
+                    _ => return Err(format!("invalid duration '{}'", duration)),
+                }
+            }
+            "--seed" => {
+                let seed = value(&mut args, &arg)?;
+                options.seed = Some(
+                    seed.parse()
+                        .map_err(|_| format!("invalid seed '{}'", seed))?,
+                );
+            }
+            "--export" => options.export = Some(PathBuf::from(value(&mut args, &arg)?)),
+            "--export-log" => options.export_log = Some(PathBuf::from(value(&mut args, &arg)?)),
+            "--export-chart" => options.export_chart = Some(PathBuf::from(value(&mut args, &arg)?)),
+            "--ensemble" => {
+                let runs = value(&mut args, &arg)?;
+                match runs.parse::<usize>() {
+                    Ok(runs) if runs > 0 => options.ensemble = Some(runs),
+                    _ => return Err(format!("invalid number of runs '{}'", runs)),
+                }
+            }
+            "--sweep" => {
+                if options.sweep.len() == 2 {
+                    return Err(String::from("--sweep can be given at most twice"));
+                }
+                options
+                    .sweep
+                    .push(SweepAxis::parse(&value(&mut args, &arg)?)?);
+            }
+            "--seeds" => {
+                let seeds = value(&mut args, &arg)?;
+                match seeds.parse::<usize>() {
+                    Ok(seeds) if seeds > 0 => options.seeds = seeds,
+                    _ => return Err(format!("invalid number of seeds '{}'", seeds)),
+                }
+            }
+            "--frames" => options.frames = Some(PathBuf::from(value(&mut args, &arg)?)),
+            "--gif" => options.gif = Some(PathBuf::from(value(&mut args, &arg)?)),
+            "--frame-interval" => {
+                let interval = value(&mut args, &arg)?;
+                match interval.parse::<f32>() {
+                    Ok(interval) if interval > 0.0 => options.frame_interval = interval,
+                    _ => return Err(format!("invalid frame interval '{}'", interval)),
+                }
+            }
+            "--snapshot" => options.snapshot = Some(PathBuf::from(value(&mut args, &arg)?)),
+            "--save-snapshot" => {
+                options.save_snapshot = Some(PathBuf::from(value(&mut args, &arg)?))
+            }
+            "--help" | "-h" => options.help = true,
+            _ => return Err(format!("unknown argument '{}'", arg)),
+        }
+    }
+    if options.ensemble.is_some() && !options.headless {
+        return Err(String::from("--ensemble requires --headless"));
+    }
+    if !options.sweep.is_empty() && !options.headless {
+        return Err(String::from("--sweep requires --headless"));
+    }
+    let exports_frames = options.frames.is_some() || options.gif.is_some();
+    if exports_frames && !options.headless {
+        return Err(String::from("--frames and --gif require --headless"));
+    }
+    if exports_frames && (options.ensemble.is_some() || !options.sweep.is_empty()) {
+        return Err(String::from(
+            "--frames and --gif cannot be used with --ensemble or --sweep",
+        ));
+    }
+    if options.export.is_some() && !options.headless {
+        return Err(String::from("--export requires --headless"));
+    }
+    if options.export_log.is_some() && !options.headless {
+        return Err(String::from("--export-log requires --headless"));
+    }
+    if options.export_log.is_some() && (options.ensemble.is_some() || !options.sweep.is_empty()) {
+        return Err(String::from(
+            "--export-log cannot be used with --ensemble or --sweep",
+        ));
+    }
+    if options.export_chart.is_some() && !options.headless {
+        return Err(String::from("--export-chart requires --headless"));
+    }
+    if options.save_snapshot.is_some() && !options.headless {
+        return Err(String::from("--save-snapshot requires --headless"));
+    }
+    if options.snapshot.is_some() && options.scenario.is_some() {
+        return Err(String::from(
+            "--snapshot and --scenario cannot be used together",
+        ));
+    }
+    if options.ensemble.is_some() && !options.sweep.is_empty() {
+        return Err(String::from(
+            "--ensemble and --sweep cannot be used together",
+        ));
+    }
+    Ok(options)
+}
+
+/// Takes the value of an option from the remaining arguments.
+///
+/// # Parameters
+/// - `args`: The remaining arguments
+/// - `option`: Name of the option, used in the error message
+///
+/// # Returns
+/// The value, or an error message if it is missing
+fn value(args: &mut impl Iterator<Item = String>, option: &str) -> Result<String, String> {
+    args.next()
+        .ok_or_else(|| format!("{} requires a value", option))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(list: &[&str]) -> Vec<String> {
+        list.iter().map(|arg| arg.to_string()).collect()
+    }
+
+    /// Tests that all options are parsed.
+    #[test]
+    fn test_parse_args() {
+        let options = parse_args(args(&[
+            "--config",
+            "settings.toml",
+            "--scenario",
+            "lockdown.toml",
+            "--headless",
+            "--duration",
+            "30",
+            "--seed",
+            "42",
+            "--export",
+            "run.json",
+            "--export-log",
+            "log.csv",
+            "--export-chart",
+            "chart.svg",
+        ]))
+        .unwrap();
+        assert_eq!(
+            options,
+            CliOptions {
+                config: Some(PathBuf::from("settings.toml")),
+                scenario: Some(PathBuf::from("lockdown.toml")),
+                headless: true,
+                duration: Some(30.0),
+                seed: Some(42),
+                export: Some(PathBuf::from("run.json")),
+                export_log: Some(PathBuf::from("log.csv")),
+                export_chart: Some(PathBuf::from("chart.svg")),
+                ensemble: None,
+                sweep: Vec::new(),
+                seeds: sweep::SWEEP_SEEDS,
+                frames: None,
+                gif: None,
+                frame_interval: frame_export::FRAME_INTERVAL,
+                snapshot: None,
+                save_snapshot: None,
+                help: false,
+            }
+        );
+    }
+
+    /// Tests that up to two swept parameters are parsed.
+    #[test]
+    fn test_parse_args_sweep() {
+        let options = parse_args(args(&[
+            "--headless",
+            "--sweep",
+            "infected_radius=2:10:5",
+            "--sweep",
+            "social_distancing_radius=0:20:3",
+            "--seeds",
+            "8",
+        ]))
+        .unwrap();
+        assert_eq!(options.sweep.len(), 2);
+        assert_eq!(options.sweep[1].values, vec![0.0, 10.0, 20.0]);
+        assert_eq!(options.seeds, 8);
+        assert!(parse_args(args(&["--sweep", "infected_radius=2:10:5"])).is_err());
+    }
+
+    /// Tests that missing values, invalid values and unknown arguments are errors.
+    #[test]
+    fn test_parse_args_errors() {
+        assert!(parse_args(args(&["--config"])).is_err());
+        assert!(parse_args(args(&["--duration", "-1"])).is_err());
+        assert!(parse_args(args(&["--seed", "abc"])).is_err());
+        assert!(parse_args(args(&["--fast"])).is_err());
+        assert!(parse_args(args(&["--ensemble", "10"])).is_err());
+        assert!(parse_args(args(&["--headless", "--ensemble", "0"])).is_err());
+        assert!(parse_args(args(&["--save-snapshot", "end.json"])).is_err());
+        assert!(parse_args(args(&["--gif", "run.gif"])).is_err());
+        assert!(parse_args(args(&["--headless", "--frame-interval", "0"])).is_err());
+        assert!(parse_args(args(&["--snapshot", "a.json", "--scenario", "b.toml"])).is_err());
+        assert!(
+            parse_args(args(&[
+                "--headless",
+                "--ensemble",
+                "10",
+                "--export-log",
+                "log.csv"
+            ]))
+            .is_err()
+        );
+    }
+
+    /// Tests that the export options are rejected without a headless run.
+    #[test]
+    fn test_parse_args_exports_require_headless() {
+        assert!(parse_args(args(&["--export", "run.csv"])).is_err());
+        assert!(parse_args(args(&["--export-log", "log.csv"])).is_err());
+        assert!(parse_args(args(&["--export-chart", "chart.svg"])).is_err());
+        let options = parse_args(args(&["--headless", "--export", "run.csv"])).unwrap();
+        assert_eq!(options.export, Some(PathBuf::from("run.csv")));
+    }
+}
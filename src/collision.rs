@@ -0,0 +1,292 @@
+//! Collision module for people bouncing off each other.
+//!
+//! People are disks with radius `person_radius`. When collisions are enabled, two
+//! people who overlap are pushed apart and, if they move towards each other, exchange
+//! the parts of their velocities along the line between them, like billiard balls of
+//! equal mass. Checking every pair would take quadratic time, so people are sorted into
+//! a grid of cells as large as a person and only people in neighbouring cells are
+//! compared.
+
+use crate::person::Person;
+use crate::settings::Settings;
+
+/// People sorted into square cells by position.
+///
+/// # Fields
+/// - `cell_size`: Width and height of each cell
+/// - `columns`, `rows`: Number of cells in each direction
+/// - `cells`: Indexes of the people in each cell, row by row
+#[derive(Clone, Debug, PartialEq)]
+pub struct SpatialGrid {
+    pub cell_size: f32,
+    pub columns: usize,
+    pub rows: usize,
+    pub cells: Vec<Vec<usize>>,
+}
+
+impl SpatialGrid {
+    /// Sorts people into a grid covering an area.
+    ///
+    /// People outside the area are sorted into the nearest cell.
+    ///
+    /// # Parameters
+    /// - `width`, `height`: Size of the area
+    /// - `cell_size`: Width and height of each cell
+    /// - `people`: Index and position `(index, x, y)` of each person
+    ///
+    /// # Returns
+    /// The grid with every person in one cell
+    pub fn new(
+        width: f32,
+        height: f32,
+        cell_size: f32,
+        people: impl IntoIterator<Item = (usize, f32, f32)>,
+    ) -> Self {
+        let columns = ((width / cell_size).ceil() as usize).max(1);
+        let rows = ((height / cell_size).ceil() as usize).max(1);
+        let mut cells = vec![Vec::new(); columns * rows];
+        for (index, x, y) in people {
+            let column = ((x / cell_size).max(0.0) as usize).min(columns - 1);
+            let row = ((y / cell_size).max(0.0) as usize).min(rows - 1);
+            cells[row * columns + column].push(index);
+        }
+        SpatialGrid {
+            cell_size,
+            columns,
+            rows,
+            cells,
+        }
+    }
+
+    /// Lists the pairs of people in the same or neighbouring cells.
+    ///
+    /// Each pair is listed once. People further apart than one cell are never listed,
+    /// so with cells as large as the collision distance no colliding pair is missed.
+    ///
+    /// # Returns
+    /// The pairs of people who may be close to each other
+    pub fn candidate_pairs(&self) -> Vec<(usize, usize)> {
+        let mut pairs = Vec::new();
+        for row in 0..self.rows {
+            for column in 0..self.columns {
+                let cell = &self.cells[row * self.columns + column];
+                for (i, &first) in cell.iter().enumerate() {
+                    for &second in &cell[i + 1..] {
+                        pairs.push((first, second));
+                    }
+                }
+                // Right, below-left, below and below-right, so each pair of cells is visited once
+                let neighbours = [(0, 1), (1, -1), (1, 0), (1, 1)];
+                for (row_offset, column_offset) in neighbours {
+                    let other_row = row + row_offset;
+                    let Some(other_column) = column.checked_add_signed(column_offset) else {
+                        continue;
+                    };
+                    if other_row >= self.rows || other_column >= self.columns {
+                        continue;
+                    }
+                    let other = &self.cells[other_row * self.columns + other_column];
+                    for &first in cell {
+                        for &second in other {
+                            pairs.push((first, second));
+                        }
+                    }
+                }
+            }
+        }
+        pairs
+    }
+}
+
+/// Resolves a collision between two people.
+///
+/// Overlapping people are pushed apart equally until they just touch. If they move
+/// towards each other, they exchange the components of their velocities along the
+/// line between them (an elastic collision of equal masses).
+///
+/// # Parameters
+/// - `first`, `second`: The two people
+/// - `radius`: Radius of each person
+///
+/// # Returns
+/// `true` if the people overlapped
+pub fn collide(first: &mut Person, second: &mut Person, radius: f32) -> bool {
+    let (dx, dy) = (second.x - first.x, second.y - first.y);
+    let distance = dx.hypot(dy);
+    let minimum_distance = radius * 2.0;
+    if distance >= minimum_distance || distance == 0.0 {
+        return false;
+    }
+    let (normal_x, normal_y) = (dx / distance, dy / distance);
+
+    let push = (minimum_distance - distance) / 2.0;
+    first.x -= normal_x * push;
+    first.y -= normal_y * push;
+    second.x += normal_x * push;
+    second.y += normal_y * push;
+
+    let approach_speed = (first.velocity_x - second.velocity_x) * normal_x
+        + (first.velocity_y - second.velocity_y) * normal_y;
+    if approach_speed > 0.0 {
+        first.velocity_x -= approach_speed * normal_x;
+        first.velocity_y -= approach_speed * normal_y;
+        second.velocity_x += approach_speed * normal_x;
+        second.velocity_y += approach_speed * normal_y;
+    }
+    true
+}
+
+/// Resolves the collisions between all people.
+///
+/// People only collide with people in the same area, the community or the quarantine.
+/// Being pushed counts as a movement, so afterwards people bounce off the border and the
+/// walls from where they were before the pushes and cannot be pushed through a wall.
+///
+/// # Parameters
+/// - `community`: All people
+/// - `settings`: Settings with the person radius, area sizes and walls
+pub fn resolve_collisions(community: &mut [Person], settings: &Settings) {
+    let radius = settings.person_radius;
+    let previous: Vec<(f32, f32)> = community
+        .iter()
+        .map(|person| (person.x, person.y))
+        .collect();
+    for in_quarantine in [false, true] {
+        let (width, height) = settings.area_size(in_quarantine);
+        let people = community
+            .iter()
+            .enumerate()
+            .filter(|(_, person)| person.is_in_quarantine == in_quarantine)
+            .map(|(index, person)| (index, person.x, person.y));
+        let grid = SpatialGrid::new(width, height, radius * 2.0, people);
+        for (first, second) in grid.candidate_pairs() {
+            let (low, high) = (first.min(second), first.max(second));
+            let (head, tail) = community.split_at_mut(high);
+            collide(&mut head[low], &mut tail[0], radius);
+        }
+    }
+    for (person, &previous) in community.iter_mut().zip(&previous) {
+        if (person.x, person.y) != previous {
+            person.bounce(previous, settings);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::settings::{SIMULATION_AREA_SIZE, TIME_STEP};
+    use crate::wall::Wall;
+
+    fn person(x: f32, y: f32, velocity_x: f32, velocity_y: f32) -> Person {
+        let mut person = Person::new(&Settings::default(), &mut rand::thread_rng());
+        person.x = x;
+        person.y = y;
+        person.velocity_x = velocity_x;
+        person.velocity_y = velocity_y;
+        person
+    }
+
+    /// Tests that the grid lists neighbouring people once and leaves out distant people.
+    #[test]
+    fn test_candidate_pairs() {
+        let grid = SpatialGrid::new(
+            100.0,
+            100.0,
+            8.0,
+            [
+                (0, 10.0, 10.0),
+                (1, 17.0, 12.0),
+                (2, 60.0, 60.0),
+                (3, 9.0, 17.0),
+            ],
+        );
+        let mut pairs: Vec<(usize, usize)> = grid
+            .candidate_pairs()
+            .into_iter()
+            .map(|(a, b)| (a.min(b), a.max(b)))
+            .collect();
+        pairs.sort();
+        assert_eq!(pairs, vec![(0, 1), (0, 3), (1, 3)]);
+    }
+
+    /// Tests that a head-on collision exchanges velocities and separates the people.
+    #[test]
+    fn test_collide() {
+        let mut first = person(100.0, 50.0, 40.0, 0.0);
+        let mut second = person(106.0, 50.0, -20.0, 5.0);
+        assert!(collide(&mut first, &mut second, 4.0));
+        assert_eq!((first.velocity_x, first.velocity_y), (-20.0, 0.0));
+        assert_eq!((second.velocity_x, second.velocity_y), (40.0, 5.0));
+        assert!((first.calculate_distance(&second) - 8.0).abs() < 1e-4);
+
+        // Moving apart keeps the velocities
+        let mut first = person(100.0, 50.0, -10.0, 0.0);
+        let mut second = person(104.0, 50.0, 10.0, 0.0);
+        assert!(collide(&mut first, &mut second, 4.0));
+        assert_eq!((first.velocity_x, second.velocity_x), (-10.0, 10.0));
+
+        let mut far = person(200.0, 50.0, 0.0, 0.0);
+        assert!(!collide(&mut first, &mut far, 4.0));
+    }
+
+    /// Tests that a crowd pressed against a wall stays on its side and inside the area.
+    #[test]
+    fn test_crowd_against_wall() {
+        let settings = Settings {
+            walls: vec![Wall {
+                x1: 100.0,
+                y1: 0.0,
+                x2: 100.0,
+                y2: SIMULATION_AREA_SIZE,
+            }],
+            collisions_enabled: true,
+            ..Settings::default()
+        };
+        let mut community: Vec<Person> = (0..60)
+            .map(|i| {
+                let (row, column) = (i / 6, i % 6);
+                person(
+                    70.0 + column as f32 * 5.0,
+                    120.0 + row as f32 * 5.0,
+                    150.0,
+                    if row % 2 == 0 { 30.0 } else { -30.0 },
+                )
+            })
+            .collect();
+        let (width, height) = settings.area_size(false);
+        let margin = settings.margin_from_wall;
+        for _ in 0..300 {
+            for person in &mut community {
+                // Keep pushing the crowd towards the wall
+                person.velocity_x = person.velocity_x.abs().max(50.0);
+                person.update_position(TIME_STEP, &settings);
+            }
+            resolve_collisions(&mut community, &settings);
+            for person in &community {
+                assert!(
+                    person.x < 100.0,
+                    "person leaked through the wall at {}",
+                    person.x
+                );
+                assert!(person.x >= margin && person.x <= width - margin);
+                assert!(person.y >= margin && person.y <= height - margin);
+            }
+        }
+    }
+
+    /// Tests that only people in the same area collide.
+    #[test]
+    fn test_resolve_collisions() {
+        let mut community = vec![
+            person(100.0, 100.0, 10.0, 0.0),
+            person(105.0, 100.0, -10.0, 0.0),
+            person(100.0, 100.0, 10.0, 0.0),
+        ];
+        community[2].is_in_quarantine = true;
+        resolve_collisions(&mut community, &Settings::default());
+        assert_eq!(community[0].velocity_x, -10.0);
+        assert_eq!(community[1].velocity_x, 10.0);
+        assert_eq!((community[2].x, community[2].velocity_x), (100.0, 10.0));
+    }
+}
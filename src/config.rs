@@ -0,0 +1,514 @@
+//! Configuration file module for the simulation settings.
+//!
+//! Settings are stored as TOML (`.toml`) or JSON (`.json`) files. Every field is
+//! optional, missing fields keep their default value from the `settings` module.
+//! Loaded settings are validated so a wrong value gives a clear error message
+//! instead of a broken simulation.
+
+use std::fmt;
+use std::fs;
+use std::path::Path;
+
+use serde::de::DeserializeOwned;
+
+use crate::settings::Settings;
+
+/// Default configuration file shown in the UI.
+pub const DEFAULT_CONFIG_PATH: &str = "settings.toml";
+
+/// Supported configuration file formats.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ConfigFormat {
+    Toml,
+    Json,
+}
+
+impl ConfigFormat {
+    /// Finds the format of a configuration file from its extension.
+    ///
+    /// # Parameters
+    /// - `path`: Path of the configuration file
+    ///
+    /// # Returns
+    /// The format, or `ConfigError::UnsupportedFormat` for other extensions
+    pub fn from_path(path: &Path) -> Result<Self, ConfigError> {
+        match path.extension().and_then(|extension| extension.to_str()) {
+            Some("toml") => Ok(ConfigFormat::Toml),
+            Some("json") => Ok(ConfigFormat::Json),
+            _ => Err(ConfigError::UnsupportedFormat(path.display().to_string())),
+        }
+    }
+}
+
+/// Errors when loading or saving a configuration file.
+///
+/// # Variants
+/// - `UnsupportedFormat`: The file extension is not `.toml` or `.json`
+/// - `Io`: The file cannot be read or written
+/// - `Parse`: The file is not valid TOML/JSON or has unknown fields or wrong types
+/// - `Serialize`: The settings cannot be written in the format
+/// - `Invalid`: The values are out of range, with one message per problem
+#[derive(Debug)]
+pub enum ConfigError {
+    UnsupportedFormat(String),
+    Io(String, std::io::Error),
+    Parse(String),
+    Serialize(String),
+    Invalid(Vec<String>),
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigError::UnsupportedFormat(path) => write!(
+                f,
+                "unsupported configuration file '{}': use a .toml or .json file",
+                path
+            ),
+            ConfigError::Io(path, error) => write!(f, "cannot access '{}': {}", path, error),
+            ConfigError::Parse(message) => write!(f, "invalid configuration: {}", message),
+            ConfigError::Serialize(message) => {
+                write!(f, "cannot write configuration: {}", message)
+            }
+            ConfigError::Invalid(problems) => {
+                write!(f, "invalid configuration: {}", problems.join("; "))
+            }
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+/// Loads and validates settings from a configuration file.
+///
+/// # Parameters
+/// - `path`: Path of a `.toml` or `.json` configuration file
+///
+/// # Returns
+/// The validated settings or the error explaining what is wrong
+pub fn load_settings(path: impl AsRef<Path>) -> Result<Settings, ConfigError> {
+    let path = path.as_ref();
+    let format = ConfigFormat::from_path(path)?;
+    let text = fs::read_to_string(path)
+        .map_err(|error| ConfigError::Io(path.display().to_string(), error))?;
+    parse_settings(&text, format)
+}
+
+/// Saves settings to a configuration file.
+///
+/// # Parameters
+/// - `path`: Path of a `.toml` or `.json` configuration file
+/// - `settings`: The settings to save
+pub fn save_settings(path: impl AsRef<Path>, settings: &Settings) -> Result<(), ConfigError> {
+    let path = path.as_ref();
+    let format = ConfigFormat::from_path(path)?;
+    let text = format_settings(settings, format)?;
+    fs::write(path, text).map_err(|error| ConfigError::Io(path.display().to_string(), error))
+}
+
+/// Parses and validates settings from the text of a configuration file.
+///
+/// # Parameters
+/// - `text`: Content of the configuration file
+/// - `format`: Format of the content
+///
+/// # Returns
+/// The validated settings or the error explaining what is wrong
+pub fn parse_settings(text: &str, format: ConfigFormat) -> Result<Settings, ConfigError> {
+    let settings: Settings = parse_file(text, format, None)?;
+    validate_settings(&settings)?;
+    Ok(settings)
+}
+
+/// Keys of older files that set both the width and the height of a square area.
+const SQUARE_AREA_KEYS: [(&str, [&str; 2]); 2] = [
+    (
+        "simulation_area_size",
+        ["simulation_area_width", "simulation_area_height"],
+    ),
+    (
+        "quarantine_area_size",
+        ["quarantine_area_width", "quarantine_area_height"],
+    ),
+];
+
+/// Parses the text of a file with settings, accepting the keys of older files.
+///
+/// `simulation_area_size` and `quarantine_area_size` are replaced by the width and
+/// height they stand for, unless the file sets the width or height itself.
+///
+/// # Parameters
+/// - `text`: Content of the file
+/// - `format`: Format of the content
+/// - `settings_key`: Key of the settings table, or `None` if the settings are the whole file
+///
+/// # Returns
+/// The parsed value or `ConfigError::Parse`
+pub fn parse_file<T: DeserializeOwned>(
+    text: &str,
+    format: ConfigFormat,
+    settings_key: Option<&str>,
+) -> Result<T, ConfigError> {
+    match format {
+        ConfigFormat::Toml => {
+            let mut table: toml::Table =
+                toml::from_str(text).map_err(|error| ConfigError::Parse(error.to_string()))?;
+            let settings = match settings_key {
+                Some(key) => table.get_mut(key).and_then(toml::Value::as_table_mut),
+                None => Some(&mut table),
+            };
+            if let Some(settings) = settings {
+                for (old_key, new_keys) in SQUARE_AREA_KEYS {
+                    if let Some(size) = settings.remove(old_key) {
+                        for new_key in new_keys {
+                            settings.entry(new_key).or_insert_with(|| size.clone());
+                        }
+                    }
+                }
+            }
+            toml::Value::Table(table)
+                .try_into()
+                .map_err(|error| ConfigError::Parse(error.to_string()))
+        }
+        ConfigFormat::Json => {
+            let mut value: serde_json::Value = serde_json::from_str(text)
+                .map_err(|error| ConfigError::Parse(error.to_string()))?;
+            let settings = match settings_key {
+                Some(key) => value.get_mut(key),
+                None => Some(&mut value),
+            };
+            if let Some(settings) = settings.and_then(serde_json::Value::as_object_mut) {
+                for (old_key, new_keys) in SQUARE_AREA_KEYS {
+                    if let Some(size) = settings.remove(old_key) {
+                        for new_key in new_keys {
+                            settings.entry(new_key).or_insert_with(|| size.clone());
+                        }
+                    }
+                }
+            }
+            serde_json::from_value(value).map_err(|error| ConfigError::Parse(error.to_string()))
+        }
+    }
+}
+
+/// Writes settings as the text of a configuration file.
+///
+/// # Parameters
+/// - `settings`: The settings to write
+/// - `format`: Format of the content
+///
+/// # Returns
+/// The content of the configuration file
+pub fn format_settings(settings: &Settings, format: ConfigFormat) -> Result<String, ConfigError> {
+    match format {
+        ConfigFormat::Toml => {
+            let mut value = toml::Value::try_from(settings)
+                .map_err(|error| ConfigError::Serialize(error.to_string()))?;
+            shorten_floats(&mut value);
+            toml::to_string_pretty(&value)
+                .map_err(|error| ConfigError::Serialize(error.to_string()))
+        }
+        ConfigFormat::Json => serde_json::to_string_pretty(settings)
+            .map_err(|error| ConfigError::Serialize(error.to_string())),
+    }
+}
+
+/// Rounds floats in a TOML value to the precision of `f32`.
+///
+/// TOML stores `f32` settings as `f64`, so without rounding `0.3` is written as
+/// `0.30000001192092896`.
+///
+/// # Parameters
+/// - `value`: The TOML value to update
+fn shorten_floats(value: &mut toml::Value) {
+    match value {
+        toml::Value::Float(float) => {
+            if let Ok(short) = (*float as f32).to_string().parse() {
+                *float = short;
+            }
+        }
+        toml::Value::Array(array) => array.iter_mut().for_each(shorten_floats),
+        toml::Value::Table(table) => table
+            .iter_mut()
+            .for_each(|(_, value)| shorten_floats(value)),
+        _ => {}
+    }
+}
+
+/// Checks that all settings are in a range the simulation can use.
+///
+/// All problems are collected so the user can fix them at once.
+///
+/// # Parameters
+/// - `settings`: The settings to check
+///
+/// # Returns
+/// `Ok(())` or `ConfigError::Invalid` with one message per problem
+pub fn validate_settings(settings: &Settings) -> Result<(), ConfigError> {
+    let mut problems = Vec::new();
+
+    let positive = [
+        ("simulation_area_width", settings.simulation_area_width),
+        ("simulation_area_height", settings.simulation_area_height),
+        ("quarantine_area_width", settings.quarantine_area_width),
+        ("quarantine_area_height", settings.quarantine_area_height),
+        ("person_radius", settings.person_radius),
+        ("recovered_day", settings.recovered_day),
+        (
+            "social_distancing_max_speed",
+            settings.social_distancing_max_speed,
+        ),
+        ("dispersion_k", settings.dispersion_k),
+        ("time_step", settings.time_step),
+    ];
+    for (name, value) in positive {
+        if value.is_nan() || value <= 0.0 {
+            problems.push(format!("`{}` must be greater than 0, got {}", name, value));
+        }
+    }
+
+    let non_negative = [
+        ("moving_speed", settings.moving_speed),
+        ("border_padding", settings.border_padding),
+        ("margin_from_wall", settings.margin_from_wall),
+        (
+            "gap_community_quarantine",
+            settings.gap_community_quarantine,
+        ),
+        ("variant_seed_time", settings.variant_seed_time),
+        ("infected_radius", settings.infected_radius),
+        (
+            "social_distancing_radius",
+            settings.social_distancing_radius,
+        ),
+        (
+            "infection_time_before_quarantine",
+            settings.infection_time_before_quarantine,
+        ),
+    ];
+    for (name, value) in non_negative {
+        if value.is_nan() || value < 0.0 {
+            problems.push(format!("`{}` must be 0 or greater, got {}", name, value));
+        }
+    }
+
+    let probabilities = [
+        ("infection_probability", settings.infection_probability),
+        ("cross_immunity", settings.cross_immunity),
+    ];
+    for (name, value) in probabilities {
+        if !(0.0..=1.0).contains(&value) {
+            problems.push(format!(
+                "`{}` must be between 0.0 and 1.0, got {}",
+                name, value
+            ));
+        }
+    }
+
+    let smallest_area = [
+        settings.simulation_area_width,
+        settings.simulation_area_height,
+        settings.quarantine_area_width,
+        settings.quarantine_area_height,
+    ]
+    .into_iter()
+    .fold(f32::INFINITY, f32::min);
+    if settings.margin_from_wall * 2.0 >= smallest_area {
+        problems.push(format!(
+            "`margin_from_wall` ({}) must be less than half of the smallest area ({})",
+            settings.margin_from_wall, smallest_area
+        ));
+    }
+
+    for (index, wall) in settings.walls.iter().enumerate() {
+        let inside = [wall.x1, wall.x2]
+            .iter()
+            .all(|&x| (0.0..=settings.simulation_area_width).contains(&x))
+            && [wall.y1, wall.y2]
+                .iter()
+                .all(|&y| (0.0..=settings.simulation_area_height).contains(&y));
+        if !inside {
+            problems.push(format!(
+                "wall {} must be inside the community area ({} x {})",
+                index + 1,
+                settings.simulation_area_width,
+                settings.simulation_area_height
+            ));
+        }
+    }
+
+    if settings.community_size == 0 {
+        problems.push(String::from("`community_size` must be at least 1"));
+    }
+    if settings.initial_infected_people > settings.community_size {
+        problems.push(format!(
+            "`initial_infected_people` ({}) must not be greater than `community_size` ({})",
+            settings.initial_infected_people, settings.community_size
+        ));
+    }
+
+    for (index, variant) in settings.variants.iter().enumerate() {
+        if !(0.0..=1.0).contains(&variant.infection_probability) {
+            problems.push(format!(
+                "variant {} `infection_probability` must be between 0.0 and 1.0, got {}",
+                index + 1,
+                variant.infection_probability
+            ));
+        }
+        if variant.recovered_day.is_nan() || variant.recovered_day <= 0.0 {
+            problems.push(format!(
+                "variant {} `recovered_day` must be greater than 0, got {}",
+                index + 1,
+                variant.recovered_day
+            ));
+        }
+        if variant.seed_time.is_nan() || variant.seed_time < 0.0 {
+            problems.push(format!(
+                "variant {} `seed_time` must be 0 or greater, got {}",
+                index + 1,
+                variant.seed_time
+            ));
+        }
+    }
+
+    let strain_count = settings.variants.len() + 1;
+    let matrix = &settings.cross_immunity_matrix;
+    if matrix.len() != strain_count || matrix.iter().any(|row| row.len() != strain_count) {
+        problems.push(format!(
+            "`cross_immunity_matrix` must be {0}x{0} (the original strain and {1} variants)",
+            strain_count,
+            strain_count - 1
+        ));
+    } else if matrix
+        .iter()
+        .flatten()
+        .any(|value| !(0.0..=1.0).contains(value))
+    {
+        problems.push(String::from(
+            "`cross_immunity_matrix` values must be between 0.0 and 1.0",
+        ));
+    }
+
+    if problems.is_empty() {
+        Ok(())
+    } else {
+        Err(ConfigError::Invalid(problems))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::wall::Wall;
+
+    /// Tests that the default settings with a wall are valid and survive a TOML and JSON round trip.
+    #[test]
+    fn test_format_and_parse_settings() {
+        let settings = Settings {
+            walls: vec![Wall {
+                x1: 100.0,
+                y1: 20.0,
+                x2: 100.0,
+                y2: 250.5,
+            }],
+            ..Settings::default()
+        };
+        assert!(validate_settings(&settings).is_ok());
+        for format in [ConfigFormat::Toml, ConfigFormat::Json] {
+            let text = format_settings(&settings, format).unwrap();
+            assert_eq!(parse_settings(&text, format).unwrap(), settings);
+        }
+    }
+
+    /// Tests that `f32` settings are written without `f64` rounding noise.
+    #[test]
+    fn test_format_settings_short_floats() {
+        let text = format_settings(&Settings::default(), ConfigFormat::Toml).unwrap();
+        assert!(text.contains("infection_probability = 0.3\n"));
+    }
+
+    /// Tests that missing fields keep their default values.
+    #[test]
+    fn test_parse_partial_settings() {
+        let settings = parse_settings(
+            "community_size = 120\nmoving_speed = 60.0",
+            ConfigFormat::Toml,
+        )
+        .unwrap();
+        assert_eq!(settings.community_size, 120);
+        assert_eq!(settings.moving_speed, 60.0);
+        assert_eq!(settings.recovered_day, Settings::default().recovered_day);
+    }
+
+    /// Tests that files with the old square area sizes set both the width and the height.
+    #[test]
+    fn test_parse_old_area_size() {
+        let settings = parse_settings(
+            "simulation_area_size = 300.0\nquarantine_area_size = 80.0\nquarantine_area_height = 60.0",
+            ConfigFormat::Toml,
+        )
+        .unwrap();
+        assert_eq!(settings.simulation_area_width, 300.0);
+        assert_eq!(settings.simulation_area_height, 300.0);
+        assert_eq!(settings.quarantine_area_width, 80.0);
+        assert_eq!(settings.quarantine_area_height, 60.0);
+
+        let settings = parse_settings(
+            "{\"simulation_area_size\": 300.0, \"quarantine_area_size\": 80.0}",
+            ConfigFormat::Json,
+        )
+        .unwrap();
+        assert_eq!(settings.simulation_area_height, 300.0);
+        assert_eq!(settings.quarantine_area_width, 80.0);
+    }
+
+    /// Tests that unknown fields are rejected with their name in the message.
+    #[test]
+    fn test_parse_unknown_field() {
+        let error = parse_settings("{\"comunity_size\": 10}", ConfigFormat::Json).unwrap_err();
+        assert!(matches!(error, ConfigError::Parse(_)));
+        assert!(error.to_string().contains("comunity_size"));
+    }
+
+    /// Tests that all out of range values are reported.
+    #[test]
+    fn test_validate_settings_problems() {
+        let settings = Settings {
+            infection_probability: 1.5,
+            community_size: 10,
+            initial_infected_people: 20,
+            cross_immunity_matrix: vec![vec![1.0]],
+            walls: vec![Wall {
+                x1: 10.0,
+                y1: 10.0,
+                x2: 500.0,
+                y2: 10.0,
+            }],
+            ..Settings::default()
+        };
+        match validate_settings(&settings) {
+            Err(ConfigError::Invalid(problems)) => {
+                assert_eq!(problems.len(), 4);
+                assert!(problems[0].contains("infection_probability"));
+                assert!(problems[1].contains("wall 1"));
+                assert!(problems[2].contains("initial_infected_people"));
+                assert!(problems[3].contains("cross_immunity_matrix"));
+            }
+            _ => panic!("settings should be invalid"),
+        }
+    }
+
+    /// Tests that the format is found from the file extension.
+    #[test]
+    fn test_config_format_from_path() {
+        assert_eq!(
+            ConfigFormat::from_path(Path::new("a.toml")).unwrap(),
+            ConfigFormat::Toml
+        );
+        assert_eq!(
+            ConfigFormat::from_path(Path::new("a.json")).unwrap(),
+            ConfigFormat::Json
+        );
+        assert!(ConfigFormat::from_path(Path::new("a.yaml")).is_err());
+    }
+}
@@ -0,0 +1,107 @@
+//! Downsampling module for the chart history.
+//!
+//! Long runs would keep adding chart samples forever, so the history is halved with
+//! the Largest-Triangle-Three-Buckets (LTTB) algorithm whenever it is full. LTTB keeps
+//! the samples which shape the curve most, such as the epidemic peak, so the chart
+//! looks the same with half of the samples.
+
+/// Chooses the samples of a series to keep with the LTTB algorithm.
+///
+/// The first and last samples are always kept. The samples in between are split into
+/// `threshold - 2` buckets and from each bucket the sample forming the largest triangle
+/// with the previously kept sample and the mean of the next bucket is kept.
+///
+/// # Parameters
+/// - `times`: Time of each sample, in ascending order
+/// - `values`: Value of each sample
+/// - `threshold`: Number of samples to keep
+///
+/// # Returns
+/// The indices of the kept samples, in ascending order
+pub fn lttb(times: &[f32], values: &[f32], threshold: usize) -> Vec<usize> {
+    let length = times.len().min(values.len());
+    if threshold >= length || threshold < 3 {
+        return (0..length).collect();
+    }
+
+    let bucket_size = (length - 2) as f32 / (threshold - 2) as f32;
+    let bucket_start = |bucket: usize| ((bucket as f32 * bucket_size) as usize + 1).min(length - 1);
+    let mut kept = Vec::with_capacity(threshold);
+    kept.push(0);
+    let mut previous = 0;
+    for bucket in 0..threshold - 2 {
+        let start = bucket_start(bucket);
+        let end = bucket_start(bucket + 1).max(start + 1);
+
+        // Mean of the next bucket, or the last sample after the last bucket
+        let next_end = bucket_start(bucket + 2).max(end + 1).min(length);
+        let next = if bucket + 3 < threshold {
+            end..next_end
+        } else {
+            length - 1..length
+        };
+        let count = next.len() as f32;
+        let mean_time = times[next.clone()].iter().sum::<f32>() / count;
+        let mean_value = values[next].iter().sum::<f32>() / count;
+
+        let (previous_time, previous_value) = (times[previous], values[previous]);
+        let mut best = start;
+        let mut best_area = -1.0;
+        for i in start..end.min(length - 1) {
+            let area = ((previous_time - mean_time) * (values[i] - previous_value)
+                - (previous_time - times[i]) * (mean_value - previous_value))
+                .abs();
+            if area > best_area {
+                best_area = area;
+                best = i;
+            }
+        }
+        kept.push(best);
+        previous = best;
+    }
+    kept.push(length - 1);
+    kept
+}
+
+/// Keeps only the elements at the given indices.
+///
+/// # Parameters
+/// - `values`: The values to filter
+/// - `indices`: Indices of the kept values, in ascending order
+pub fn retain_indices<T: Copy>(values: &mut Vec<T>, indices: &[usize]) {
+    *values = indices
+        .iter()
+        .filter_map(|&index| values.get(index).copied())
+        .collect();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Tests that LTTB keeps the ends, the threshold and the peak of a curve.
+    #[test]
+    fn test_lttb_keeps_peak() {
+        let times: Vec<f32> = (0..1000).map(|i| i as f32).collect();
+        let values: Vec<f32> = times
+            .iter()
+            .map(|&time| if time == 437.0 { 100.0 } else { time / 100.0 })
+            .collect();
+        let kept = lttb(&times, &values, 100);
+        assert_eq!(kept.len(), 100);
+        assert_eq!(kept[0], 0);
+        assert_eq!(kept[99], 999);
+        assert!(kept.windows(2).all(|pair| pair[0] < pair[1]));
+        assert!(kept.contains(&437));
+
+        assert_eq!(lttb(&times[..5], &values[..5], 10), vec![0, 1, 2, 3, 4]);
+    }
+
+    /// Tests that only the elements at the indices are kept.
+    #[test]
+    fn test_retain_indices() {
+        let mut values = vec![10, 11, 12, 13, 14];
+        retain_indices(&mut values, &[0, 2, 4]);
+        assert_eq!(values, vec![10, 12, 14]);
+    }
+}
@@ -0,0 +1,324 @@
+//! Monte Carlo ensemble module.
+//!
+//! A single stochastic run can be misleading, so an ensemble runs the same settings
+//! with many seeds in parallel (without a window) and summarises the runs: the median
+//! infected curve with 50% and 90% bands, and the distributions of peak size, peak
+//! time and final attack rate.
+
+use std::io::Write;
+use std::path::Path;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+
+use serde::Serialize;
+
+use crate::config::ConfigError;
+use crate::export::{self, ExportError, ExportFormat};
+use crate::scenario::Scenario;
+use crate::settings::Settings;
+use crate::simulation::Simulation;
+
+/// Default number of runs of an ensemble.
+pub const ENSEMBLE_RUNS: usize = 20;
+
+/// Default length (in seconds) of each run of an ensemble.
+pub const ENSEMBLE_DURATION: f32 = 60.0;
+
+/// Summary of one run of an ensemble.
+///
+/// # Fields
+/// - `seed`: Seed of the run
+/// - `infected`: Percentage of infected people at the start and after every second
+/// - `peak_size`: Highest percentage of infected people
+/// - `peak_time`: Time (in seconds) of the highest percentage of infected people
+/// - `attack_rate`: Percentage of people who are not susceptible at the end of the run
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct EnsembleRun {
+    pub seed: u64,
+    #[serde(skip)]
+    pub infected: Vec<f32>,
+    pub peak_size: f32,
+    pub peak_time: f32,
+    pub attack_rate: f32,
+}
+
+/// Results of all runs of an ensemble.
+///
+/// # Fields
+/// - `runs`: Summary of each run, in the order of their seeds
+/// - `times`: Time (in seconds) of each sample of the infected curves
+/// - `median`: Median percentage of infected people at each time
+/// - `band_50`: 25% and 75% quantiles at each time
+/// - `band_90`: 5% and 95% quantiles at each time
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Ensemble {
+    pub runs: Vec<EnsembleRun>,
+    pub times: Vec<f32>,
+    pub median: Vec<f32>,
+    pub band_50: Vec<(f32, f32)>,
+    pub band_90: Vec<(f32, f32)>,
+}
+
+impl Ensemble {
+    /// Summarises the infected curves of the runs.
+    ///
+    /// # Parameters
+    /// - `runs`: The runs, all with the same number of samples
+    ///
+    /// # Returns
+    /// The ensemble with the median and bands at each time
+    pub fn from_runs(runs: Vec<EnsembleRun>) -> Self {
+        let samples = runs.iter().map(|run| run.infected.len()).min().unwrap_or(0);
+        let mut ensemble = Ensemble {
+            times: (0..samples).map(|i| i as f32).collect(),
+            ..Ensemble::default()
+        };
+        for i in 0..samples {
+            let mut values: Vec<f32> = runs.iter().map(|run| run.infected[i]).collect();
+            values.sort_by(f32::total_cmp);
+            ensemble.median.push(quantile(&values, 0.5));
+            ensemble
+                .band_50
+                .push((quantile(&values, 0.25), quantile(&values, 0.75)));
+            ensemble
+                .band_90
+                .push((quantile(&values, 0.05), quantile(&values, 0.95)));
+        }
+        ensemble.runs = runs;
+        ensemble
+    }
+
+    /// Writes the per-run summaries as CSV.
+    ///
+    /// # Parameters
+    /// - `out`: Where the CSV is written
+    pub fn write_runs_csv(&self, out: &mut impl Write) -> Result<(), ExportError> {
+        let write_error = |error: std::io::Error| ExportError::Serialize(error.to_string());
+        writeln!(out, "seed,peak_size,peak_time,attack_rate").map_err(write_error)?;
+        for run in &self.runs {
+            writeln!(
+                out,
+                "{},{:.3},{:.3},{:.3}",
+                run.seed, run.peak_size, run.peak_time, run.attack_rate
+            )
+            .map_err(write_error)?;
+        }
+        Ok(())
+    }
+
+    /// Writes the median infected curve and its bands as CSV.
+    ///
+    /// # Parameters
+    /// - `out`: Where the CSV is written
+    pub fn write_bands_csv(&self, out: &mut impl Write) -> Result<(), ExportError> {
+        let write_error = |error: std::io::Error| ExportError::Serialize(error.to_string());
+        writeln!(out, "time,median,p5,p25,p75,p95").map_err(write_error)?;
+        for i in 0..self.times.len() {
+            writeln!(
+                out,
+                "{:.3},{:.3},{:.3},{:.3},{:.3},{:.3}",
+                self.times[i],
+                self.median[i],
+                self.band_90[i].0,
+                self.band_50[i].0,
+                self.band_50[i].1,
+                self.band_90[i].1
+            )
+            .map_err(write_error)?;
+        }
+        Ok(())
+    }
+
+    /// Saves the per-run summaries to a CSV or JSON file.
+    ///
+    /// # Parameters
+    /// - `path`: Path of a `.csv` or `.json` file
+    pub fn save_runs(&self, path: impl AsRef<Path>) -> Result<(), ExportError> {
+        export::save_as(path, |format, out| match format {
+            ExportFormat::Csv => self.write_runs_csv(out),
+            ExportFormat::Json => serde_json::to_writer_pretty(out, &self.runs)
+                .map_err(|error| ExportError::Serialize(error.to_string())),
+        })
+    }
+}
+
+/// Finds a quantile of sorted values with linear interpolation.
+///
+/// # Parameters
+/// - `sorted`: Values sorted in ascending order
+/// - `q`: The quantile between 0 and 1
+///
+/// # Returns
+/// The quantile, or 0 without values
+pub fn quantile(sorted: &[f32], q: f32) -> f32 {
+    if sorted.is_empty() {
+        return 0.0;
+    }
+    let position = q.clamp(0.0, 1.0) * (sorted.len() - 1) as f32;
+    let lower = position.floor() as usize;
+    let upper = position.ceil() as usize;
+    let fraction = position - lower as f32;
+    sorted[lower] + (sorted[upper] - sorted[lower]) * fraction
+}
+
+/// Runs one simulation without a window and summarises it.
+///
+/// # Parameters
+/// - `settings`: Settings of the run (the seed is replaced)
+/// - `scenario`: Scenario whose events are played back, if any (its settings are replaced)
+/// - `seed`: Seed of the run
+/// - `duration`: Length (in seconds) of the run
+///
+/// # Returns
+/// The summary of the run, or the error if the scenario does not fit the settings
+pub fn run_single(
+    settings: &Settings,
+    scenario: Option<&Scenario>,
+    seed: u64,
+    duration: f32,
+) -> Result<EnsembleRun, ConfigError> {
+    let settings = Settings {
+        seed: Some(seed),
+        ..settings.clone()
+    };
+    let mut simulation = Simulation::with_settings(settings.clone());
+    if let Some(scenario) = scenario {
+        simulation.set_scenario(Scenario {
+            settings: Some(settings),
+            ..scenario.clone()
+        })?;
+    }
+
+    let time_step = simulation.settings.time_step;
+    let ticks_per_second = ((1.0 / time_step).round() as usize).max(1);
+    let seconds = duration.round() as usize;
+    let mut infected = Vec::with_capacity(seconds + 1);
+    let mut peak_size = 0.0;
+    let mut peak_time = 0.0;
+    for second in 0..=seconds {
+        if second > 0 {
+            for _ in 0..ticks_per_second {
+                simulation.step(time_step);
+            }
+        }
+        let (_, current_infected, _) = simulation.count_states();
+        let percentage = current_infected as f32 / simulation.community.len() as f32 * 100.0;
+        if percentage > peak_size {
+            peak_size = percentage;
+            peak_time = second as f32;
+        }
+        infected.push(percentage);
+    }
+
+    let (susceptible, _, _) = simulation.count_states();
+    let attack_rate = (1.0 - susceptible as f32 / simulation.community.len() as f32) * 100.0;
+    Ok(EnsembleRun {
+        seed,
+        infected,
+        peak_size,
+        peak_time,
+        attack_rate,
+    })
+}
+
+/// Runs an ensemble of simulations in parallel.
+///
+/// Run `i` uses the seed `base_seed + i`, so the result does not depend on the number
+/// of threads.
+///
+/// # Parameters
+/// - `settings`: Settings of every run
+/// - `scenario`: Scenario played back in every run, if any
+/// - `base_seed`: Seed of the first run
+/// - `runs`: Number of runs
+/// - `duration`: Length (in seconds) of each run
+///
+/// # Returns
+/// The ensemble, or the error if the scenario does not fit the settings
+pub fn run_ensemble(
+    settings: &Settings,
+    scenario: Option<&Scenario>,
+    base_seed: u64,
+    runs: usize,
+    duration: f32,
+) -> Result<Ensemble, ConfigError> {
+    let threads = thread::available_parallelism()
+        .map(|count| count.get())
+        .unwrap_or(1)
+        .min(runs.max(1));
+    let next_run = AtomicUsize::new(0);
+    let mut results: Vec<(usize, Result<EnsembleRun, ConfigError>)> = thread::scope(|scope| {
+        let workers: Vec<_> = (0..threads)
+            .map(|_| {
+                scope.spawn(|| {
+                    let mut finished = Vec::new();
+                    loop {
+                        let run = next_run.fetch_add(1, Ordering::Relaxed);
+                        if run >= runs {
+                            return finished;
+                        }
+                        let seed = base_seed.wrapping_add(run as u64);
+                        finished.push((run, run_single(settings, scenario, seed, duration)));
+                    }
+                })
+            })
+            .collect();
+        workers
+            .into_iter()
+            .flat_map(|worker| worker.join().expect("ensemble worker panicked"))
+            .collect()
+    });
+    results.sort_by_key(|(run, _)| *run);
+    let runs = results
+        .into_iter()
+        .map(|(_, result)| result)
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(Ensemble::from_runs(runs))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Tests quantiles with linear interpolation.
+    #[test]
+    fn test_quantile() {
+        let values = [1.0, 2.0, 3.0, 4.0, 5.0];
+        assert_eq!(quantile(&values, 0.5), 3.0);
+        assert_eq!(quantile(&values, 0.25), 2.0);
+        assert!((quantile(&values, 0.1) - 1.4).abs() < 1e-6);
+        assert_eq!(quantile(&[], 0.5), 0.0);
+    }
+
+    /// Tests that the median and bands are computed at each time.
+    #[test]
+    fn test_from_runs() {
+        let runs = (0..5)
+            .map(|i| EnsembleRun {
+                seed: i,
+                infected: vec![i as f32, 2.0 * i as f32],
+                peak_size: 0.0,
+                peak_time: 0.0,
+                attack_rate: 0.0,
+            })
+            .collect();
+        let ensemble = Ensemble::from_runs(runs);
+        assert_eq!(ensemble.times, vec![0.0, 1.0]);
+        assert_eq!(ensemble.median, vec![2.0, 4.0]);
+        assert_eq!(ensemble.band_50[1], (2.0, 6.0));
+    }
+
+    /// Tests that an ensemble gives the same runs as single runs with the same seeds.
+    #[test]
+    fn test_run_ensemble_deterministic() {
+        let settings = Settings::default();
+        let ensemble = run_ensemble(&settings, None, 10, 3, 2.0).unwrap();
+        assert_eq!(ensemble.runs.len(), 3);
+        assert_eq!(ensemble.times.len(), 3);
+        for (i, run) in ensemble.runs.iter().enumerate() {
+            let single = run_single(&settings, None, 10 + i as u64, 2.0).unwrap();
+            assert_eq!(*run, single);
+            assert!(run.attack_rate >= 3.0 / 80.0 * 100.0);
+        }
+    }
+}
@@ -0,0 +1,475 @@
+//! Export module for simulation results.
+//!
+//! Writes the S/I/R time series of a run, with counts, percentages and the run
+//! parameters, to CSV (`.csv`) or JSON (`.json`) files for analysis in other tools.
+
+use std::fmt;
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+
+use serde::Serialize;
+
+use crate::settings::{CHART_INTERVAL, Settings};
+use crate::simulation::Simulation;
+
+/// Default export file shown in the UI.
+pub const DEFAULT_EXPORT_PATH: &str = "sir_data.csv";
+
+/// Supported export file formats.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ExportFormat {
+    Csv,
+    Json,
+}
+
+impl ExportFormat {
+    /// Finds the format of an export file from its extension.
+    ///
+    /// # Parameters
+    /// - `path`: Path of the export file
+    ///
+    /// # Returns
+    /// The format, or `ExportError::UnsupportedFormat` for other extensions
+    pub fn from_path(path: &Path) -> Result<Self, ExportError> {
+        match path.extension().and_then(|extension| extension.to_str()) {
+            Some("csv") => Ok(ExportFormat::Csv),
+            Some("json") => Ok(ExportFormat::Json),
+            _ => Err(ExportError::UnsupportedFormat(
+                path.display().to_string(),
+                ".csv or .json",
+            )),
+        }
+    }
+}
+
+/// Errors when exporting simulation results.
+///
+/// # Variants
+/// - `UnsupportedFormat`: The file extension is not supported, with the supported extensions
+/// - `Io`: The file cannot be written
+/// - `Serialize`: The results cannot be written in the format
+#[derive(Debug)]
+pub enum ExportError {
+    UnsupportedFormat(String, &'static str),
+    Io(String, io::Error),
+    Serialize(String),
+}
+
+impl fmt::Display for ExportError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ExportError::UnsupportedFormat(path, extensions) => write!(
+                f,
+                "unsupported export file '{}': use a {} file",
+                path, extensions
+            ),
+            ExportError::Io(path, error) => write!(f, "cannot write '{}': {}", path, error),
+            ExportError::Serialize(message) => write!(f, "cannot export data: {}", message),
+        }
+    }
+}
+
+impl std::error::Error for ExportError {}
+
+/// One sample of the S/I/R time series.
+///
+/// # Fields
+/// - `time`: Simulation time (in seconds)
+/// - `susceptible`, `infected`, `recovered`: Number of people in each state
+/// - `susceptible_percent`, `infected_percent`, `recovered_percent`: Percentage of people in each state
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct TimeSeriesRow {
+    pub time: f32,
+    pub susceptible: usize,
+    pub infected: usize,
+    pub recovered: usize,
+    pub susceptible_percent: f32,
+    pub infected_percent: f32,
+    pub recovered_percent: f32,
+}
+
+impl TimeSeriesRow {
+    /// Creates a row from the number of people in each state.
+    ///
+    /// # Parameters
+    /// - `time`: Simulation time (in seconds)
+    /// - `counts`: Number of `(susceptible, infected, recovered)` people
+    pub fn new(time: f32, (susceptible, infected, recovered): (usize, usize, usize)) -> Self {
+        let total = (susceptible + infected + recovered).max(1) as f32;
+        TimeSeriesRow {
+            time,
+            susceptible,
+            infected,
+            recovered,
+            susceptible_percent: susceptible as f32 / total * 100.0,
+            infected_percent: infected as f32 / total * 100.0,
+            recovered_percent: recovered as f32 / total * 100.0,
+        }
+    }
+
+    /// Writes the row as one CSV line.
+    ///
+    /// # Parameters
+    /// - `out`: Where the line is written
+    fn write_csv(&self, out: &mut impl Write) -> io::Result<()> {
+        writeln!(
+            out,
+            "{:.4},{},{},{},{:.3},{:.3},{:.3}",
+            self.time,
+            self.susceptible,
+            self.infected,
+            self.recovered,
+            self.susceptible_percent,
+            self.infected_percent,
+            self.recovered_percent
+        )
+    }
+}
+
+/// Writes the run parameters as `# name = value` lines followed by the CSV header.
+///
+/// # Parameters
+/// - `out`: Where the lines are written
+/// - `settings`: Settings of the run
+fn write_csv_header(out: &mut impl Write, settings: &Settings) -> Result<(), ExportError> {
+    let write_error = |error: io::Error| ExportError::Serialize(error.to_string());
+    let parameters = serde_json::to_value(settings)
+        .map_err(|error| ExportError::Serialize(error.to_string()))?;
+    if let serde_json::Value::Object(parameters) = parameters {
+        for (name, value) in parameters {
+            writeln!(out, "# {} = {}", name, value).map_err(write_error)?;
+        }
+    }
+    writeln!(
+        out,
+        "time,susceptible,infected,recovered,susceptible_percent,infected_percent,recovered_percent"
+    )
+    .map_err(write_error)
+}
+
+/// The S/I/R time series of a run with its parameters.
+///
+/// # Fields
+/// - `seed`: Seed of the random number generator of the run
+/// - `settings`: Settings of the run
+/// - `rows`: One row per sample of the chart history
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct TimeSeries {
+    pub seed: u64,
+    pub settings: Settings,
+    pub rows: Vec<TimeSeriesRow>,
+}
+
+impl TimeSeries {
+    /// Collects the recorded chart data of a simulation.
+    ///
+    /// The chart history is downsampled in long runs, so the rows are as far apart as the
+    /// chart samples. `TimeSeriesWriter` exports every sample while the run goes on.
+    ///
+    /// # Parameters
+    /// - `simulation`: The simulation to export
+    ///
+    /// # Returns
+    /// The time series from the last restart until now
+    pub fn from_simulation(simulation: &Simulation) -> Self {
+        let rows = simulation
+            .total_time
+            .iter()
+            .zip(&simulation.state_counts)
+            .map(|(&time, &counts)| TimeSeriesRow::new(time, counts))
+            .collect();
+        TimeSeries {
+            seed: simulation.seed,
+            settings: simulation.run_settings(),
+            rows,
+        }
+    }
+
+    /// Writes the time series as CSV.
+    ///
+    /// The run parameters are written first as `# name = value` comment lines,
+    /// followed by a header and one line per row.
+    ///
+    /// # Parameters
+    /// - `out`: Where the CSV is written
+    pub fn write_csv(&self, out: &mut impl Write) -> Result<(), ExportError> {
+        write_csv_header(out, &self.settings)?;
+        for row in &self.rows {
+            row.write_csv(out)
+                .map_err(|error| ExportError::Serialize(error.to_string()))?;
+        }
+        Ok(())
+    }
+
+    /// Writes the time series as pretty-printed JSON.
+    ///
+    /// # Parameters
+    /// - `out`: Where the JSON is written
+    pub fn write_json(&self, out: &mut impl Write) -> Result<(), ExportError> {
+        serde_json::to_writer_pretty(out, self)
+            .map_err(|error| ExportError::Serialize(error.to_string()))
+    }
+
+    /// Saves the time series to a CSV or JSON file.
+    ///
+    /// # Parameters
+    /// - `path`: Path of a `.csv` or `.json` file
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), ExportError> {
+        save_as(path, |format, out| match format {
+            ExportFormat::Csv => self.write_csv(out),
+            ExportFormat::Json => self.write_json(out),
+        })
+    }
+}
+
+/// Writes the S/I/R time series of a run to a file while the run goes on.
+///
+/// A row is written every `CHART_INTERVAL` of simulation time, so long runs are exported
+/// without downsampling and without keeping the rows in memory. The file has the same
+/// content as a saved `TimeSeries`, with compact instead of pretty-printed JSON.
+///
+/// # Fields
+/// - `path`: Path of the export file
+/// - `format`: Format of the export file
+/// - `out`: The export file
+/// - `last_time`: Simulation time of the last written row
+/// - `rows`: Number of written rows
+pub struct TimeSeriesWriter {
+    pub path: String,
+    pub format: ExportFormat,
+    pub out: BufWriter<File>,
+    pub last_time: f32,
+    pub rows: usize,
+}
+
+impl TimeSeriesWriter {
+    /// Creates the export file and writes the run parameters and the first row.
+    ///
+    /// # Parameters
+    /// - `path`: Path of a `.csv` or `.json` file
+    /// - `simulation`: The simulation at the start of the run
+    pub fn create(path: impl AsRef<Path>, simulation: &Simulation) -> Result<Self, ExportError> {
+        let path = path.as_ref();
+        let format = ExportFormat::from_path(path)?;
+        let file = File::create(path)
+            .map_err(|error| ExportError::Io(path.display().to_string(), error))?;
+        let mut writer = TimeSeriesWriter {
+            path: path.display().to_string(),
+            format,
+            out: BufWriter::new(file),
+            last_time: simulation.elapsed_time,
+            rows: 0,
+        };
+        let settings = simulation.run_settings();
+        match format {
+            ExportFormat::Csv => write_csv_header(&mut writer.out, &settings)?,
+            ExportFormat::Json => {
+                let settings = serde_json::to_string(&settings)
+                    .map_err(|error| ExportError::Serialize(error.to_string()))?;
+                let prefix = format!(
+                    "{{\"seed\":{},\"settings\":{},\"rows\":[",
+                    simulation.seed, settings
+                );
+                writer.write(prefix.as_bytes())?;
+            }
+        }
+        writer.write_row(simulation)?;
+        Ok(writer)
+    }
+
+    /// Writes a row when `CHART_INTERVAL` has passed since the last row.
+    ///
+    /// # Parameters
+    /// - `simulation`: The simulation after a tick
+    pub fn record(&mut self, simulation: &Simulation) -> Result<(), ExportError> {
+        // Half a tick of tolerance, so rounding errors do not skip a row
+        let tolerance = simulation.settings.time_step / 2.0;
+        if simulation.elapsed_time - self.last_time + tolerance < CHART_INTERVAL {
+            return Ok(());
+        }
+        self.write_row(simulation)
+    }
+
+    /// Finishes the file.
+    pub fn finish(mut self) -> Result<(), ExportError> {
+        if self.format == ExportFormat::Json {
+            self.write(b"]}")?;
+        }
+        self.out
+            .flush()
+            .map_err(|error| ExportError::Io(self.path.clone(), error))
+    }
+
+    /// Writes a row with the current state counts.
+    ///
+    /// # Parameters
+    /// - `simulation`: The simulation to export
+    fn write_row(&mut self, simulation: &Simulation) -> Result<(), ExportError> {
+        let row = TimeSeriesRow::new(simulation.elapsed_time, simulation.count_states());
+        let text = match self.format {
+            ExportFormat::Csv => {
+                let mut line = Vec::new();
+                row.write_csv(&mut line)
+                    .map_err(|error| ExportError::Serialize(error.to_string()))?;
+                line
+            }
+            ExportFormat::Json => {
+                let mut text = if self.rows > 0 {
+                    vec![b',']
+                } else {
+                    Vec::new()
+                };
+                serde_json::to_writer(&mut text, &row)
+                    .map_err(|error| ExportError::Serialize(error.to_string()))?;
+                text
+            }
+        };
+        self.write(&text)?;
+        self.last_time = simulation.elapsed_time;
+        self.rows += 1;
+        Ok(())
+    }
+
+    /// Writes bytes to the export file.
+    fn write(&mut self, bytes: &[u8]) -> Result<(), ExportError> {
+        self.out
+            .write_all(bytes)
+            .map_err(|error| ExportError::Io(self.path.clone(), error))
+    }
+}
+
+/// Writes a file in the format given by its extension.
+///
+/// The content is written to memory first, so nothing is written if it cannot be formatted.
+///
+/// # Parameters
+/// - `path`: Path of a `.csv` or `.json` file
+/// - `write`: Writes the content in the given format
+pub fn save_as(
+    path: impl AsRef<Path>,
+    write: impl FnOnce(ExportFormat, &mut Vec<u8>) -> Result<(), ExportError>,
+) -> Result<(), ExportError> {
+    let path = path.as_ref();
+    let format = ExportFormat::from_path(path)?;
+    let mut text = Vec::new();
+    write(format, &mut text)?;
+    fs::write(path, text).map_err(|error| ExportError::Io(path.display().to_string(), error))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::settings::MAX_CHART_SAMPLES;
+
+    fn simulation() -> Simulation {
+        let mut simulation = Simulation::with_settings(Settings {
+            seed: Some(5),
+            ..Settings::default()
+        });
+        for _ in 0..10 {
+            simulation.step(0.1);
+        }
+        simulation
+    }
+
+    /// Tests that every chart sample becomes a row with counts and percentages.
+    #[test]
+    fn test_from_simulation() {
+        let simulation = simulation();
+        let series = TimeSeries::from_simulation(&simulation);
+        assert_eq!(series.seed, 5);
+        assert_eq!(series.rows.len(), 11);
+        let first = &series.rows[0];
+        assert_eq!(first.time, 0.0);
+        assert_eq!(
+            (first.susceptible, first.infected, first.recovered),
+            (77, 3, 0)
+        );
+        assert_eq!(first.infected_percent, 3.0 / 80.0 * 100.0);
+    }
+
+    /// Tests that the settings of the run are exported, not UI values waiting for a restart.
+    #[test]
+    fn test_from_simulation_run_settings() {
+        let mut simulation = simulation();
+        let radius = simulation.infected_radius;
+        simulation.community_size = 120;
+        simulation.initial_infected_count = 10;
+        simulation.ui_infected_radius = radius + 5.0;
+        let series = TimeSeries::from_simulation(&simulation);
+        assert_eq!(series.settings.community_size, 80);
+        assert_eq!(series.settings.initial_infected_people, 3);
+        assert_eq!(series.settings.infected_radius, radius);
+        assert_eq!(series.settings.seed, Some(5));
+    }
+
+    /// Tests that a run longer than the chart history is written at a fixed interval.
+    #[test]
+    fn test_time_series_writer() {
+        for extension in ["csv", "json"] {
+            let path = std::env::temp_dir().join(format!("time_series_writer_test.{}", extension));
+            let mut simulation = simulation();
+            let mut writer = TimeSeriesWriter::create(&path, &simulation).unwrap();
+            let samples = MAX_CHART_SAMPLES + 200;
+            for _ in 0..samples {
+                simulation.step(CHART_INTERVAL);
+                writer.record(&simulation).unwrap();
+            }
+            assert!(simulation.total_time.len() < samples);
+            assert_eq!(writer.rows, samples + 1);
+            writer.finish().unwrap();
+
+            let text = fs::read_to_string(&path).unwrap();
+            let times: Vec<f64> = if extension == "csv" {
+                assert!(text.contains("# seed = 5\n"));
+                text.lines()
+                    .skip_while(|line| !line.starts_with("time,"))
+                    .skip(1)
+                    .map(|line| line.split(',').next().unwrap().parse().unwrap())
+                    .collect()
+            } else {
+                let value: serde_json::Value = serde_json::from_str(&text).unwrap();
+                assert_eq!(value["settings"]["community_size"], 80);
+                value["rows"]
+                    .as_array()
+                    .unwrap()
+                    .iter()
+                    .map(|row| row["time"].as_f64().unwrap())
+                    .collect()
+            };
+            assert_eq!(times.len(), samples + 1);
+            assert!(times.windows(2).all(|times| {
+                (times[1] - times[0] - CHART_INTERVAL as f64).abs() < CHART_INTERVAL as f64 / 2.0
+            }));
+            fs::remove_file(path).unwrap();
+        }
+    }
+
+    /// Tests that the CSV has parameter comments, a header and one line per row.
+    #[test]
+    fn test_write_csv() {
+        let series = TimeSeries::from_simulation(&simulation());
+        let mut out = Vec::new();
+        series.write_csv(&mut out).unwrap();
+        let text = String::from_utf8(out).unwrap();
+        assert!(text.contains("# community_size = 80\n"));
+        assert!(text.contains("# seed = 5\n"));
+        let data: Vec<&str> = text.lines().filter(|line| !line.starts_with('#')).collect();
+        assert!(data[0].starts_with("time,susceptible,infected,recovered"));
+        assert_eq!(data.len(), 12);
+        assert_eq!(data[1], "0.0000,77,3,0,96.250,3.750,0.000");
+    }
+
+    /// Tests that the JSON contains the settings and rows.
+    #[test]
+    fn test_write_json() {
+        let series = TimeSeries::from_simulation(&simulation());
+        let mut out = Vec::new();
+        series.write_json(&mut out).unwrap();
+        let value: serde_json::Value = serde_json::from_slice(&out).unwrap();
+        assert_eq!(value["settings"]["community_size"], 80);
+        assert_eq!(value["rows"].as_array().unwrap().len(), 11);
+        assert_eq!(value["rows"][0]["infected"], 3);
+    }
+}
@@ -0,0 +1,635 @@
+//! Frame export module for animations of the community view.
+//!
+//! Renders the community and quarantine areas in software, without a GPU or window,
+//! with the same colors as the UI (`PersonState::person_colors`). The frames of a
+//! headless run are saved as numbered PNG files or as one animated GIF.
+//!
+//! All colors come from a small fixed palette, so frames are stored as palette
+//! indices and both formats use indexed color. The GIF encoder is built in because
+//! it only needs a global palette and LZW compression.
+
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+use eframe::egui;
+
+use crate::export::ExportError;
+use crate::person::PersonState;
+use crate::settings::Settings;
+use crate::simulation::Simulation;
+use crate::strain::STRAIN_COLORS;
+
+/// Default simulation time (in seconds) between two exported frames.
+pub const FRAME_INTERVAL: f32 = 0.1;
+
+/// Number of image pixels per simulation unit.
+pub const FRAME_SCALE: f32 = 2.0;
+
+/// Width (in simulation units) of the area borders, like the UI.
+const BORDER_WIDTH: f32 = 3.0;
+
+/// Returns the palette of all colors used in frames.
+///
+/// The background is black, the area borders white, and people have the colors of
+/// their state.
+pub fn palette() -> Vec<egui::Color32> {
+    let mut palette = vec![
+        egui::Color32::BLACK,
+        egui::Color32::WHITE,
+        PersonState::Susceptible.person_colors(),
+        PersonState::Recovered.person_colors(),
+    ];
+    palette.extend(STRAIN_COLORS);
+    palette
+}
+
+/// An image with one palette index per pixel.
+///
+/// # Fields
+/// - `width`, `height`: Size in pixels
+/// - `pixels`: Palette index of each pixel, row by row
+#[derive(Clone, Debug, PartialEq)]
+pub struct Canvas {
+    pub width: usize,
+    pub height: usize,
+    pub pixels: Vec<u8>,
+}
+
+impl Canvas {
+    /// Creates a canvas filled with the first palette color (black).
+    ///
+    /// # Parameters
+    /// - `width`, `height`: Size in pixels
+    pub fn new(width: usize, height: usize) -> Self {
+        Canvas {
+            width,
+            height,
+            pixels: vec![0; width * height],
+        }
+    }
+
+    /// Fills a rectangle, clipped to the canvas.
+    ///
+    /// # Parameters
+    /// - `left`, `top`, `right`, `bottom`: Edges in pixels (right and bottom excluded)
+    /// - `color`: Palette index
+    fn fill_rect(&mut self, left: f32, top: f32, right: f32, bottom: f32, color: u8) {
+        let x0 = left.round().clamp(0.0, self.width as f32) as usize;
+        let x1 = right.round().clamp(0.0, self.width as f32) as usize;
+        let y0 = top.round().clamp(0.0, self.height as f32) as usize;
+        let y1 = bottom.round().clamp(0.0, self.height as f32) as usize;
+        for y in y0..y1 {
+            self.pixels[y * self.width + x0..y * self.width + x1].fill(color);
+        }
+    }
+
+    /// Draws the outline of a rectangle centered on its edges, like `rect_stroke` in the UI.
+    ///
+    /// # Parameters
+    /// - `left`, `top`, `right`, `bottom`: Edges in pixels
+    /// - `width`: Line width in pixels
+    /// - `color`: Palette index
+    fn stroke_rect(&mut self, left: f32, top: f32, right: f32, bottom: f32, width: f32, color: u8) {
+        let half = width / 2.0;
+        self.fill_rect(left - half, top - half, right + half, top + half, color);
+        self.fill_rect(
+            left - half,
+            bottom - half,
+            right + half,
+            bottom + half,
+            color,
+        );
+        self.fill_rect(left - half, top - half, left + half, bottom + half, color);
+        self.fill_rect(right - half, top - half, right + half, bottom + half, color);
+    }
+
+    /// Fills a circle, clipped to the canvas.
+    ///
+    /// # Parameters
+    /// - `center_x`, `center_y`: Center in pixels
+    /// - `radius`: Radius in pixels
+    /// - `color`: Palette index
+    fn fill_circle(&mut self, center_x: f32, center_y: f32, radius: f32, color: u8) {
+        let x0 = (center_x - radius).floor().max(0.0) as usize;
+        let y0 = (center_y - radius).floor().max(0.0) as usize;
+        let x1 = ((center_x + radius).ceil().max(0.0) as usize).min(self.width);
+        let y1 = ((center_y + radius).ceil().max(0.0) as usize).min(self.height);
+        for y in y0..y1 {
+            for x in x0..x1 {
+                let dx = x as f32 + 0.5 - center_x;
+                let dy = y as f32 + 0.5 - center_y;
+                if dx * dx + dy * dy <= radius * radius {
+                    self.pixels[y * self.width + x] = color;
+                }
+            }
+        }
+    }
+
+    /// Draws a line with round ends, clipped to the canvas.
+    ///
+    /// # Parameters
+    /// - `start`, `end`: Ends of the line in pixels
+    /// - `width`: Line width in pixels
+    /// - `color`: Palette index
+    fn stroke_line(&mut self, start: (f32, f32), end: (f32, f32), width: f32, color: u8) {
+        let length = (end.0 - start.0).hypot(end.1 - start.1);
+        let steps = (length * 2.0).ceil().max(1.0) as usize;
+        for step in 0..=steps {
+            let t = step as f32 / steps as f32;
+            self.fill_circle(
+                start.0 + (end.0 - start.0) * t,
+                start.1 + (end.1 - start.1) * t,
+                width / 2.0,
+                color,
+            );
+        }
+    }
+}
+
+/// Returns the palette index of a color, or white for colors outside the palette.
+///
+/// # Parameters
+/// - `palette`: The palette from `palette()`
+/// - `color`: The color to find
+fn color_index(palette: &[egui::Color32], color: egui::Color32) -> u8 {
+    palette
+        .iter()
+        .position(|&entry| entry == color)
+        .unwrap_or(1) as u8
+}
+
+/// Renders the community and quarantine areas with the same layout as the UI.
+///
+/// # Parameters
+/// - `simulation`: The simulation to draw
+/// - `scale`: Number of pixels per simulation unit
+///
+/// # Returns
+/// The rendered frame
+pub fn render_frame(simulation: &Simulation, scale: f32) -> Canvas {
+    let settings = &simulation.settings;
+    let (width, height) = frame_size(settings, scale);
+    let mut canvas = Canvas::new(width, height);
+    let palette = palette();
+    let border = BORDER_WIDTH * scale;
+
+    let top = settings.border_padding * scale;
+    let community_left = settings.border_padding * scale;
+    let community_width = settings.simulation_area_width * scale;
+    let community_height = settings.simulation_area_height * scale;
+    let quarantine_left =
+        community_left + community_width + settings.gap_community_quarantine * scale;
+    let quarantine_width = settings.quarantine_area_width * scale;
+    let quarantine_height = settings.quarantine_area_height * scale;
+    canvas.stroke_rect(
+        community_left,
+        top,
+        community_left + community_width,
+        top + community_height,
+        border,
+        1,
+    );
+    canvas.stroke_rect(
+        quarantine_left,
+        top,
+        quarantine_left + quarantine_width,
+        top + quarantine_height,
+        border,
+        1,
+    );
+
+    for wall in &settings.walls {
+        canvas.stroke_line(
+            (community_left + wall.x1 * scale, top + wall.y1 * scale),
+            (community_left + wall.x2 * scale, top + wall.y2 * scale),
+            border,
+            1,
+        );
+    }
+
+    for person in &simulation.community {
+        let left = if person.is_in_quarantine {
+            quarantine_left
+        } else {
+            community_left
+        };
+        canvas.fill_circle(
+            left + person.x * scale,
+            top + person.y * scale,
+            settings.person_radius * scale,
+            color_index(&palette, person.state.person_colors()),
+        );
+    }
+    canvas
+}
+
+/// Returns the size (in pixels) of the frames of a simulation.
+///
+/// # Parameters
+/// - `settings`: Settings with the area sizes
+/// - `scale`: Number of pixels per simulation unit
+fn frame_size(settings: &Settings, scale: f32) -> (usize, usize) {
+    let width = settings.border_padding * 2.0
+        + settings.simulation_area_width
+        + settings.gap_community_quarantine
+        + settings.quarantine_area_width;
+    let height = settings.border_padding * 2.0
+        + settings
+            .simulation_area_height
+            .max(settings.quarantine_area_height);
+    (
+        (width * scale).ceil() as usize,
+        (height * scale).ceil() as usize,
+    )
+}
+
+/// Saves a frame as an indexed-color PNG file.
+///
+/// # Parameters
+/// - `path`: Path of the `.png` file
+/// - `canvas`: The frame to save
+pub fn save_png(path: impl AsRef<Path>, canvas: &Canvas) -> Result<(), ExportError> {
+    let path = path.as_ref();
+    let io_error = |error| ExportError::Io(path.display().to_string(), error);
+    let png_error = |error: png::EncodingError| ExportError::Serialize(error.to_string());
+    let file = File::create(path).map_err(io_error)?;
+
+    let mut encoder = png::Encoder::new(
+        BufWriter::new(file),
+        canvas.width as u32,
+        canvas.height as u32,
+    );
+    encoder.set_color(png::ColorType::Indexed);
+    encoder.set_depth(png::BitDepth::Eight);
+    encoder.set_palette(
+        palette()
+            .iter()
+            .flat_map(|color| [color.r(), color.g(), color.b()])
+            .collect::<Vec<u8>>(),
+    );
+    let mut writer = encoder.write_header().map_err(png_error)?;
+    writer.write_image_data(&canvas.pixels).map_err(png_error)?;
+    writer.finish().map_err(png_error)
+}
+
+/// Bits of the palette indices; the palette is padded to `2^GIF_COLOR_BITS` colors.
+const GIF_COLOR_BITS: u8 = 4;
+
+/// Largest code of the GIF LZW compression.
+const GIF_MAX_CODE: u16 = 4095;
+
+/// Writes frames of the same size as a looping animated GIF.
+///
+/// # Fields
+/// - `out`: Where the GIF is written
+/// - `width`, `height`: Size of every frame in pixels
+/// - `delay`: Time between frames in hundredths of a second
+pub struct GifWriter<W: Write> {
+    pub out: W,
+    pub width: usize,
+    pub height: usize,
+    pub delay: u16,
+}
+
+impl<W: Write> GifWriter<W> {
+    /// Writes the GIF header with the palette and starts the animation.
+    ///
+    /// # Parameters
+    /// - `out`: Where the GIF is written
+    /// - `width`, `height`: Size of every frame in pixels
+    /// - `delay`: Time between frames in hundredths of a second
+    pub fn new(mut out: W, width: usize, height: usize, delay: u16) -> std::io::Result<Self> {
+        out.write_all(b"GIF89a")?;
+        out.write_all(&(width as u16).to_le_bytes())?;
+        out.write_all(&(height as u16).to_le_bytes())?;
+        // Global palette with 8-bit color resolution
+        out.write_all(&[0xF0 | (GIF_COLOR_BITS - 1), 0, 0])?;
+        let mut colors = palette();
+        colors.resize(1 << GIF_COLOR_BITS, egui::Color32::BLACK);
+        for color in colors {
+            out.write_all(&[color.r(), color.g(), color.b()])?;
+        }
+        // Loop forever
+        out.write_all(b"\x21\xFF\x0BNETSCAPE2.0\x03\x01\x00\x00\x00")?;
+        Ok(GifWriter {
+            out,
+            width,
+            height,
+            delay,
+        })
+    }
+
+    /// Appends a frame to the animation.
+    ///
+    /// # Parameters
+    /// - `canvas`: The frame, with the size given to `new`
+    pub fn write_frame(&mut self, canvas: &Canvas) -> std::io::Result<()> {
+        let [delay_low, delay_high] = self.delay.to_le_bytes();
+        self.out
+            .write_all(&[0x21, 0xF9, 0x04, 0x00, delay_low, delay_high, 0x00, 0x00])?;
+        self.out.write_all(&[0x2C, 0, 0, 0, 0])?;
+        self.out.write_all(&(self.width as u16).to_le_bytes())?;
+        self.out.write_all(&(self.height as u16).to_le_bytes())?;
+        self.out.write_all(&[0x00, GIF_COLOR_BITS])?;
+        for block in lzw_compress(&canvas.pixels, GIF_COLOR_BITS).chunks(255) {
+            self.out.write_all(&[block.len() as u8])?;
+            self.out.write_all(block)?;
+        }
+        self.out.write_all(&[0x00])
+    }
+
+    /// Ends the animation and flushes the output.
+    pub fn finish(mut self) -> std::io::Result<()> {
+        self.out.write_all(&[0x3B])?;
+        self.out.flush()
+    }
+}
+
+/// Compresses palette indices with the variable-length LZW of the GIF format.
+///
+/// # Parameters
+/// - `indices`: Palette indices, each below `2^min_code_size`
+/// - `min_code_size`: Number of bits of the palette indices
+///
+/// # Returns
+/// The compressed bytes, without the sub-block lengths
+fn lzw_compress(indices: &[u8], min_code_size: u8) -> Vec<u8> {
+    let clear_code = 1u16 << min_code_size;
+    let end_code = clear_code + 1;
+    let mut code_size = min_code_size + 1;
+    let mut next_code = end_code + 1;
+    let mut table: HashMap<(u16, u8), u16> = HashMap::new();
+
+    let mut bits = BitWriter::default();
+    bits.write(clear_code, code_size);
+    let mut pixels = indices.iter();
+    if let Some(&first) = pixels.next() {
+        let mut prefix = first as u16;
+        for &pixel in pixels {
+            if let Some(&code) = table.get(&(prefix, pixel)) {
+                prefix = code;
+                continue;
+            }
+            bits.write(prefix, code_size);
+            // The decoder widens its codes one entry later than the encoder adds them
+            if next_code >= 1 << code_size && code_size < 12 {
+                code_size += 1;
+            }
+            if next_code >= GIF_MAX_CODE {
+                bits.write(clear_code, code_size);
+                table.clear();
+                code_size = min_code_size + 1;
+                next_code = end_code + 1;
+            } else {
+                table.insert((prefix, pixel), next_code);
+                next_code += 1;
+            }
+            prefix = pixel as u16;
+        }
+        bits.write(prefix, code_size);
+        if next_code >= 1 << code_size && code_size < 12 {
+            code_size += 1;
+        }
+    }
+    bits.write(end_code, code_size);
+    bits.finish()
+}
+
+/// Packs codes of varying size into bytes, least significant bit first.
+///
+/// # Fields
+/// - `bytes`: Complete bytes
+/// - `buffer`: Bits which do not fill a byte yet
+/// - `count`: Number of bits in `buffer`
+#[derive(Default)]
+struct BitWriter {
+    bytes: Vec<u8>,
+    buffer: u32,
+    count: u8,
+}
+
+impl BitWriter {
+    /// Appends the lowest `size` bits of a code.
+    fn write(&mut self, code: u16, size: u8) {
+        self.buffer |= (code as u32) << self.count;
+        self.count += size;
+        while self.count >= 8 {
+            self.bytes.push(self.buffer as u8);
+            self.buffer >>= 8;
+            self.count -= 8;
+        }
+    }
+
+    /// Returns the bytes, with the last byte padded with zeros.
+    fn finish(mut self) -> Vec<u8> {
+        if self.count > 0 {
+            self.bytes.push(self.buffer as u8);
+        }
+        self.bytes
+    }
+}
+
+/// Saves frames of a headless run every `interval` simulation seconds.
+///
+/// # Fields
+/// - `png_directory`: Directory the numbered PNG frames are saved to, if any
+/// - `gif`: Animated GIF the frames are appended to, if any
+/// - `gif_path`: Path of the animated GIF, used in error messages
+/// - `interval_ticks`: Number of ticks between two frames
+/// - `scale`: Number of pixels per simulation unit
+/// - `frame_count`: Number of frames saved so far
+pub struct FrameExporter {
+    pub png_directory: Option<PathBuf>,
+    pub gif: Option<GifWriter<BufWriter<File>>>,
+    pub gif_path: PathBuf,
+    pub interval_ticks: usize,
+    pub scale: f32,
+    pub frame_count: usize,
+}
+
+impl FrameExporter {
+    /// Prepares the PNG directory and the GIF file.
+    ///
+    /// # Parameters
+    /// - `png_directory`: Directory for numbered PNG frames, if any (created if missing)
+    /// - `gif_path`: Path of an animated GIF, if any
+    /// - `interval`: Simulation time (in seconds) between two frames
+    /// - `simulation`: The simulation which is exported
+    ///
+    /// # Returns
+    /// The exporter, or the error if the directory or file cannot be created
+    pub fn new(
+        png_directory: Option<PathBuf>,
+        gif_path: Option<PathBuf>,
+        interval: f32,
+        simulation: &Simulation,
+    ) -> Result<Self, ExportError> {
+        if let Some(directory) = &png_directory {
+            fs::create_dir_all(directory)
+                .map_err(|error| ExportError::Io(directory.display().to_string(), error))?;
+        }
+        let time_step = simulation.settings.time_step;
+        let interval_ticks = ((interval / time_step).round() as usize).max(1);
+        let (width, height) = frame_size(&simulation.settings, FRAME_SCALE);
+        if width > u16::MAX as usize || height > u16::MAX as usize {
+            return Err(ExportError::Serialize(String::from(
+                "the areas are too large for a frame",
+            )));
+        }
+
+        let gif = match &gif_path {
+            Some(path) => {
+                let io_error = |error| ExportError::Io(path.display().to_string(), error);
+                let file = File::create(path).map_err(io_error)?;
+                let delay = (interval_ticks as f32 * time_step * 100.0).round() as u16;
+                Some(
+                    GifWriter::new(BufWriter::new(file), width, height, delay.max(2))
+                        .map_err(io_error)?,
+                )
+            }
+            None => None,
+        };
+        Ok(FrameExporter {
+            png_directory,
+            gif,
+            gif_path: gif_path.unwrap_or_default(),
+            interval_ticks,
+            scale: FRAME_SCALE,
+            frame_count: 0,
+        })
+    }
+
+    /// Saves a frame if the tick is a multiple of the frame interval.
+    ///
+    /// # Parameters
+    /// - `tick`: Number of ticks since the start of the run
+    /// - `simulation`: The simulation after the tick
+    pub fn capture(&mut self, tick: usize, simulation: &Simulation) -> Result<(), ExportError> {
+        if !tick.is_multiple_of(self.interval_ticks) {
+            return Ok(());
+        }
+        let canvas = render_frame(simulation, self.scale);
+        if let Some(directory) = &self.png_directory {
+            save_png(
+                directory.join(format!("frame_{:05}.png", self.frame_count)),
+                &canvas,
+            )?;
+        }
+        if let Some(gif) = &mut self.gif {
+            gif.write_frame(&canvas)
+                .map_err(|error| ExportError::Io(self.gif_path.display().to_string(), error))?;
+        }
+        self.frame_count += 1;
+        Ok(())
+    }
+
+    /// Ends the animated GIF.
+    pub fn finish(self) -> Result<(), ExportError> {
+        match self.gif {
+            Some(gif) => gif
+                .finish()
+                .map_err(|error| ExportError::Io(self.gif_path.display().to_string(), error)),
+            None => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Decodes GIF LZW data, to check the encoder.
+    fn lzw_decompress(bytes: &[u8], min_code_size: u8) -> Vec<u8> {
+        let clear_code = 1u16 << min_code_size;
+        let end_code = clear_code + 1;
+        let mut code_size = min_code_size + 1;
+        let mut table: Vec<Vec<u8>> = Vec::new();
+        let reset = |table: &mut Vec<Vec<u8>>| {
+            *table = (0..clear_code).map(|i| vec![i as u8]).collect();
+            table.push(Vec::new());
+            table.push(Vec::new());
+        };
+        reset(&mut table);
+        let mut output = Vec::new();
+        let mut previous: Option<Vec<u8>> = None;
+        let mut position = 0usize;
+        loop {
+            let mut code = 0u16;
+            for bit in 0..code_size as usize {
+                let byte = bytes[(position + bit) / 8];
+                code |= (((byte >> ((position + bit) % 8)) & 1) as u16) << bit;
+            }
+            position += code_size as usize;
+            if code == clear_code {
+                reset(&mut table);
+                code_size = min_code_size + 1;
+                previous = None;
+                continue;
+            }
+            if code == end_code {
+                return output;
+            }
+            let entry = match (table.get(code as usize), &previous) {
+                (Some(entry), _) => entry.clone(),
+                (None, Some(previous)) => [previous.clone(), vec![previous[0]]].concat(),
+                (None, None) => panic!("invalid code"),
+            };
+            output.extend(&entry);
+            if let Some(previous) = previous {
+                table.push([previous, vec![entry[0]]].concat());
+                if table.len() == 1 << code_size && code_size < 12 {
+                    code_size += 1;
+                }
+            }
+            previous = Some(entry);
+        }
+    }
+
+    /// Tests that the LZW data decodes to the original indices, also past the code limit.
+    #[test]
+    fn test_lzw_round_trip() {
+        let mut state = 1u32;
+        let noise: Vec<u8> = (0..20_000)
+            .map(|_| {
+                state = state.wrapping_mul(1_103_515_245).wrapping_add(12_345);
+                ((state >> 16) % 10) as u8
+            })
+            .collect();
+        let flat = vec![3u8; 5000];
+        for indices in [noise, flat, vec![7u8]] {
+            let compressed = lzw_compress(&indices, GIF_COLOR_BITS);
+            assert_eq!(lzw_decompress(&compressed, GIF_COLOR_BITS), indices);
+        }
+    }
+
+    /// Tests that frames use the person colors and are saved as PNG.
+    #[test]
+    fn test_render_and_save_png() {
+        let mut simulation = Simulation::new();
+        // Only one person, so no one else can be drawn over them
+        simulation.community.truncate(1);
+        simulation.community[0].x = 50.0;
+        simulation.community[0].y = 50.0;
+        simulation.community[0].is_in_quarantine = false;
+        simulation.community[0].state = PersonState::Recovered;
+        let canvas = render_frame(&simulation, 1.0);
+        let padding = simulation.settings.border_padding;
+        let center = (padding + 50.0) as usize * canvas.width + (padding + 50.0) as usize;
+        assert_eq!(
+            palette()[canvas.pixels[center] as usize],
+            egui::Color32::GRAY
+        );
+
+        let path = std::env::temp_dir().join("frame_export_test.png");
+        save_png(&path, &canvas).unwrap();
+        let decoder = png::Decoder::new(std::io::BufReader::new(File::open(&path).unwrap()));
+        let mut reader = decoder.read_info().unwrap();
+        let mut pixels = vec![0; reader.output_buffer_size().unwrap()];
+        reader.next_frame(&mut pixels).unwrap();
+        assert_eq!(reader.info().width as usize, canvas.width);
+        assert_eq!(pixels, canvas.pixels);
+        fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,81 @@
+//! Headless module for running the simulation without a window.
+//!
+//! The simulation is stepped in fixed ticks and the number of susceptible, infected
+//! and recovered people is written as CSV once per simulated second.
+
+use std::io::{self, Write};
+
+use crate::simulation::Simulation;
+
+/// Runs the simulation without a window and writes the SIR counts as CSV.
+///
+/// The first row is the header `time,susceptible,infected,recovered`, followed by one row
+/// at the start and one after every simulated second.
+///
+/// # Parameters
+/// - `simulation`: The simulation to run (already restarted with its settings and scenario)
+/// - `duration`: Length (in seconds) of the run
+/// - `out`: Where the CSV is written
+/// - `on_tick`: Called with the tick number at the start (tick 0) and after every tick,
+///   for example to export frames
+///
+/// # Returns
+/// `Ok(())` or the error of writing the output or of `on_tick`
+pub fn run(
+    simulation: &mut Simulation,
+    duration: f32,
+    out: &mut impl Write,
+    mut on_tick: impl FnMut(usize, &Simulation) -> io::Result<()>,
+) -> io::Result<()> {
+    let time_step = simulation.settings.time_step;
+    let ticks_per_second = ((1.0 / time_step).round() as usize).max(1);
+    let total_ticks = (duration / time_step).round() as usize;
+
+    writeln!(out, "time,susceptible,infected,recovered")?;
+    write_row(simulation, out)?;
+    on_tick(0, simulation)?;
+    for tick in 1..=total_ticks {
+        simulation.step(time_step);
+        on_tick(tick, simulation)?;
+        if tick % ticks_per_second == 0 || tick == total_ticks {
+            write_row(simulation, out)?;
+        }
+    }
+    out.flush()
+}
+
+/// Writes one CSV row with the current time and SIR counts.
+///
+/// # Parameters
+/// - `simulation`: The simulation to report
+/// - `out`: Where the row is written
+fn write_row(simulation: &Simulation, out: &mut impl Write) -> io::Result<()> {
+    let (susceptible, infected, recovered) = simulation.count_states();
+    writeln!(
+        out,
+        "{:.3},{},{},{}",
+        simulation.elapsed_time, susceptible, infected, recovered
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::settings::Settings;
+
+    /// Tests that a headless run writes a header and one row per second.
+    #[test]
+    fn test_run_writes_rows() {
+        let mut simulation = Simulation::with_settings(Settings {
+            seed: Some(1),
+            ..Settings::default()
+        });
+        let mut out = Vec::new();
+        run(&mut simulation, 3.0, &mut out, |_, _| Ok(())).unwrap();
+        let text = String::from_utf8(out).unwrap();
+        let lines: Vec<&str> = text.lines().collect();
+        assert_eq!(lines[0], "time,susceptible,infected,recovered");
+        assert_eq!(lines.len(), 5);
+        assert!(lines[4].starts_with("3.000,"));
+    }
+}
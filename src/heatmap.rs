@@ -0,0 +1,80 @@
+//! Heatmap module for where infections happen.
+//!
+//! The community area is divided into a grid of `HEATMAP_CELLS` by `HEATMAP_CELLS` cells and every transmission in the
+//! community is counted in the cell of the newly infected person. Drawn under the
+//! people, the counts show hotspots such as the corners where people are kept inside
+//! the area. Counts are kept across restarts until the heatmap is reset, so several
+//! runs can be combined.
+
+/// Number of cells along each side of the community area.
+pub const HEATMAP_CELLS: usize = 20;
+
+/// Counts of infections in each cell of the community area.
+///
+/// # Fields
+/// - `cells`: Number of cells along each side
+/// - `counts`: Number of infections in each cell, row by row
+#[derive(Clone, Debug, PartialEq)]
+pub struct InfectionHeatmap {
+    pub cells: usize,
+    pub counts: Vec<u32>,
+}
+
+impl Default for InfectionHeatmap {
+    fn default() -> Self {
+        InfectionHeatmap {
+            cells: HEATMAP_CELLS,
+            counts: vec![0; HEATMAP_CELLS * HEATMAP_CELLS],
+        }
+    }
+}
+
+impl InfectionHeatmap {
+    /// Counts an infection at a position in the community area.
+    ///
+    /// Positions outside the area are counted in the nearest cell.
+    ///
+    /// # Parameters
+    /// - `x`, `y`: Position of the infected person
+    /// - `width`, `height`: Size of the community area
+    pub fn record(&mut self, x: f32, y: f32, width: f32, height: f32) {
+        let cell = |position: f32, size: f32| {
+            ((position / size * self.cells as f32).max(0.0) as usize).min(self.cells - 1)
+        };
+        let index = cell(y, height) * self.cells + cell(x, width);
+        self.counts[index] += 1;
+    }
+
+    /// Returns the highest count of a cell.
+    pub fn max_count(&self) -> u32 {
+        self.counts.iter().copied().max().unwrap_or(0)
+    }
+
+    /// Sets all counts to zero.
+    pub fn clear(&mut self) {
+        self.counts.fill(0);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Tests that infections are counted in the cell of their position.
+    #[test]
+    fn test_record() {
+        let mut heatmap = InfectionHeatmap::default();
+        heatmap.record(0.0, 0.0, 200.0, 200.0);
+        heatmap.record(15.0, 25.0, 200.0, 200.0);
+        heatmap.record(215.0, -3.0, 200.0, 200.0);
+        heatmap.record(399.0, 200.0, 400.0, 200.0);
+        assert_eq!(heatmap.counts[0], 1);
+        assert_eq!(heatmap.counts[2 * HEATMAP_CELLS + 1], 1);
+        assert_eq!(heatmap.counts[HEATMAP_CELLS - 1], 1);
+        assert_eq!(heatmap.counts[HEATMAP_CELLS * HEATMAP_CELLS - 1], 1);
+        assert_eq!(heatmap.max_count(), 1);
+
+        heatmap.clear();
+        assert_eq!(heatmap.max_count(), 0);
+    }
+}
@@ -0,0 +1,212 @@
+//! Infection log module for transmission-chain analysis.
+//!
+//! Every infection of a run is recorded with its source, so the log gives who
+//! infected whom, where and when. The infector's infection age at the moment of
+//! transmission is the generation interval used for serial-interval estimates.
+
+use std::io::Write;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use crate::export::{self, ExportError, ExportFormat};
+
+/// Default infection log file shown in the UI.
+pub const DEFAULT_LOG_PATH: &str = "infection_log.csv";
+
+/// Area where an infection happened.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum InfectionLocation {
+    Community,
+    Quarantine,
+}
+
+impl InfectionLocation {
+    /// Returns the name used in exported files.
+    pub fn name(self) -> &'static str {
+        match self {
+            InfectionLocation::Community => "community",
+            InfectionLocation::Quarantine => "quarantine",
+        }
+    }
+}
+
+/// One infection of a run.
+///
+/// # Fields
+/// - `person`: Index of the infected person in the community
+/// - `infector`: Index of the person who transmitted the infection, `None` for seeded
+///   and imported cases
+/// - `time`: Simulation time (in seconds) of the infection
+/// - `strain`: Index of the strain
+/// - `location`: Area where the infection happened
+/// - `infector_infection_age`: How long (in seconds) the infector had been infected,
+///   `None` without an infector
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct InfectionEvent {
+    pub person: usize,
+    pub infector: Option<usize>,
+    pub time: f32,
+    pub strain: usize,
+    pub location: InfectionLocation,
+    pub infector_infection_age: Option<f32>,
+}
+
+/// All infections of a run in the order they happened.
+///
+/// # Fields
+/// - `events`: The recorded infections
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct InfectionLog {
+    pub events: Vec<InfectionEvent>,
+}
+
+impl InfectionLog {
+    /// Records an infection.
+    ///
+    /// # Parameters
+    /// - `event`: The infection to record
+    pub fn record(&mut self, event: InfectionEvent) {
+        self.events.push(event);
+    }
+
+    /// Removes all recorded infections.
+    pub fn clear(&mut self) {
+        self.events.clear();
+    }
+
+    /// Removes a person who left the community from the log.
+    ///
+    /// The infections of the person are dropped, infections they caused keep no
+    /// infector and the indexes of the people after them are shifted down by one,
+    /// like the community vector.
+    ///
+    /// # Parameters
+    /// - `person`: Index of the removed person
+    pub fn remove_person(&mut self, person: usize) {
+        self.events.retain(|event| event.person != person);
+        for event in &mut self.events {
+            if event.person > person {
+                event.person -= 1;
+            }
+            event.infector = match event.infector {
+                Some(infector) if infector == person => None,
+                Some(infector) if infector > person => Some(infector - 1),
+                infector => infector,
+            };
+        }
+    }
+
+    /// Writes the log as CSV with one line per infection.
+    ///
+    /// Missing infectors and infection ages are written as empty values.
+    ///
+    /// # Parameters
+    /// - `out`: Where the CSV is written
+    pub fn write_csv(&self, out: &mut impl Write) -> Result<(), ExportError> {
+        let write_error = |error: std::io::Error| ExportError::Serialize(error.to_string());
+        writeln!(
+            out,
+            "time,person,infector,strain,location,infector_infection_age"
+        )
+        .map_err(write_error)?;
+        for event in &self.events {
+            let infector = event
+                .infector
+                .map(|infector| infector.to_string())
+                .unwrap_or_default();
+            let age = event
+                .infector_infection_age
+                .map(|age| format!("{:.4}", age))
+                .unwrap_or_default();
+            writeln!(
+                out,
+                "{:.4},{},{},{},{},{}",
+                event.time,
+                event.person,
+                infector,
+                event.strain,
+                event.location.name(),
+                age
+            )
+            .map_err(write_error)?;
+        }
+        Ok(())
+    }
+
+    /// Writes the log as pretty-printed JSON.
+    ///
+    /// # Parameters
+    /// - `out`: Where the JSON is written
+    pub fn write_json(&self, out: &mut impl Write) -> Result<(), ExportError> {
+        serde_json::to_writer_pretty(out, self)
+            .map_err(|error| ExportError::Serialize(error.to_string()))
+    }
+
+    /// Saves the log to a CSV or JSON file.
+    ///
+    /// # Parameters
+    /// - `path`: Path of a `.csv` or `.json` file
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), ExportError> {
+        export::save_as(path, |format, out| match format {
+            ExportFormat::Csv => self.write_csv(out),
+            ExportFormat::Json => self.write_json(out),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn log() -> InfectionLog {
+        let mut log = InfectionLog::default();
+        log.record(InfectionEvent {
+            person: 0,
+            infector: None,
+            time: 0.0,
+            strain: 0,
+            location: InfectionLocation::Community,
+            infector_infection_age: None,
+        });
+        log.record(InfectionEvent {
+            person: 4,
+            infector: Some(0),
+            time: 2.5,
+            strain: 0,
+            location: InfectionLocation::Quarantine,
+            infector_infection_age: Some(2.5),
+        });
+        log
+    }
+
+    /// Tests that the CSV has one line per infection with empty values for seeded cases.
+    #[test]
+    fn test_write_csv() {
+        let mut out = Vec::new();
+        log().write_csv(&mut out).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "time,person,infector,strain,location,infector_infection_age\n\
+             0.0000,0,,0,community,\n\
+             2.5000,4,0,0,quarantine,2.5000\n"
+        );
+    }
+
+    /// Tests that removing a person drops their infections and shifts later indexes.
+    #[test]
+    fn test_remove_person() {
+        let mut removed_infector = log();
+        removed_infector.remove_person(0);
+        assert_eq!(removed_infector.events.len(), 1);
+        assert_eq!(removed_infector.events[0].person, 3);
+        assert_eq!(removed_infector.events[0].infector, None);
+
+        let mut removed_between = log();
+        removed_between.remove_person(2);
+        assert_eq!(removed_between.events.len(), 2);
+        assert_eq!(removed_between.events[1].person, 3);
+        assert_eq!(removed_between.events[1].infector, Some(0));
+    }
+}
@@ -0,0 +1,133 @@
+//! Inspector module for the person selected in the community view.
+//!
+//! Clicking a dot selects the person nearest to the pointer. The inspector panel then
+//! shows their current state and, from the infection log, who infected them and whom
+//! they infected.
+
+use crate::infection_log::InfectionLog;
+
+/// Infection history of one person.
+///
+/// # Fields
+/// - `infection_count`: Number of times the person was infected
+/// - `infected_at`: Time (in seconds) of the latest infection, `None` if never infected
+/// - `infector`: Person who caused the latest infection, `None` for seeded and imported
+///   cases or if never infected
+/// - `infectees`: People this person infected, in the order they were infected
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct PersonHistory {
+    pub infection_count: usize,
+    pub infected_at: Option<f32>,
+    pub infector: Option<usize>,
+    pub infectees: Vec<usize>,
+}
+
+impl PersonHistory {
+    /// Collects the infection history of a person from the infection log.
+    ///
+    /// Infections after `until` are left out, so a replayed moment shows the history up
+    /// to that moment.
+    ///
+    /// # Parameters
+    /// - `log`: The infection log of the run
+    /// - `person`: Index of the person in the community
+    /// - `until`: Time (in seconds) of the last infection to include
+    ///
+    /// # Returns
+    /// The infection history of the person
+    pub fn from_log(log: &InfectionLog, person: usize, until: f32) -> Self {
+        let mut history = PersonHistory::default();
+        for event in log.events.iter().take_while(|event| event.time <= until) {
+            if event.person == person {
+                history.infection_count += 1;
+                history.infected_at = Some(event.time);
+                history.infector = event.infector;
+            }
+            if event.infector == Some(person) {
+                history.infectees.push(event.person);
+            }
+        }
+        history
+    }
+}
+
+/// Finds the person nearest to a point.
+///
+/// # Parameters
+/// - `positions`: Position of each person, by index
+/// - `x`, `y`: The point, such as the pointer position
+/// - `max_distance`: Largest distance at which a person is found
+///
+/// # Returns
+/// The index of the nearest person within `max_distance`, if any
+pub fn nearest_person(
+    positions: &[(f32, f32)],
+    x: f32,
+    y: f32,
+    max_distance: f32,
+) -> Option<usize> {
+    positions
+        .iter()
+        .enumerate()
+        .map(|(index, &(px, py))| (index, (px - x).hypot(py - y)))
+        .filter(|&(_, distance)| distance <= max_distance)
+        .min_by(|a, b| a.1.total_cmp(&b.1))
+        .map(|(index, _)| index)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::infection_log::{InfectionEvent, InfectionLocation};
+
+    fn event(person: usize, infector: Option<usize>, time: f32) -> InfectionEvent {
+        InfectionEvent {
+            person,
+            infector,
+            time,
+            strain: 0,
+            location: InfectionLocation::Community,
+            infector_infection_age: None,
+        }
+    }
+
+    /// Tests that the history has the latest infector and all infectees.
+    #[test]
+    fn test_person_history() {
+        let log = InfectionLog {
+            events: vec![
+                event(0, None, 0.0),
+                event(1, Some(0), 1.0),
+                event(2, Some(0), 2.0),
+                event(0, Some(2), 12.0),
+                event(3, Some(0), 13.0),
+            ],
+        };
+        let history = PersonHistory::from_log(&log, 0, f32::INFINITY);
+        assert_eq!(history.infection_count, 2);
+        assert_eq!(history.infected_at, Some(12.0));
+        assert_eq!(history.infector, Some(2));
+        assert_eq!(history.infectees, vec![1, 2, 3]);
+
+        assert_eq!(
+            PersonHistory::from_log(&log, 4, f32::INFINITY),
+            PersonHistory::default()
+        );
+
+        // A replayed moment only shows the infections until then
+        let history = PersonHistory::from_log(&log, 0, 2.0);
+        assert_eq!(history.infection_count, 1);
+        assert_eq!(history.infected_at, Some(0.0));
+        assert_eq!(history.infector, None);
+        assert_eq!(history.infectees, vec![1, 2]);
+    }
+
+    /// Tests that the nearest person within the distance is found.
+    #[test]
+    fn test_nearest_person() {
+        let positions = [(10.0, 10.0), (20.0, 10.0), (14.0, 10.0)];
+        assert_eq!(nearest_person(&positions, 11.0, 10.0, 6.0), Some(0));
+        assert_eq!(nearest_person(&positions, 13.0, 11.0, 6.0), Some(2));
+        assert_eq!(nearest_person(&positions, 50.0, 50.0, 6.0), None);
+    }
+}
@@ -1,21 +1,239 @@
+mod chart_svg;
+mod chart_view;
+mod cli;
+mod collision;
+mod config;
+mod downsample;
+mod ensemble;
+mod export;
+mod frame_export;
+mod headless;
+mod heatmap;
+mod infection_log;
+mod inspector;
+mod ode;
+mod offspring;
+mod paint;
 mod person;
+mod recording;
+mod rt;
+mod scenario;
 mod settings;
 mod simulation;
+mod snapshot;
+mod strain;
+mod sweep;
+mod transmission_tree;
+mod view;
+mod wall;
 
-use crate::settings::*;
 use eframe::egui;
 use simulation::Simulation;
 
+/// Creates the simulation from the command line options.
+///
+/// The settings are loaded from `--config`, replaced by the settings of the scenario if
+/// it has any, and `--seed` overrides the seed of both.
+///
+/// # Parameters
+/// - `options`: The parsed command line options
+///
+/// # Returns
+/// The simulation and the scenario duration, or an error message
+fn simulation_from_options(options: &cli::CliOptions) -> Result<(Simulation, Option<f32>), String> {
+    let mut settings = match &options.config {
+        Some(path) => config::load_settings(path).map_err(|error| error.to_string())?,
+        None => settings::Settings::default(),
+    };
+    if options.seed.is_some() {
+        settings.seed = options.seed;
+    }
+    let mut simulation = Simulation::with_settings(settings);
+
+    if let Some(path) = &options.snapshot {
+        snapshot::load_snapshot(path)
+            .map_err(|error| error.to_string())?
+            .restore(&mut simulation);
+        let duration = simulation
+            .scenario
+            .as_ref()
+            .and_then(|scenario| scenario.duration);
+        return Ok((simulation, duration));
+    }
+
+    let mut duration = None;
+    if let Some(path) = &options.scenario {
+        let mut scenario = scenario::load_scenario(path).map_err(|error| error.to_string())?;
+        if let (Some(seed), Some(settings)) = (options.seed, scenario.settings.as_mut()) {
+            settings.seed = Some(seed);
+        }
+        duration = scenario.duration;
+        simulation
+            .set_scenario(scenario)
+            .map_err(|error| error.to_string())?;
+    }
+    Ok((simulation, duration))
+}
+
+/// Runs the simulation, an ensemble with `--ensemble` or a sweep with `--sweep`,
+/// without a window.
+///
+/// The CSV is printed to standard output and the results are saved to the files given
+/// with `--export`, `--export-log`, `--export-chart` and `--save-snapshot`. Frames of the community view
+/// are saved with `--frames` and `--gif`.
+///
+/// # Parameters
+/// - `options`: The parsed command line options
+/// - `simulation`: The simulation created from the options
+/// - `duration`: Length (in seconds) of the run
+///
+/// # Returns
+/// `Ok(())` or an error message
+fn run_headless(
+    options: &cli::CliOptions,
+    simulation: &mut Simulation,
+    duration: f32,
+) -> Result<(), String> {
+    let mut out = std::io::stdout().lock();
+    if let Some(runs) = options.ensemble {
+        let ensemble = ensemble::run_ensemble(
+            &simulation.current_settings(),
+            simulation.scenario.as_ref(),
+            simulation.seed,
+            runs,
+            duration,
+        )
+        .map_err(|error| error.to_string())?;
+        ensemble
+            .write_bands_csv(&mut out)
+            .map_err(|error| error.to_string())?;
+        if let Some(path) = &options.export {
+            ensemble
+                .save_runs(path)
+                .map_err(|error| error.to_string())?;
+        }
+        return Ok(());
+    }
+
+    if !options.sweep.is_empty() {
+        let sweep = sweep::run_sweep(
+            &simulation.current_settings(),
+            simulation.scenario.as_ref(),
+            &options.sweep,
+            simulation.seed,
+            options.seeds,
+            duration,
+        )
+        .map_err(|error| error.to_string())?;
+        sweep
+            .write_csv(&mut out)
+            .map_err(|error| error.to_string())?;
+        if let Some(path) = &options.export {
+            sweep.save(path).map_err(|error| error.to_string())?;
+        }
+        return Ok(());
+    }
+
+    let mut frames = if options.frames.is_some() || options.gif.is_some() {
+        Some(
+            frame_export::FrameExporter::new(
+                options.frames.clone(),
+                options.gif.clone(),
+                options.frame_interval,
+                simulation,
+            )
+            .map_err(|error| error.to_string())?,
+        )
+    } else {
+        None
+    };
+    // The time series is written while the run goes on, so it is never downsampled
+    let mut series = match &options.export {
+        Some(path) => Some(
+            export::TimeSeriesWriter::create(path, simulation)
+                .map_err(|error| error.to_string())?,
+        ),
+        None => None,
+    };
+    headless::run(simulation, duration, &mut out, |tick, simulation| {
+        if let Some(frames) = &mut frames {
+            frames
+                .capture(tick, simulation)
+                .map_err(|error| std::io::Error::other(error.to_string()))?;
+        }
+        if let Some(series) = &mut series {
+            series
+                .record(simulation)
+                .map_err(|error| std::io::Error::other(error.to_string()))?;
+        }
+        Ok(())
+    })
+    .map_err(|error| error.to_string())?;
+    if let Some(frames) = frames {
+        frames.finish().map_err(|error| error.to_string())?;
+    }
+    if let Some(series) = series {
+        series.finish().map_err(|error| error.to_string())?;
+    }
+    if let Some(path) = &options.export_chart {
+        chart_svg::save_svg(path, simulation).map_err(|error| error.to_string())?;
+    }
+    if let Some(path) = &options.export_log {
+        simulation
+            .infection_log
+            .save(path)
+            .map_err(|error| error.to_string())?;
+    }
+    if let Some(path) = &options.save_snapshot {
+        snapshot::save_snapshot(path, simulation).map_err(|error| error.to_string())?;
+    }
+    Ok(())
+}
+
 fn main() {
+    let options = match cli::parse_args(std::env::args().skip(1)) {
+        Ok(options) => options,
+        Err(error) => {
+            eprintln!("Error: {}\n\n{}", error, cli::USAGE);
+            std::process::exit(1);
+        }
+    };
+    if options.help {
+        println!("{}", cli::USAGE);
+        return;
+    }
+
+    let (mut simulation, scenario_duration) = match simulation_from_options(&options) {
+        Ok(result) => result,
+        Err(error) => {
+            eprintln!("Error: {}", error);
+            std::process::exit(1);
+        }
+    };
+
+    if options.headless {
+        let duration = options
+            .duration
+            .or(scenario_duration)
+            .unwrap_or(cli::DEFAULT_HEADLESS_DURATION);
+        if let Err(error) = run_headless(&options, &mut simulation, duration) {
+            eprintln!("Error: {}", error);
+            std::process::exit(1);
+        }
+        return;
+    }
+
+    simulation.recording_enabled = true;
     let options = eframe::NativeOptions {
         viewport: egui::ViewportBuilder::default()
-            .with_inner_size([SIMULATION_AREA_SIZE * 3.0, SIMULATION_AREA_SIZE * 3.0]),
+            .with_inner_size(settings::WINDOW_SIZE)
+            .with_min_inner_size(settings::MIN_WINDOW_SIZE),
         ..Default::default()
     };
     let result = eframe::run_native(
         "Epidemic Simulation - SIR Model",
         options,
-        Box::new(|_cc| Ok(Box::new(Simulation::new()))),
+        Box::new(|_cc| Ok(Box::new(simulation))),
     );
 
     match result {
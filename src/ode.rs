@@ -0,0 +1,269 @@
+//! Deterministic SIR model for comparison with the agent-based simulation.
+//!
+//! The classic compartmental model is
+//!
+//! ```text
+//! dS/dt = -β S I
+//! dI/dt =  β S I - γ I
+//! dR/dt =  γ I
+//! ```
+//!
+//! with S, I and R as fractions of the community. The agent-based model has no
+//! latent period, so SIR (not SEIR) is the matching textbook model.
+//!
+//! β is derived from the agent parameters with the kinetic theory of gases: a
+//! susceptible person sweeps a strip of width `2 × infected_radius` at the mean
+//! relative speed of two people walking in random directions, `4/π × moving_speed`,
+//! so it meets `2 r v_rel N / A` people per second. Each meeting lasts as long as it
+//! takes to cross the infection circle (mean chord `π r / 2`), and during that time the
+//! per-tick infection probability gives the chance of transmission. γ is
+//! `1 / recovered_day`. The model assumes well-mixed people and ignores walls,
+//! quarantine and social distancing, which is where it disagrees with the agents.
+
+use crate::settings::Settings;
+
+/// Time step (in seconds) of the numerical solution.
+pub const ODE_STEP: f32 = 0.05;
+
+/// Rates of the SIR model.
+///
+/// # Fields
+/// - `beta`: Transmission rate (per second)
+/// - `gamma`: Recovery rate (per second)
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SirParameters {
+    pub beta: f32,
+    pub gamma: f32,
+}
+
+impl SirParameters {
+    /// Derives the rates from the agent parameters of the original strain.
+    ///
+    /// # Parameters
+    /// - `settings`: Settings with the area size, speed, tick length and disease parameters
+    /// - `community_size`: Number of people in the community
+    /// - `infected_radius`: Distance that infected people can spread disease
+    ///
+    /// # Returns
+    /// The SIR rates
+    pub fn from_agents(settings: &Settings, community_size: usize, infected_radius: f32) -> Self {
+        let area = settings.simulation_area_width * settings.simulation_area_height;
+        let density = community_size as f32 / area;
+        let relative_speed = 4.0 / std::f32::consts::PI * settings.moving_speed;
+        let encounter_rate = 2.0 * infected_radius * relative_speed * density;
+
+        let hazard = -(1.0 - settings.infection_probability.min(0.999)).ln() / settings.time_step;
+        let contact_time = std::f32::consts::PI * infected_radius / 2.0 / relative_speed;
+        let transmission = 1.0 - (-hazard * contact_time).exp();
+
+        SirParameters {
+            beta: encounter_rate * transmission,
+            gamma: 1.0 / settings.recovered_day,
+        }
+    }
+
+    /// Returns the basic reproduction number `R0 = β / γ`.
+    pub fn basic_reproduction_number(&self) -> f32 {
+        self.beta / self.gamma
+    }
+}
+
+/// State of the SIR model as fractions of the community.
+///
+/// # Fields
+/// - `susceptible`, `infected`, `recovered`: Fraction of people in each state
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SirState {
+    pub susceptible: f32,
+    pub infected: f32,
+    pub recovered: f32,
+}
+
+impl SirState {
+    /// Returns the time derivative of the state.
+    ///
+    /// # Parameters
+    /// - `parameters`: The SIR rates
+    fn derivative(&self, parameters: &SirParameters) -> SirState {
+        let infections = parameters.beta * self.susceptible * self.infected;
+        let recoveries = parameters.gamma * self.infected;
+        SirState {
+            susceptible: -infections,
+            infected: infections - recoveries,
+            recovered: recoveries,
+        }
+    }
+
+    /// Returns `self + other × factor`.
+    fn add_scaled(&self, other: &SirState, factor: f32) -> SirState {
+        SirState {
+            susceptible: self.susceptible + other.susceptible * factor,
+            infected: self.infected + other.infected * factor,
+            recovered: self.recovered + other.recovered * factor,
+        }
+    }
+}
+
+/// Solution of the SIR model which grows with the run.
+///
+/// The solution is kept between frames, so only the steps after the last solved time
+/// are computed when the run goes on.
+///
+/// # Fields
+/// - `parameters`: The SIR rates
+/// - `initial`: State at time 0
+/// - `points`: The time and state every `ODE_STEP` seconds from 0
+#[derive(Clone, Debug, PartialEq)]
+pub struct SirSolution {
+    pub parameters: SirParameters,
+    pub initial: SirState,
+    pub points: Vec<(f32, SirState)>,
+}
+
+impl SirSolution {
+    /// Creates a solution with only the initial state.
+    ///
+    /// # Parameters
+    /// - `parameters`: The SIR rates
+    /// - `initial`: State at time 0
+    pub fn new(parameters: SirParameters, initial: SirState) -> Self {
+        SirSolution {
+            parameters,
+            initial,
+            points: vec![(0.0, initial)],
+        }
+    }
+
+    /// Solves the model with the fourth-order Runge-Kutta method until `end_time`.
+    ///
+    /// # Parameters
+    /// - `end_time`: Last time (in seconds) the solution must reach
+    pub fn extend_to(&mut self, end_time: f32) {
+        let steps = step_count(end_time);
+        let parameters = self.parameters;
+        while self.points.len() <= steps {
+            let (_, state) = self.points[self.points.len() - 1];
+            let k1 = state.derivative(&parameters);
+            let k2 = state
+                .add_scaled(&k1, ODE_STEP / 2.0)
+                .derivative(&parameters);
+            let k3 = state
+                .add_scaled(&k2, ODE_STEP / 2.0)
+                .derivative(&parameters);
+            let k4 = state.add_scaled(&k3, ODE_STEP).derivative(&parameters);
+            let next = state
+                .add_scaled(&k1, ODE_STEP / 6.0)
+                .add_scaled(&k2, ODE_STEP / 3.0)
+                .add_scaled(&k3, ODE_STEP / 3.0)
+                .add_scaled(&k4, ODE_STEP / 6.0);
+            self.points
+                .push((self.points.len() as f32 * ODE_STEP, next));
+        }
+    }
+
+    /// Returns the solved points until `end_time`.
+    ///
+    /// # Parameters
+    /// - `end_time`: Last time (in seconds) of the returned points
+    ///
+    /// # Returns
+    /// The time and state every `ODE_STEP` seconds from 0 to `end_time`, or as far as
+    /// the model is solved
+    pub fn until(&self, end_time: f32) -> &[(f32, SirState)] {
+        let steps = step_count(end_time).min(self.points.len() - 1);
+        &self.points[..=steps]
+    }
+}
+
+/// Returns the number of `ODE_STEP` steps needed to reach `end_time`.
+fn step_count(end_time: f32) -> usize {
+    (end_time / ODE_STEP).ceil().max(0.0) as usize
+}
+
+/// Solves the SIR model with the fourth-order Runge-Kutta method.
+///
+/// # Parameters
+/// - `parameters`: The SIR rates
+/// - `initial`: State at time 0
+/// - `end_time`: Last time (in seconds) of the solution
+///
+/// # Returns
+/// The time and state every `ODE_STEP` seconds from 0 to `end_time`
+pub fn solve_sir(
+    parameters: &SirParameters,
+    initial: SirState,
+    end_time: f32,
+) -> Vec<(f32, SirState)> {
+    let mut solution = SirSolution::new(*parameters, initial);
+    solution.extend_to(end_time);
+    solution.points
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Tests that β grows with density and radius, and γ is the inverse duration.
+    #[test]
+    fn test_parameters_from_agents() {
+        let settings = Settings::default();
+        let parameters = SirParameters::from_agents(&settings, 80, 3.5);
+        assert_eq!(parameters.gamma, 1.0 / 8.0);
+        assert!(parameters.beta > 0.0);
+        assert!(SirParameters::from_agents(&settings, 160, 3.5).beta > parameters.beta);
+        assert!(SirParameters::from_agents(&settings, 80, 7.0).beta > parameters.beta);
+    }
+
+    /// Tests that the solution keeps the population constant and follows R0.
+    #[test]
+    fn test_solve_sir() {
+        let initial = SirState {
+            susceptible: 0.99,
+            infected: 0.01,
+            recovered: 0.0,
+        };
+        let growing = SirParameters {
+            beta: 0.5,
+            gamma: 0.1,
+        };
+        let solution = solve_sir(&growing, initial, 100.0);
+        assert_eq!(solution.len(), 2001);
+        for (_, state) in &solution {
+            let total = state.susceptible + state.infected + state.recovered;
+            assert!((total - 1.0).abs() < 1e-4);
+        }
+        let peak = solution
+            .iter()
+            .map(|(_, state)| state.infected)
+            .fold(0.0, f32::max);
+        assert!(peak > 0.4);
+
+        let fading = SirParameters {
+            beta: 0.05,
+            gamma: 0.1,
+        };
+        let solution = solve_sir(&fading, initial, 100.0);
+        assert!(solution.iter().all(|(_, state)| state.infected <= 0.01));
+    }
+
+    /// Tests that extending a solution step by step gives the same points as solving at once.
+    #[test]
+    fn test_extend_solution() {
+        let parameters = SirParameters {
+            beta: 0.5,
+            gamma: 0.1,
+        };
+        let initial = SirState {
+            susceptible: 0.99,
+            infected: 0.01,
+            recovered: 0.0,
+        };
+        let mut solution = SirSolution::new(parameters, initial);
+        for end_time in [0.3, 7.0, 20.0] {
+            solution.extend_to(end_time);
+        }
+        assert_eq!(solution.points, solve_sir(&parameters, initial, 20.0));
+        assert_eq!(solution.until(1.0).len(), 21);
+        assert_eq!(solution.until(50.0).len(), 401);
+    }
+}
@@ -0,0 +1,137 @@
+//! Offspring distribution module for superspreading analysis.
+//!
+//! The offspring distribution is the number of secondary cases caused by each
+//! infected person. With heterogeneous infectiousness most cases infect nobody
+//! while a few superspreaders cause most of the transmission.
+
+use crate::person::Person;
+
+/// Summary of the secondary cases caused by everyone who has been infected.
+///
+/// # Fields
+/// - `frequencies`: `frequencies[n]` is the number of cases that infected exactly `n` people
+/// - `total_cases`: Number of people who have been infected (currently infected or recovered)
+/// - `mean`: Mean number of secondary cases (the realised reproduction number)
+/// - `variance`: Variance of the number of secondary cases
+/// - `top_20_percent_share`: Share of all transmission caused by the most infectious 20% of cases
+pub struct OffspringDistribution {
+    pub frequencies: Vec<usize>,
+    pub total_cases: usize,
+    pub mean: f32,
+    pub variance: f32,
+    pub top_20_percent_share: f32,
+}
+
+impl OffspringDistribution {
+    /// Builds the offspring distribution from the people in a community.
+    ///
+    /// Only people who have been infected are counted. People who are still
+    /// infected may cause more cases, so the result is final only when the epidemic is over.
+    ///
+    /// # Parameters
+    /// - `community`: All people in the simulation
+    ///
+    /// # Returns
+    /// The offspring distribution of the community
+    pub fn from_community(community: &[Person]) -> Self {
+        let mut secondary_cases: Vec<usize> = community
+            .iter()
+            .filter(|person| !person.is_susceptible())
+            .map(|person| person.secondary_cases)
+            .collect();
+        let total_cases = secondary_cases.len();
+
+        let max_cases = secondary_cases.iter().copied().max().unwrap_or(0);
+        let mut frequencies = vec![0; max_cases + 1];
+        for &cases in &secondary_cases {
+            frequencies[cases] += 1;
+        }
+
+        if total_cases == 0 {
+            return Self {
+                frequencies,
+                total_cases,
+                mean: 0.0,
+                variance: 0.0,
+                top_20_percent_share: 0.0,
+            };
+        }
+
+        let total_transmission: usize = secondary_cases.iter().sum();
+        let mean = total_transmission as f32 / total_cases as f32;
+        let variance = secondary_cases
+            .iter()
+            .map(|&cases| (cases as f32 - mean).powi(2))
+            .sum::<f32>()
+            / total_cases as f32;
+
+        secondary_cases.sort_unstable_by(|a, b| b.cmp(a));
+        let top_count = ((total_cases as f32 * 0.2).ceil() as usize).max(1);
+        let top_transmission: usize = secondary_cases.iter().take(top_count).sum();
+        let top_20_percent_share = if total_transmission == 0 {
+            0.0
+        } else {
+            top_transmission as f32 / total_transmission as f32
+        };
+
+        Self {
+            frequencies,
+            total_cases,
+            mean,
+            variance,
+            top_20_percent_share,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::person::PersonState;
+    use crate::settings::Settings;
+
+    /// Creates a person with the given state and number of secondary cases.
+    fn case(state: PersonState, secondary_cases: usize) -> Person {
+        let mut person = Person::new(&Settings::default(), &mut rand::thread_rng());
+        person.state = state;
+        person.secondary_cases = secondary_cases;
+        person
+    }
+
+    /// Tests that only infected and recovered people are counted and the histogram is correct.
+    #[test]
+    fn test_from_community_frequencies() {
+        let community = vec![
+            case(PersonState::Recovered, 0),
+            case(PersonState::Recovered, 0),
+            case(PersonState::Recovered, 3),
+            case(PersonState::Infected(0), 1),
+            case(PersonState::Susceptible, 0),
+        ];
+        let distribution = OffspringDistribution::from_community(&community);
+        assert_eq!(distribution.total_cases, 4);
+        assert_eq!(distribution.frequencies, vec![2, 1, 0, 1]);
+        assert_eq!(distribution.mean, 1.0);
+        assert_eq!(distribution.variance, 1.5);
+    }
+
+    /// Tests that the top 20% share is computed from the most infectious cases.
+    #[test]
+    fn test_from_community_top_20_percent_share() {
+        let mut community: Vec<Person> = (0..8).map(|_| case(PersonState::Recovered, 0)).collect();
+        community.push(case(PersonState::Recovered, 8));
+        community.push(case(PersonState::Recovered, 2));
+        let distribution = OffspringDistribution::from_community(&community);
+        assert_eq!(distribution.top_20_percent_share, 1.0);
+    }
+
+    /// Tests that a community without cases gives an empty distribution.
+    #[test]
+    fn test_from_community_no_cases() {
+        let community = vec![case(PersonState::Susceptible, 0)];
+        let distribution = OffspringDistribution::from_community(&community);
+        assert_eq!(distribution.total_cases, 0);
+        assert_eq!(distribution.mean, 0.0);
+        assert_eq!(distribution.frequencies, vec![0]);
+    }
+}
@@ -0,0 +1,104 @@
+//! Paint module for changing the community with the mouse.
+//!
+//! With a paint tool selected, clicking or dragging in the community area infects,
+//! immunises, adds or removes people under the brush instead of selecting a person.
+//! This places outbreaks and vaccinated groups exactly where a demonstration needs them.
+//! Walls are drawn by dragging from one end to the other and erased with the brush.
+
+use crate::person::Person;
+
+/// What clicking or dragging in the community area does.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum PaintTool {
+    #[default]
+    Select,
+    Infect,
+    Immunise,
+    Add,
+    Remove,
+    Wall,
+    EraseWall,
+}
+
+impl PaintTool {
+    /// All tools in the order they are shown in the UI.
+    pub const ALL: [PaintTool; 7] = [
+        PaintTool::Select,
+        PaintTool::Infect,
+        PaintTool::Immunise,
+        PaintTool::Add,
+        PaintTool::Remove,
+        PaintTool::Wall,
+        PaintTool::EraseWall,
+    ];
+
+    /// Returns the name shown in the UI.
+    pub fn name(self) -> &'static str {
+        match self {
+            PaintTool::Select => "Select",
+            PaintTool::Infect => "Infect",
+            PaintTool::Immunise => "Immunise",
+            PaintTool::Add => "Add",
+            PaintTool::Remove => "Remove",
+            PaintTool::Wall => "Draw wall",
+            PaintTool::EraseWall => "Erase wall",
+        }
+    }
+
+    /// Returns `true` when the tool acts on everything under a round brush.
+    pub fn uses_brush(self) -> bool {
+        matches!(
+            self,
+            PaintTool::Infect | PaintTool::Immunise | PaintTool::Remove | PaintTool::EraseWall
+        )
+    }
+}
+
+/// Finds the people in the community area under the brush.
+///
+/// People in the quarantine area are never under the brush.
+///
+/// # Parameters
+/// - `community`: All people
+/// - `x`, `y`: Center of the brush in the community area
+/// - `radius`: Radius of the brush
+///
+/// # Returns
+/// The indexes of the people under the brush, in ascending order
+pub fn brush_targets(community: &[Person], x: f32, y: f32, radius: f32) -> Vec<usize> {
+    community
+        .iter()
+        .enumerate()
+        .filter(|(_, person)| {
+            !person.is_in_quarantine && (person.x - x).hypot(person.y - y) <= radius
+        })
+        .map(|(index, _)| index)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::settings::Settings;
+
+    /// Tests that only people in the community area within the radius are targeted.
+    #[test]
+    fn test_brush_targets() {
+        let mut rng = rand::thread_rng();
+        let mut community: Vec<Person> = (0..4)
+            .map(|_| Person::new(&Settings::default(), &mut rng))
+            .collect();
+        for (person, (x, y)) in community.iter_mut().zip([
+            (100.0, 100.0),
+            (110.0, 100.0),
+            (130.0, 100.0),
+            (100.0, 105.0),
+        ]) {
+            person.x = x;
+            person.y = y;
+        }
+        community[3].is_in_quarantine = true;
+        assert_eq!(brush_targets(&community, 102.0, 100.0, 15.0), vec![0, 1]);
+        assert!(brush_targets(&community, 300.0, 300.0, 15.0).is_empty());
+    }
+}
@@ -5,20 +5,22 @@
 
 use eframe::egui;
 use rand::Rng;
+use serde::{Deserialize, Serialize};
 
-use crate::settings::*;
+use crate::settings::Settings;
+use crate::strain::strain_color;
 
 /// It represents a person's state in three states of SIR epidemic model.
 ///
 /// # Fields
 /// - `Susceptible`: People who haven't gotten sick yet but could catch the disease
-/// - `Infected`: People who are currently sick and can spread the disease to others
+/// - `Infected`: People who are currently sick with a strain (by index) and can spread it to others
 /// - `Recovered`: People who got better and now have immunity (or died/got isolated and
 ///   can't spread it anymore)
-#[derive(PartialEq)]
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
 pub enum PersonState {
     Susceptible,
-    Infected,
+    Infected(usize),
     Recovered,
 }
 
@@ -26,14 +28,14 @@ impl PersonState {
     /// Returns the color that represetns each person state in the UI.
     ///
     /// - `Susceptible`: Blue
-    /// - `Infected`: Red
+    /// - `Infected`: The strain color (red for the original strain)
     /// - `Recovered`: Gray
     ///
     /// # Returns
     /// An `egui::Color32` value corresponding to the current state
     pub fn person_colors(&self) -> egui::Color32 {
         match self {
-            PersonState::Infected => egui::Color32::RED,
+            PersonState::Infected(strain) => strain_color(*strain),
             PersonState::Recovered => egui::Color32::GRAY,
             PersonState::Susceptible => egui::Color32::BLUE,
         }
@@ -52,6 +54,10 @@ impl PersonState {
 /// - `state`: Current health state (Susceptible, Infected, or Recovered)
 /// - `infection_duration`: Time elapsed since infection started, used to determine when recovery occurs
 /// - `is_in_quarantine`: Determine whether a person is in the quarantine area.
+/// - `infectiousness`: Relative infectiousness while infected (`1.0` is the population average)
+/// - `secondary_cases`: Number of people this person has infected
+/// - `recovered_from`: Strains this person has recovered from
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub struct Person {
     pub x: f32,
     pub y: f32,
@@ -60,6 +66,9 @@ pub struct Person {
     pub state: PersonState,
     pub infection_duration: f32,
     pub is_in_quarantine: bool,
+    pub infectiousness: f32,
+    pub secondary_cases: usize,
+    pub recovered_from: Vec<usize>,
 }
 
 impl Person {
@@ -68,22 +77,64 @@ impl Person {
     /// The initital person has:
     /// - Random position within the community area
     /// - Random movement direction
-    /// - Constant speed defined by `MOVING_SPEED`
+    /// - Constant speed defined by `moving_speed`
     /// - The initial state is `Susceptible`
     ///
+    /// # Parameters
+    /// - `settings`: The simulation settings
+    /// - `rng`: Random number generator of the simulation
+    ///
     /// # Returns
     /// A new `Person` instance ready to participate in the simulation
-    pub fn new() -> Self {
-        let mut rng = rand::thread_rng();
+    pub fn new(settings: &Settings, rng: &mut impl Rng) -> Self {
         let direction = rng.gen_range(0.0..std::f32::consts::TAU);
         Self {
-            x: rng.gen_range(0.0..SIMULATION_AREA_SIZE),
-            y: rng.gen_range(0.0..SIMULATION_AREA_SIZE),
-            velocity_x: direction.cos() * MOVING_SPEED,
-            velocity_y: direction.sin() * MOVING_SPEED,
+            x: rng.gen_range(0.0..settings.simulation_area_width),
+            y: rng.gen_range(0.0..settings.simulation_area_height),
+            velocity_x: direction.cos() * settings.moving_speed,
+            velocity_y: direction.sin() * settings.moving_speed,
             state: PersonState::Susceptible,
             infection_duration: 0.0,
             is_in_quarantine: false,
+            infectiousness: 1.0,
+            secondary_cases: 0,
+            recovered_from: Vec::new(),
+        }
+    }
+
+    /// Infects the person with a strain.
+    ///
+    /// The infection clock starts from zero and the person keeps the given
+    /// infectiousness until recovery.
+    ///
+    /// # Parameters
+    /// - `strain`: Index of the strain
+    /// - `infectiousness`: Relative infectiousness drawn for this infection
+    pub fn infect(&mut self, strain: usize, infectiousness: f32) {
+        self.state = PersonState::Infected(strain);
+        self.infection_duration = 0.0;
+        self.infectiousness = infectiousness;
+    }
+
+    /// Recovers the person from the current infection.
+    ///
+    /// The strain is remembered in `recovered_from` for immunity against later infections.
+    pub fn recover(&mut self) {
+        if let Some(strain) = self.strain() {
+            self.recovered_from.push(strain);
+        }
+        self.infection_duration = 0.0;
+        self.state = PersonState::Recovered;
+    }
+
+    /// Returns the strain the person is infected with.
+    ///
+    /// # Returns
+    /// `Some(strain)` if the person is infected, `None` otherwise
+    pub fn strain(&self) -> Option<usize> {
+        match self.state {
+            PersonState::Infected(strain) => Some(strain),
+            _ => None,
         }
     }
 
@@ -104,33 +155,57 @@ impl Person {
     /// - Their velocity is reversed.
     /// - Their position is clamped to the boundary to prevent wall penetration
     ///
+    /// In the community area, people also bounce off the walls inside the area.
+    ///
     /// # Parameters
     /// - `time_frame_per_second`: Time delta for this frame
-    pub fn update_position(&mut self, time_frame_per_second: f32) {
+    /// - `settings`: The simulation settings with the area sizes and walls
+    pub fn update_position(&mut self, time_frame_per_second: f32, settings: &Settings) {
+        let previous = (self.x, self.y);
         self.x += self.velocity_x * time_frame_per_second;
         self.y += self.velocity_y * time_frame_per_second;
+        self.bounce(previous, settings);
+    }
 
-        let max_size = if self.is_in_quarantine {
-            QUARANTINE_AREA_SIZE
-        } else {
-            SIMULATION_AREA_SIZE
-        };
+    /// Bounces the person off the border of its area and the walls after a movement.
+    ///
+    /// # Parameters
+    /// - `previous`: Position before the movement, to find walls the person moved through
+    /// - `settings`: The simulation settings with the area sizes and walls
+    pub fn bounce(&mut self, previous: (f32, f32), settings: &Settings) {
+        let (width, height) = settings.area_size(self.is_in_quarantine);
+        let margin = settings.margin_from_wall;
 
-        if self.x <= MARGIN_FROM_WALL {
+        if self.x <= margin {
             self.velocity_x = -self.velocity_x;
-            self.x = MARGIN_FROM_WALL;
+            self.x = margin;
         }
-        if self.x >= max_size - MARGIN_FROM_WALL {
+        if self.x >= width - margin {
             self.velocity_x = -self.velocity_x;
-            self.x = max_size - MARGIN_FROM_WALL;
+            self.x = width - margin;
         }
-        if self.y <= MARGIN_FROM_WALL {
+        if self.y <= margin {
             self.velocity_y = -self.velocity_y;
-            self.y = MARGIN_FROM_WALL;
+            self.y = margin;
         }
-        if self.y >= max_size - MARGIN_FROM_WALL {
+        if self.y >= height - margin {
             self.velocity_y = -self.velocity_y;
-            self.y = max_size - MARGIN_FROM_WALL;
+            self.y = height - margin;
+        }
+
+        if !self.is_in_quarantine {
+            let mut position = (self.x, self.y);
+            let mut velocity = (self.velocity_x, self.velocity_y);
+            for wall in &settings.walls {
+                wall.collide(
+                    previous,
+                    &mut position,
+                    &mut velocity,
+                    settings.person_radius,
+                );
+            }
+            (self.x, self.y) = position;
+            (self.velocity_x, self.velocity_y) = velocity;
         }
     }
 
@@ -147,7 +222,7 @@ impl Person {
     /// # Returns
     /// `true` if the person is infected to infection, `false` otherwise
     pub fn is_infected(&self) -> bool {
-        matches!(self.state, PersonState::Infected)
+        matches!(self.state, PersonState::Infected(_))
     }
 
     /// Calculates the Euclidean distance between this person and another person.
@@ -169,11 +244,12 @@ impl Person {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::settings::*;
     use eframe::egui;
     /// Tests that creating a new person places them within the SIMULATION_AREA_SIZE.
     #[test]
     fn test_create_new_person() {
-        let person = Person::new();
+        let person = Person::new(&Settings::default(), &mut rand::thread_rng());
         assert!(matches!(person.state, PersonState::Susceptible));
         assert_eq!(person.infection_duration, 0.0);
         assert!(person.x >= 0.0 && person.x <= SIMULATION_AREA_SIZE);
@@ -191,8 +267,11 @@ mod tests {
             state: PersonState::Susceptible,
             infection_duration: 0.0,
             is_in_quarantine: false,
+            infectiousness: 1.0,
+            secondary_cases: 0,
+            recovered_from: Vec::new(),
         };
-        person.update_position(1.0);
+        person.update_position(1.0, &Settings::default());
         assert_eq!(person.x, 152.0);
         assert_eq!(person.y, 22.0);
     }
@@ -209,8 +288,11 @@ mod tests {
             state: PersonState::Susceptible,
             infection_duration: 0.0,
             is_in_quarantine: false,
+            infectiousness: 1.0,
+            secondary_cases: 0,
+            recovered_from: Vec::new(),
         };
-        person.update_position(1.0);
+        person.update_position(1.0, &Settings::default());
         assert_eq!(person.x, MARGIN_FROM_WALL);
         assert_eq!(person.velocity_x, 2.0);
         assert_eq!(person.velocity_y, 2.0);
@@ -227,6 +309,9 @@ mod tests {
             state: PersonState::Susceptible,
             infection_duration: 0.0,
             is_in_quarantine: false,
+            infectiousness: 1.0,
+            secondary_cases: 0,
+            recovered_from: Vec::new(),
         };
         let person2 = Person {
             x: 10.0,
@@ -236,6 +321,9 @@ mod tests {
             state: PersonState::Susceptible,
             infection_duration: 0.0,
             is_in_quarantine: false,
+            infectiousness: 1.0,
+            secondary_cases: 0,
+            recovered_from: Vec::new(),
         };
         let distance = person1.calculate_distance(&person2);
         assert_eq!(distance, 4.0);
@@ -252,6 +340,9 @@ mod tests {
             state: PersonState::Susceptible,
             infection_duration: 0.0,
             is_in_quarantine: false,
+            infectiousness: 1.0,
+            secondary_cases: 0,
+            recovered_from: Vec::new(),
         };
         assert!(person.is_susceptible());
     }
@@ -264,21 +355,98 @@ mod tests {
             y: 23.0,
             velocity_x: 2.0,
             velocity_y: 2.0,
-            state: PersonState::Infected,
+            state: PersonState::Infected(0),
             infection_duration: 0.0,
             is_in_quarantine: false,
+            infectiousness: 1.0,
+            secondary_cases: 0,
+            recovered_from: Vec::new(),
         };
         assert!(person.is_infected());
     }
 
+    /// Tests that infecting a person resets the infection clock and keeps the infectiousness.
+    #[test]
+    fn test_infect() {
+        let mut person = Person::new(&Settings::default(), &mut rand::thread_rng());
+        person.infection_duration = 3.0;
+        person.infect(1, 2.5);
+        assert!(person.is_infected());
+        assert_eq!(person.strain(), Some(1));
+        assert_eq!(person.infection_duration, 0.0);
+        assert_eq!(person.infectiousness, 2.5);
+    }
+
+    /// Tests that recovering remembers the strain for immunity.
+    #[test]
+    fn test_recover() {
+        let mut person = Person::new(&Settings::default(), &mut rand::thread_rng());
+        person.infect(1, 1.0);
+        person.recover();
+        assert!(matches!(person.state, PersonState::Recovered));
+        assert_eq!(person.strain(), None);
+        assert_eq!(person.recovered_from, vec![1]);
+    }
+
     /// Tests that the person state matches the corresponding color in the UI.
     #[test]
     fn test_person_colors() {
-        assert_eq!(PersonState::Infected.person_colors(), egui::Color32::RED);
+        assert_eq!(PersonState::Infected(0).person_colors(), egui::Color32::RED);
         assert_eq!(PersonState::Recovered.person_colors(), egui::Color32::GRAY);
         assert_eq!(
             PersonState::Susceptible.person_colors(),
             egui::Color32::BLUE
         );
     }
+
+    /// Tests that a person bounces off a wall inside the community and stays on its side.
+    #[test]
+    fn test_update_position_wall() {
+        let settings = Settings {
+            walls: vec![crate::wall::Wall {
+                x1: 200.0,
+                y1: 0.0,
+                x2: 200.0,
+                y2: SIMULATION_AREA_SIZE,
+            }],
+            ..Settings::default()
+        };
+        let mut person = Person::new(&settings, &mut rand::thread_rng());
+        person.x = 150.0;
+        person.y = 100.0;
+        person.velocity_x = MOVING_SPEED;
+        person.velocity_y = 5.0;
+        let mut bounced = false;
+        for _ in 0..120 {
+            person.update_position(TIME_STEP, &settings);
+            assert!(person.x <= 200.0 - settings.person_radius + 1e-3);
+            bounced |= person.velocity_x < 0.0;
+        }
+        assert!(bounced);
+    }
+
+    /// Tests that people are placed and kept inside non-square areas.
+    #[test]
+    fn test_non_square_areas() {
+        let settings = Settings {
+            simulation_area_width: 600.0,
+            simulation_area_height: 150.0,
+            quarantine_area_width: 120.0,
+            quarantine_area_height: 300.0,
+            ..Settings::default()
+        };
+        let mut rng = rand::thread_rng();
+        for _ in 0..100 {
+            let mut person = Person::new(&settings, &mut rng);
+            assert!(person.x < 600.0 && person.y < 150.0);
+            person.is_in_quarantine = rng.gen_bool(0.5);
+            for _ in 0..300 {
+                person.update_position(TIME_STEP, &settings);
+            }
+            let (width, height) = settings.area_size(person.is_in_quarantine);
+            let margin = settings.margin_from_wall;
+            assert!(person.x >= margin && person.x <= width - margin);
+            assert!(person.y >= margin && person.y <= height - margin);
+        }
+    }
 }
@@ -0,0 +1,363 @@
+//! Recording module for scrubbing back through a run.
+//!
+//! Every tick the positions, states and quarantine flags of all people are recorded so
+//! the community view can be replayed at any earlier moment. A full snapshot (a
+//! keyframe) is kept every `KEYFRAME_INTERVAL` ticks. Runs are deterministic, so the
+//! simulation can be rewound to any recorded tick by restoring the keyframe before it
+//! and stepping forward. Keyframes leave out the chart history and the infection log,
+//! which only grow and are truncated instead. Removing a person rewrites the infection
+//! log, so the keyframes before a removal are dropped: the earlier frames can still be
+//! shown, but the run can only be resumed from after the removal.
+
+use std::mem;
+
+use crate::person::{Person, PersonState};
+use crate::simulation::Simulation;
+use crate::snapshot::SimulationSnapshot;
+
+/// Number of ticks between two keyframes.
+///
+/// More keyframes make rewinding faster but use more memory.
+pub const KEYFRAME_INTERVAL: usize = 300;
+
+/// Maximum memory (in bytes) used by the frames and keyframes of a recording.
+///
+/// Recording stops when it is reached, so a long run cannot use up the memory. With
+/// the default community this is about 7 minutes at the default time step.
+pub const MAX_RECORDING_BYTES: usize = 64 * 1024 * 1024;
+
+/// What is drawn of a person in a recorded frame.
+///
+/// # Fields
+/// - `x`, `y`: Position in the community or quarantine area
+/// - `state`: Health state
+/// - `is_in_quarantine`: Determine whether the person is in the quarantine area
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct RecordedPerson {
+    pub x: f32,
+    pub y: f32,
+    pub state: PersonState,
+    pub is_in_quarantine: bool,
+}
+
+impl From<&Person> for RecordedPerson {
+    fn from(person: &Person) -> Self {
+        RecordedPerson {
+            x: person.x,
+            y: person.y,
+            state: person.state,
+            is_in_quarantine: person.is_in_quarantine,
+        }
+    }
+}
+
+/// The community at one tick.
+///
+/// # Fields
+/// - `time`: Simulation time (in seconds) of the tick
+/// - `people`: Every person of the community
+#[derive(Clone, Debug, PartialEq)]
+pub struct Frame {
+    pub time: f32,
+    pub people: Vec<RecordedPerson>,
+}
+
+impl Frame {
+    /// Returns the approximate memory (in bytes) used by the frame.
+    fn bytes(&self) -> usize {
+        mem::size_of::<Frame>() + self.people.len() * mem::size_of::<RecordedPerson>()
+    }
+}
+
+/// A full snapshot taken while recording.
+///
+/// # Fields
+/// - `frame`: Index of the frame the snapshot was taken at
+/// - `snapshot`: The simulation state without the chart history and the infection log
+/// - `log_length`: Number of infections in the infection log at the snapshot
+#[derive(Clone, Debug, PartialEq)]
+pub struct Keyframe {
+    pub frame: usize,
+    pub snapshot: SimulationSnapshot,
+    pub log_length: usize,
+}
+
+impl Keyframe {
+    /// Returns the approximate memory (in bytes) used by the keyframe.
+    fn bytes(&self) -> usize {
+        mem::size_of::<Keyframe>() + self.snapshot.community.len() * mem::size_of::<Person>()
+    }
+}
+
+/// Recorded frames and keyframes of the current run.
+///
+/// # Fields
+/// - `frames`: One frame per tick since recording started
+/// - `keyframes`: A keyframe every `KEYFRAME_INTERVAL` frames, starting at the first frame
+///   or at the last removal of people
+/// - `bytes`: Approximate memory (in bytes) used by the frames and keyframes
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Recording {
+    pub frames: Vec<Frame>,
+    pub keyframes: Vec<Keyframe>,
+    pub bytes: usize,
+}
+
+impl Recording {
+    /// Records the current tick of a simulation.
+    ///
+    /// Nothing is recorded when the recording is full.
+    ///
+    /// # Parameters
+    /// - `simulation`: The simulation after the tick
+    pub fn record(&mut self, simulation: &Simulation) {
+        if self.is_full() {
+            return;
+        }
+        if self.frames.len().is_multiple_of(KEYFRAME_INTERVAL) {
+            self.push_keyframe(simulation);
+        }
+        self.push_frame(simulation);
+    }
+
+    /// Records a change made to the simulation between ticks, such as painting people.
+    ///
+    /// The latest frame is replaced by the changed state and a keyframe is taken, so
+    /// rewinding to that frame or later starts from the changed state. When people were
+    /// removed, the earlier keyframes no longer match the infection log and are dropped.
+    /// Nothing is recorded before the first frame.
+    ///
+    /// # Parameters
+    /// - `simulation`: The changed simulation
+    pub fn record_edit(&mut self, simulation: &Simulation) {
+        let Some(last) = self.frames.pop() else {
+            return;
+        };
+        self.bytes -= last.bytes();
+        let frame = self.frames.len();
+        let removed_people = simulation.community.len() < last.people.len();
+        let bytes = &mut self.bytes;
+        self.keyframes.retain(|key| {
+            let keep = !removed_people && key.frame < frame;
+            if !keep {
+                *bytes -= key.bytes();
+            }
+            keep
+        });
+        self.push_keyframe(simulation);
+        self.push_frame(simulation);
+    }
+
+    /// Returns `true` if the run can be rewound to a recorded frame.
+    ///
+    /// # Parameters
+    /// - `frame`: Index of the frame
+    pub fn can_rewind(&self, frame: usize) -> bool {
+        self.keyframes.iter().any(|key| key.frame <= frame)
+    }
+
+    /// Takes a keyframe for the next frame.
+    ///
+    /// # Parameters
+    /// - `simulation`: The simulation at the next frame
+    fn push_keyframe(&mut self, simulation: &Simulation) {
+        let mut snapshot = SimulationSnapshot::from_simulation(simulation);
+        snapshot.total_time.clear();
+        snapshot.infected_chart.clear();
+        snapshot.susceptible_chart.clear();
+        snapshot.recovered_chart.clear();
+        snapshot.state_counts.clear();
+        snapshot.strain_charts.iter_mut().for_each(Vec::clear);
+        snapshot.infection_log.events.clear();
+        let keyframe = Keyframe {
+            frame: self.frames.len(),
+            snapshot,
+            log_length: simulation.infection_log.events.len(),
+        };
+        self.bytes += keyframe.bytes();
+        self.keyframes.push(keyframe);
+    }
+
+    /// Records the people of the next frame.
+    ///
+    /// # Parameters
+    /// - `simulation`: The simulation at the next frame
+    fn push_frame(&mut self, simulation: &Simulation) {
+        let frame = Frame {
+            time: simulation.elapsed_time,
+            people: simulation
+                .community
+                .iter()
+                .map(RecordedPerson::from)
+                .collect(),
+        };
+        self.bytes += frame.bytes();
+        self.frames.push(frame);
+    }
+
+    /// Adds up the memory used by the frames and keyframes again after some were dropped.
+    fn update_bytes(&mut self) {
+        self.bytes = self.frames.iter().map(Frame::bytes).sum::<usize>()
+            + self.keyframes.iter().map(Keyframe::bytes).sum::<usize>();
+    }
+
+    /// Returns `true` when no more frames are recorded.
+    pub fn is_full(&self) -> bool {
+        self.bytes >= MAX_RECORDING_BYTES
+    }
+
+    /// Removes all frames and keyframes.
+    pub fn clear(&mut self) {
+        *self = Recording::default();
+    }
+
+    /// Rewinds a simulation to a recorded frame and drops the frames after it.
+    ///
+    /// The keyframe before the frame is restored, the chart history and the infection log
+    /// are truncated to its time and the remaining ticks are simulated again without
+    /// counting their infections in the heatmap a second time. Nothing happens when there
+    /// is no keyframe before the frame.
+    ///
+    /// # Parameters
+    /// - `frame`: Index of the frame to rewind to
+    /// - `simulation`: The simulation which was recorded
+    pub fn rewind(&mut self, frame: usize, simulation: &mut Simulation) {
+        let Some(keyframe) = self.keyframes.iter().rev().find(|key| key.frame <= frame) else {
+            return;
+        };
+        let frame = frame.min(self.frames.len() - 1);
+
+        let mut total_time = mem::take(&mut simulation.total_time);
+        let keyframe_time = keyframe.snapshot.elapsed_time + simulation.settings.time_step / 2.0;
+        let chart_length = total_time
+            .iter()
+            .take_while(|&&time| time <= keyframe_time)
+            .count();
+        let mut infected_chart = mem::take(&mut simulation.infected_chart);
+        let mut susceptible_chart = mem::take(&mut simulation.susceptible_chart);
+        let mut recovered_chart = mem::take(&mut simulation.recovered_chart);
+        let mut state_counts = mem::take(&mut simulation.state_counts);
+        let mut strain_charts = mem::take(&mut simulation.strain_charts);
+        let mut infection_log = mem::take(&mut simulation.infection_log);
+        total_time.truncate(chart_length);
+        infected_chart.truncate(chart_length);
+        susceptible_chart.truncate(chart_length);
+        recovered_chart.truncate(chart_length);
+        state_counts.truncate(chart_length);
+        strain_charts.truncate(keyframe.snapshot.strain_charts.len());
+        strain_charts
+            .iter_mut()
+            .for_each(|chart| chart.truncate(chart_length));
+        infection_log.events.truncate(keyframe.log_length);
+
+        keyframe.snapshot.clone().restore(simulation);
+        simulation.total_time = total_time;
+        simulation.infected_chart = infected_chart;
+        simulation.susceptible_chart = susceptible_chart;
+        simulation.recovered_chart = recovered_chart;
+        simulation.state_counts = state_counts;
+        simulation.strain_charts = strain_charts;
+        simulation.infection_log = infection_log;
+        // The infections of the ticks simulated again are already in the heatmap
+        let heatmap = mem::take(&mut simulation.heatmap);
+        for _ in keyframe.frame..frame {
+            simulation.step(simulation.settings.time_step);
+        }
+        simulation.heatmap = heatmap;
+
+        self.frames.truncate(frame + 1);
+        self.keyframes.retain(|key| key.frame <= frame);
+        self.update_bytes();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::settings::Settings;
+
+    /// Tests that rewinding gives the same state as the original run at that tick.
+    #[test]
+    fn test_rewind_same_state() {
+        let mut simulation = Simulation::with_settings(Settings {
+            seed: Some(4),
+            ..Settings::default()
+        });
+        let mut recording = Recording::default();
+        recording.record(&simulation);
+        let mut expected = None;
+        for tick in 1..=400 {
+            simulation.step(simulation.settings.time_step);
+            recording.record(&simulation);
+            if tick == 350 {
+                expected = Some(SimulationSnapshot::from_simulation(&simulation));
+            }
+        }
+        assert_eq!(recording.frames.len(), 401);
+        assert_eq!(recording.keyframes.len(), 2);
+        assert!(
+            recording
+                .keyframes
+                .iter()
+                .all(|key| key.snapshot.infection_log.events.is_empty())
+        );
+
+        recording.rewind(350, &mut simulation);
+        assert_eq!(
+            Some(SimulationSnapshot::from_simulation(&simulation)),
+            expected
+        );
+        assert_eq!(recording.frames.len(), 351);
+        assert_eq!(
+            recording.frames[350].people,
+            simulation
+                .community
+                .iter()
+                .map(RecordedPerson::from)
+                .collect::<Vec<_>>()
+        );
+    }
+
+    /// Tests that rewinding does not count the infections simulated again in the heatmap.
+    #[test]
+    fn test_rewind_keeps_heatmap() {
+        let mut simulation = Simulation::with_settings(Settings {
+            seed: Some(4),
+            infected_radius: 20.0,
+            ..Settings::default()
+        });
+        let mut recording = Recording::default();
+        recording.record(&simulation);
+        for _ in 1..=400 {
+            simulation.step(simulation.settings.time_step);
+            recording.record(&simulation);
+        }
+        let keyframe_time = recording.keyframes[1].snapshot.elapsed_time;
+        let replayed = simulation
+            .infection_log
+            .events
+            .iter()
+            .filter(|event| event.infector.is_some() && event.time > keyframe_time)
+            .count();
+        assert!(replayed > 0);
+        let total: u32 = simulation.heatmap.counts.iter().sum();
+
+        recording.rewind(399, &mut simulation);
+        assert_eq!(simulation.heatmap.counts.iter().sum::<u32>(), total);
+    }
+
+    /// Tests that recording stops at the memory limit.
+    #[test]
+    fn test_recording_memory_limit() {
+        let simulation = Simulation::new();
+        let mut recording = Recording::default();
+        while !recording.is_full() {
+            recording.record(&simulation);
+        }
+        let frames = recording.frames.len();
+        assert!(recording.bytes >= MAX_RECORDING_BYTES);
+        assert!(recording.bytes < MAX_RECORDING_BYTES + recording.frames[0].bytes() * 2);
+        recording.record(&simulation);
+        assert_eq!(recording.frames.len(), frames);
+    }
+}
@@ -0,0 +1,144 @@
+//! Effective reproduction number (Rt) module.
+//!
+//! Rt is computed in time bins from the infection log in two ways:
+//! - The "true" cohort Rt is the mean number of secondary cases of the infections
+//!   in each bin. Recent cohorts are still infectious, so their value grows until
+//!   they recover.
+//! - The estimated Rt uses the incidence alone, like it is done with surveillance
+//!   data: the local infections in a window divided by the infection pressure of
+//!   earlier infections, assuming people are equally infectious during the whole
+//!   infectious period (renewal equation).
+
+use crate::infection_log::InfectionLog;
+use crate::transmission_tree::TransmissionTree;
+
+/// Width (in seconds) of the time bins of the Rt chart.
+pub const RT_BIN: f32 = 1.0;
+
+/// Number of bins which are summed for the incidence-based estimate.
+///
+/// A longer window gives a smoother but later estimate.
+pub const RT_WINDOW: usize = 3;
+
+/// Rt over time.
+///
+/// # Fields
+/// - `times`: Start time (in seconds) of each bin
+/// - `cohort`: Mean secondary cases of the infections in each bin, `None` without infections
+/// - `estimated`: Incidence-based estimate for each bin, `None` without earlier infections
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct RtSeries {
+    pub times: Vec<f32>,
+    pub cohort: Vec<Option<f32>>,
+    pub estimated: Vec<Option<f32>>,
+}
+
+impl RtSeries {
+    /// Computes both Rt series from an infection log.
+    ///
+    /// # Parameters
+    /// - `log`: The infection log of the run
+    /// - `end_time`: Current simulation time (in seconds)
+    /// - `infectious_period`: How long (in seconds) people are infectious
+    ///
+    /// # Returns
+    /// The Rt series with one value per complete bin (infections in the current bin are
+    /// left out until it is complete)
+    pub fn from_log(log: &InfectionLog, end_time: f32, infectious_period: f32) -> Self {
+        let bin_count = (end_time / RT_BIN).floor() as usize;
+
+        let tree = TransmissionTree::from_log(log);
+        let mut infections = vec![0usize; bin_count];
+        let mut secondary_cases = vec![0usize; bin_count];
+        let mut local_incidence = vec![0.0f32; bin_count];
+        for (node, event) in tree.nodes.iter().zip(&log.events) {
+            let index = (event.time / RT_BIN) as usize;
+            if index >= bin_count {
+                continue;
+            }
+            infections[index] += 1;
+            secondary_cases[index] += node.children.len();
+            if event.infector.is_some() {
+                local_incidence[index] += 1.0;
+            }
+        }
+
+        let cohort = infections
+            .iter()
+            .zip(&secondary_cases)
+            .map(|(&infections, &secondary)| {
+                (infections > 0).then(|| secondary as f32 / infections as f32)
+            })
+            .collect();
+
+        // Infection pressure of earlier infections: each infection is equally
+        // infectious for `infectious_period` after it happened.
+        let period_bins = ((infectious_period / RT_BIN).round() as usize).max(1);
+        let weight = 1.0 / period_bins as f32;
+        let pressure: Vec<f32> = (0..bin_count)
+            .map(|t| {
+                (1..=period_bins.min(t))
+                    .map(|s| infections[t - s] as f32 * weight)
+                    .sum()
+            })
+            .collect();
+        let estimated = (0..bin_count)
+            .map(|t| {
+                let start = (t + 1).saturating_sub(RT_WINDOW);
+                let cases: f32 = local_incidence[start..=t].iter().sum();
+                let expected: f32 = pressure[start..=t].iter().sum();
+                (expected > 0.0).then(|| cases / expected)
+            })
+            .collect();
+
+        RtSeries {
+            times: (0..bin_count).map(|t| t as f32 * RT_BIN).collect(),
+            cohort,
+            estimated,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::infection_log::{InfectionEvent, InfectionLocation};
+
+    fn event(person: usize, infector: Option<usize>, time: f32) -> InfectionEvent {
+        InfectionEvent {
+            person,
+            infector,
+            time,
+            strain: 0,
+            location: InfectionLocation::Community,
+            infector_infection_age: None,
+        }
+    }
+
+    /// Tests that the cohort Rt is the mean number of secondary cases per bin.
+    #[test]
+    fn test_cohort_rt() {
+        let mut log = InfectionLog::default();
+        log.record(event(0, None, 0.0));
+        log.record(event(1, None, 0.5));
+        log.record(event(2, Some(0), 1.5));
+        log.record(event(3, Some(0), 1.7));
+        log.record(event(4, Some(0), 2.2));
+        let series = RtSeries::from_log(&log, 3.0, 2.0);
+        assert_eq!(series.times, vec![0.0, 1.0, 2.0]);
+        assert_eq!(series.cohort, vec![Some(1.5), Some(0.0), Some(0.0)]);
+    }
+
+    /// Tests the incidence-based estimate with a constant number of infections.
+    #[test]
+    fn test_estimated_rt() {
+        let mut log = InfectionLog::default();
+        log.record(event(0, None, 0.0));
+        for second in 1..6 {
+            log.record(event(second, Some(second - 1), second as f32));
+        }
+        let series = RtSeries::from_log(&log, 6.0, 1.0);
+        assert_eq!(series.estimated[0], None);
+        assert_eq!(series.estimated[5], Some(1.0));
+    }
+}
@@ -0,0 +1,267 @@
+//! Scenario module for scripted simulation runs.
+//!
+//! A scenario is a TOML or JSON file with optional settings and a list of timed
+//! events, for example seeding infections or enabling quarantine at a given time.
+//! The simulation plays the events back at fixed ticks, so a scenario with a seed
+//! gives the same run in the UI and in headless mode.
+//!
+//! ```toml
+//! duration = 60.0
+//!
+//! [settings]
+//! seed = 42
+//!
+//! [[events]]
+//! action = "set_quarantine"
+//! time = 10.0
+//! enabled = true
+//! ```
+
+use std::fs;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use crate::config::{self, ConfigError, ConfigFormat};
+use crate::settings::Settings;
+
+/// Default path of the scenario file used by the UI.
+pub const DEFAULT_SCENARIO_PATH: &str = "scenario.toml";
+
+/// A timed event of a scenario.
+///
+/// # Variants
+/// - `SeedInfections`: Infects `count` random people in the community who are not infected
+/// - `ImportCases`: Adds `count` new infected people to the community
+/// - `SetQuarantine`: Enables or disables quarantine
+/// - `SetSocialDistancing`: Enables or disables social distancing, optionally with a new radius
+/// - `SetInfectedRadius`: Changes the distance that infected people can spread disease
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "action", rename_all = "snake_case", deny_unknown_fields)]
+pub enum ScenarioEvent {
+    SeedInfections {
+        time: f32,
+        count: usize,
+        #[serde(default)]
+        strain: usize,
+    },
+    ImportCases {
+        time: f32,
+        count: usize,
+        #[serde(default)]
+        strain: usize,
+    },
+    SetQuarantine {
+        time: f32,
+        enabled: bool,
+    },
+    SetSocialDistancing {
+        time: f32,
+        enabled: bool,
+        radius: Option<f32>,
+    },
+    SetInfectedRadius {
+        time: f32,
+        radius: f32,
+    },
+}
+
+impl ScenarioEvent {
+    /// Returns the simulation time (in seconds) when the event happens.
+    pub fn time(&self) -> f32 {
+        match self {
+            ScenarioEvent::SeedInfections { time, .. }
+            | ScenarioEvent::ImportCases { time, .. }
+            | ScenarioEvent::SetQuarantine { time, .. }
+            | ScenarioEvent::SetSocialDistancing { time, .. }
+            | ScenarioEvent::SetInfectedRadius { time, .. } => *time,
+        }
+    }
+
+    /// Returns the strain used by the event, if any.
+    pub fn strain(&self) -> Option<usize> {
+        match self {
+            ScenarioEvent::SeedInfections { strain, .. }
+            | ScenarioEvent::ImportCases { strain, .. } => Some(*strain),
+            _ => None,
+        }
+    }
+}
+
+/// A scripted simulation run.
+///
+/// # Fields
+/// - `settings`: Settings used for the run instead of the current settings, if given
+/// - `duration`: Length (in seconds) of a headless run, if given
+/// - `events`: Timed events sorted by time
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Scenario {
+    pub settings: Option<Settings>,
+    pub duration: Option<f32>,
+    pub events: Vec<ScenarioEvent>,
+}
+
+/// Loads and validates a scenario file.
+///
+/// # Parameters
+/// - `path`: Path of a `.toml` or `.json` scenario file
+///
+/// # Returns
+/// The validated scenario with events sorted by time, or the error explaining what is wrong
+pub fn load_scenario(path: impl AsRef<Path>) -> Result<Scenario, ConfigError> {
+    let path = path.as_ref();
+    let format = ConfigFormat::from_path(path)?;
+    let text = fs::read_to_string(path)
+        .map_err(|error| ConfigError::Io(path.display().to_string(), error))?;
+    parse_scenario(&text, format)
+}
+
+/// Parses and validates a scenario from the text of a scenario file.
+///
+/// # Parameters
+/// - `text`: Content of the scenario file
+/// - `format`: Format of the content
+///
+/// # Returns
+/// The validated scenario with events sorted by time, or the error explaining what is wrong
+pub fn parse_scenario(text: &str, format: ConfigFormat) -> Result<Scenario, ConfigError> {
+    let mut scenario: Scenario = config::parse_file(text, format, Some("settings"))?;
+    if let Some(settings) = &scenario.settings {
+        config::validate_settings(settings)?;
+    }
+    validate_events(&scenario)?;
+    scenario
+        .events
+        .sort_by(|a, b| a.time().total_cmp(&b.time()));
+    Ok(scenario)
+}
+
+/// Checks that the duration and all events are in a range the simulation can use.
+///
+/// # Parameters
+/// - `scenario`: The scenario to check
+///
+/// # Returns
+/// `Ok(())` or `ConfigError::Invalid` with one message per problem
+fn validate_events(scenario: &Scenario) -> Result<(), ConfigError> {
+    let mut problems = Vec::new();
+    if let Some(duration) = scenario.duration
+        && (!duration.is_finite() || duration <= 0.0)
+    {
+        problems.push(format!(
+            "`duration` must be a finite number greater than 0, got {}",
+            duration
+        ));
+    }
+    for (index, event) in scenario.events.iter().enumerate() {
+        let time = event.time();
+        if time.is_nan() || time < 0.0 {
+            problems.push(format!(
+                "event {} `time` must be 0 or greater, got {}",
+                index + 1,
+                time
+            ));
+        }
+        let radius = match event {
+            ScenarioEvent::SetInfectedRadius { radius, .. } => Some(*radius),
+            ScenarioEvent::SetSocialDistancing { radius, .. } => *radius,
+            _ => None,
+        };
+        if let Some(radius) = radius
+            && (radius.is_nan() || radius < 0.0)
+        {
+            problems.push(format!(
+                "event {} `radius` must be 0 or greater, got {}",
+                index + 1,
+                radius
+            ));
+        }
+    }
+
+    if problems.is_empty() {
+        Ok(())
+    } else {
+        Err(ConfigError::Invalid(problems))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Tests that a TOML scenario is parsed and its events are sorted by time.
+    #[test]
+    fn test_parse_scenario() {
+        let text = r#"
+            duration = 30.0
+
+            [settings]
+            seed = 7
+
+            [[events]]
+            action = "set_infected_radius"
+            time = 15.0
+            radius = 6.0
+
+            [[events]]
+            action = "seed_infections"
+            time = 5.0
+            count = 4
+        "#;
+        let scenario = parse_scenario(text, ConfigFormat::Toml).unwrap();
+        assert_eq!(scenario.duration, Some(30.0));
+        assert_eq!(scenario.settings.unwrap().seed, Some(7));
+        assert_eq!(
+            scenario.events,
+            vec![
+                ScenarioEvent::SeedInfections {
+                    time: 5.0,
+                    count: 4,
+                    strain: 0
+                },
+                ScenarioEvent::SetInfectedRadius {
+                    time: 15.0,
+                    radius: 6.0
+                },
+            ]
+        );
+    }
+
+    /// Tests that unknown actions are rejected.
+    #[test]
+    fn test_parse_scenario_unknown_action() {
+        let text = r#"{"events": [{"action": "close_schools", "time": 1.0}]}"#;
+        let error = parse_scenario(text, ConfigFormat::Json).unwrap_err();
+        assert!(error.to_string().contains("close_schools"));
+    }
+
+    /// Tests that negative times and radii are reported.
+    #[test]
+    fn test_parse_scenario_invalid_events() {
+        let text = r#"
+            [[events]]
+            action = "set_social_distancing"
+            time = -1.0
+            enabled = true
+            radius = -5.0
+        "#;
+        match parse_scenario(text, ConfigFormat::Toml) {
+            Err(ConfigError::Invalid(problems)) => assert_eq!(problems.len(), 2),
+            _ => panic!("scenario should be invalid"),
+        }
+    }
+
+    /// Tests that an infinite duration is rejected instead of running forever.
+    #[test]
+    fn test_parse_scenario_infinite_duration() {
+        let text = "duration = inf";
+        match parse_scenario(text, ConfigFormat::Toml) {
+            Err(ConfigError::Invalid(problems)) => {
+                assert_eq!(problems.len(), 1);
+                assert!(problems[0].contains("finite"));
+            }
+            _ => panic!("scenario should be invalid"),
+        }
+    }
+}
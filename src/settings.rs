@@ -1,10 +1,19 @@
 // # Simulation Settings
 //!
 //! Global configuration constants for the epidemic spread simulation.
+//!
+//! The constants are the default values of `Settings`, which can be loaded from
+//! and saved to a configuration file (see the `config` module).
+
+use serde::{Deserialize, Serialize};
+
+use crate::strain::{Strain, StrainSet};
+use crate::wall::Wall;
 
-/// Size of the simulation area in pixels for a community.
+/// Default width and height of the simulation area in pixels for a community.
 ///
-/// It represents the height and width border of a community.
+/// The width and height can be set separately at runtime, so density can be varied
+/// independently of the population.
 /// # Default Value
 /// `350.0` pixels (creating a 350x350 simulation area)
 pub const SIMULATION_AREA_SIZE: f32 = 350.0;
@@ -51,7 +60,7 @@ pub const INFECTION_PROBABILITY: f32 = 0.3;
 /// `70.0` pixels per second
 pub const SOCIAL_DISTANCING_MAX_SPEED: f32 = 40.0;
 
-/// Size of the quarantine area in pixels for quarantine zone.
+/// Default width and height of the quarantine area in pixels for quarantine zone.
 ///
 /// # Default Value
 /// `200.0` pixels (creating a 200x200  area)
@@ -63,4 +72,269 @@ pub const QUARANTINE_AREA_SIZE: f32 = 200.0;
 /// `40.0` pixels
 pub const GAP_COMMUNITY_QUARANTINE: f32 = 40.0;
 
+/// Time (in seconds) before an infected person recovers.
+///
+/// # Default Value
+/// `8.0` seconds
 pub const RECOVERED_DAY: f32 = 8.0;
+
+/// Dispersion parameter k of the gamma distribution used for individual infectiousness.
+///
+/// Lower values concentrate transmission in a few superspreaders.
+/// # Default Value
+/// `0.1` (close to SARS-CoV-2 estimates, about 20% of cases cause 80% of transmission)
+pub const DISPERSION_K: f32 = 0.1;
+
+/// Time between the introduction of one strain and the next variant.
+///
+/// # Default Value
+/// `10.0` seconds
+pub const VARIANT_SEED_TIME: f32 = 10.0;
+
+/// Protection against a different strain after recovering from another one.
+///
+/// # Default Value
+/// `0.5` (50% protection)
+pub const CROSS_IMMUNITY: f32 = 0.5;
+
+/// Number of people in a community.
+///
+/// # Default Value
+/// `80` people
+pub const COMMUNITY_SIZE: usize = 80;
+
+/// The distance that infected people can spread disease.
+///
+/// # Default Value
+/// `3.5` pixels
+pub const INFECTED_RADIUS: f32 = 3.5;
+
+/// Distance at which people repel each other when social distancing is enabled.
+///
+/// # Default Value
+/// `20.0` pixels
+pub const SOCIAL_DISTANCING_RADIUS: f32 = 20.0;
+
+/// Time before infected people are moved to the quarantine area when quarantine is enabled.
+///
+/// # Default Value
+/// `5.0` seconds
+pub const INFECTION_TIME_BEFORE_QUARANTINE: f32 = 5.0;
+
+/// Duration of one simulation tick.
+///
+/// The simulation always advances in ticks of this size, so a run with the same
+/// seed gives the same result in the UI and in headless mode.
+/// # Default Value
+/// `1/60` seconds
+pub const TIME_STEP: f32 = 1.0 / 60.0;
+
+/// Maximum number of ticks simulated in one frame.
+///
+/// Limits the work after a slow frame so that the UI stays responsive.
+/// # Default Value
+/// `10` ticks
+pub const MAX_TICKS_PER_FRAME: usize = 10;
+
+/// Simulation time between two samples of the chart history.
+///
+/// The interval doubles each time the history is halved.
+/// # Default Value
+/// `0.1` seconds
+pub const CHART_INTERVAL: f32 = 0.1;
+
+/// Maximum number of samples in the chart history.
+///
+/// When it is exceeded, the history is halved with LTTB downsampling, so a run left
+/// open overnight uses a bounded amount of memory and the charts stay fast to draw.
+/// # Default Value
+/// `2000` samples
+pub const MAX_CHART_SAMPLES: usize = 2000;
+
+/// Slowest and fastest speed multiplier of the speed slider.
+///
+/// A speed of 2 runs two fixed ticks for each tick of real time.
+/// # Default Value
+/// `0.25` to `20`
+pub const SPEED_RANGE: (f32, f32) = (0.25, 20.0);
+
+/// Wall-clock time (in seconds) spent on ticks in one frame when running as fast as possible.
+///
+/// The rest of the frame is left for drawing the UI.
+/// # Default Value
+/// `0.015` seconds
+pub const FAST_FRAME_BUDGET: f32 = 0.015;
+
+/// Radius of the brush which infects, immunises or removes people in the community view.
+/// # Default Value
+/// `15.0` pixels
+pub const BRUSH_RADIUS: f32 = 15.0;
+
+/// Initial size of the window in pixels.
+/// # Default Value
+/// `1400.0` x `900.0` pixels
+pub const WINDOW_SIZE: [f32; 2] = [1400.0, 900.0];
+
+/// Smallest size of the window in pixels.
+/// # Default Value
+/// `800.0` x `500.0` pixels
+pub const MIN_WINDOW_SIZE: [f32; 2] = [800.0, 500.0];
+
+/// Initial width of the side panel with the controls in pixels.
+/// # Default Value
+/// `420.0` pixels
+pub const CONTROL_PANEL_WIDTH: f32 = 420.0;
+
+/// Smallest number of screen pixels per world unit in the community view.
+/// # Default Value
+/// `0.3` pixels
+pub const MIN_VIEW_SCALE: f32 = 0.3;
+
+/// Largest number of screen pixels per world unit in the community view.
+/// # Default Value
+/// `4.0` pixels
+pub const MAX_VIEW_SCALE: f32 = 4.0;
+
+/// Share of the window height used by the community view.
+///
+/// The view is narrower when the width of the window is the tighter side.
+/// # Default Value
+/// `0.6` (60% of the window height)
+pub const COMMUNITY_VIEW_HEIGHT: f32 = 0.6;
+
+/// Smallest height of a chart in pixels.
+///
+/// Charts take a share of the window height but never less than this.
+/// # Default Value
+/// `150.0` pixels
+pub const MIN_CHART_HEIGHT: f32 = 150.0;
+
+/// All parameters of the simulation.
+///
+/// The default values are the constants of this module. The first group of fields are
+/// world and disease parameters, the second group are the initial values of the UI controls.
+///
+/// # Fields
+/// - `simulation_area_width`, `simulation_area_height`: See `SIMULATION_AREA_SIZE`
+/// - `moving_speed`: See `MOVING_SPEED`
+/// - `border_padding`: See `BORDER_PADDING`
+/// - `person_radius`: See `PERSON_RADIUS`
+/// - `margin_from_wall`: See `MARGIN_FROM_WALL`
+/// - `infection_probability`: See `INFECTION_PROBABILITY`
+/// - `social_distancing_max_speed`: See `SOCIAL_DISTANCING_MAX_SPEED`
+/// - `quarantine_area_width`, `quarantine_area_height`: See `QUARANTINE_AREA_SIZE`
+/// - `gap_community_quarantine`: See `GAP_COMMUNITY_QUARANTINE`
+/// - `recovered_day`: See `RECOVERED_DAY`
+/// - `variant_seed_time`: See `VARIANT_SEED_TIME`
+/// - `cross_immunity`: See `CROSS_IMMUNITY`
+/// - `community_size`: See `COMMUNITY_SIZE`
+/// - `initial_infected_people`: See `INITIAL_INFECTED_PEOPLE`
+/// - `infected_radius`: See `INFECTED_RADIUS`
+/// - `social_distancing_enabled`: Determine whether social distancing is active
+/// - `social_distancing_radius`: See `SOCIAL_DISTANCING_RADIUS`
+/// - `quarantine_enabled`: Determine whether quarantine is active
+/// - `infection_time_before_quarantine`: See `INFECTION_TIME_BEFORE_QUARANTINE`
+/// - `superspreading_enabled`: Determine whether infectiousness varies between people
+/// - `dispersion_k`: See `DISPERSION_K`
+/// - `variants_enabled`: Determine whether variants are seeded
+/// - `variants`: Variants seeded after the original strain
+/// - `cross_immunity_matrix`: Cross-immunity between all strains, the original strain first
+/// - `walls`: Walls inside the community area, which people bounce off
+/// - `collisions_enabled`: Determine whether people bounce off each other
+/// - `time_step`: See `TIME_STEP`
+/// - `seed`: Seed of the random number generator, a new random seed is used on every restart when `None`
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Settings {
+    pub simulation_area_width: f32,
+    pub simulation_area_height: f32,
+    pub moving_speed: f32,
+    pub border_padding: f32,
+    pub person_radius: f32,
+    pub margin_from_wall: f32,
+    pub infection_probability: f32,
+    pub social_distancing_max_speed: f32,
+    pub quarantine_area_width: f32,
+    pub quarantine_area_height: f32,
+    pub gap_community_quarantine: f32,
+    pub recovered_day: f32,
+    pub variant_seed_time: f32,
+    pub cross_immunity: f32,
+    pub community_size: usize,
+    pub initial_infected_people: usize,
+    pub infected_radius: f32,
+    pub social_distancing_enabled: bool,
+    pub social_distancing_radius: f32,
+    pub quarantine_enabled: bool,
+    pub infection_time_before_quarantine: f32,
+    pub superspreading_enabled: bool,
+    pub dispersion_k: f32,
+    pub variants_enabled: bool,
+    pub variants: Vec<Strain>,
+    pub cross_immunity_matrix: Vec<Vec<f32>>,
+    pub walls: Vec<Wall>,
+    pub collisions_enabled: bool,
+    pub time_step: f32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub seed: Option<u64>,
+}
+
+impl Default for Settings {
+    /// Creates the settings from the default constants with one default variant.
+    fn default() -> Self {
+        let mut settings = Self {
+            simulation_area_width: SIMULATION_AREA_SIZE,
+            simulation_area_height: SIMULATION_AREA_SIZE,
+            moving_speed: MOVING_SPEED,
+            border_padding: BORDER_PADDING,
+            person_radius: PERSON_RADIUS,
+            margin_from_wall: MARGIN_FROM_WALL,
+            infection_probability: INFECTION_PROBABILITY,
+            social_distancing_max_speed: SOCIAL_DISTANCING_MAX_SPEED,
+            quarantine_area_width: QUARANTINE_AREA_SIZE,
+            quarantine_area_height: QUARANTINE_AREA_SIZE,
+            gap_community_quarantine: GAP_COMMUNITY_QUARANTINE,
+            recovered_day: RECOVERED_DAY,
+            variant_seed_time: VARIANT_SEED_TIME,
+            cross_immunity: CROSS_IMMUNITY,
+            community_size: COMMUNITY_SIZE,
+            initial_infected_people: INITIAL_INFECTED_PEOPLE,
+            infected_radius: INFECTED_RADIUS,
+            social_distancing_enabled: false,
+            social_distancing_radius: SOCIAL_DISTANCING_RADIUS,
+            quarantine_enabled: false,
+            infection_time_before_quarantine: INFECTION_TIME_BEFORE_QUARANTINE,
+            superspreading_enabled: false,
+            dispersion_k: DISPERSION_K,
+            variants_enabled: false,
+            variants: Vec::new(),
+            cross_immunity_matrix: vec![vec![1.0]],
+            walls: Vec::new(),
+            collisions_enabled: false,
+            time_step: TIME_STEP,
+            seed: None,
+        };
+        let mut strain_set = StrainSet::from_settings(&settings);
+        strain_set.add_variant(&settings);
+        settings.variants = strain_set.strains.split_off(1);
+        settings.cross_immunity_matrix = strain_set.cross_immunity;
+        settings
+    }
+}
+
+impl Settings {
+    /// Returns the size of the community or quarantine area.
+    ///
+    /// # Parameters
+    /// - `in_quarantine`: Determine whether the size of the quarantine area is returned
+    ///
+    /// # Returns
+    /// The `(width, height)` of the area
+    pub fn area_size(&self, in_quarantine: bool) -> (f32, f32) {
+        if in_quarantine {
+            (self.quarantine_area_width, self.quarantine_area_height)
+        } else {
+            (self.simulation_area_width, self.simulation_area_height)
+        }
+    }
+}
@@ -4,16 +4,50 @@
 //! simulation using the SIR (Susceptible-Infected-Recovered) model.
 //! It have some features:
 //! - Community population management (set using UI and restart button)
-//! - Disease transmission.
+//! - Disease transmission (with optional superspreading and co-circulating variants).
 //! - Prevention methods:
 //!    - social distancing.
 //!    - quarantine.
-use eframe::egui;
-use egui_plot::{Line, Plot, PlotPoints};
-use rand::Rng;
+//! - Scenario playback with timed events.
+use std::mem;
+use std::thread::{self, JoinHandle};
+use std::time::Instant;
 
+use eframe::egui;
+use egui_plot::{Bar, BarChart, HLine, Line, LineStyle, Plot, PlotPoints, Polygon, VLine};
+use rand::distributions::WeightedIndex;
+use rand::seq::SliceRandom;
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+use rand_distr::{Distribution, Gamma};
+
+use crate::chart_svg;
+use crate::chart_view::{self, ChartView};
+use crate::collision;
+use crate::config::{self, ConfigError};
+use crate::downsample::{self, retain_indices};
+use crate::ensemble::{self, Ensemble};
+use crate::export::{self, TimeSeries};
+use crate::heatmap::InfectionHeatmap;
+use crate::infection_log::{self, InfectionEvent, InfectionLocation, InfectionLog};
+use crate::inspector::{self, PersonHistory};
+use crate::ode::{self, SirParameters, SirSolution, SirState};
+use crate::offspring::OffspringDistribution;
+use crate::paint::{self, PaintTool};
 use crate::person::{Person, PersonState};
-use crate::settings::*;
+use crate::recording::{RecordedPerson, Recording};
+use crate::rt::{RT_BIN, RtSeries};
+use crate::scenario::{self, Scenario, ScenarioEvent};
+use crate::settings::{
+    BRUSH_RADIUS, CHART_INTERVAL, COMMUNITY_VIEW_HEIGHT, CONTROL_PANEL_WIDTH, FAST_FRAME_BUDGET,
+    MAX_CHART_SAMPLES, MAX_TICKS_PER_FRAME, MIN_CHART_HEIGHT, SPEED_RANGE, Settings,
+};
+use crate::snapshot;
+use crate::strain::{StrainSet, strain_color};
+use crate::sweep::{self, Sweep, SweepAxis, SweepCell, SweepParameter};
+use crate::transmission_tree::{self, TransmissionTree};
+use crate::view::ViewTransform;
+use crate::wall::Wall;
 
 /// Simulation structure for epidemic spread modeling.
 ///
@@ -22,18 +56,75 @@ use crate::settings::*;
 ///
 /// # Fields
 /// - `community`: Vector of all people in the simulation
-/// - `community_size`: Total number of people in the simulation
+/// - `community_size`: Number of people in the simulation, created on restart and changed by adding or removing people with the mouse
 /// - `initial_infected_count`: Number of initially infected people
 /// - `infected_radius`: The initial distance that infected people can spead disease.
 /// - `ui_infected_radius`: UI control value for infection radius (applied on restart)
 /// - `infected_chart`: Percentage of infected people over time
 /// - `susceptible_chart`: Percentage of susceptible people over time
 /// - `recovered_chart`: Percentage of recovered people over time
+/// - `state_counts`: Number of `(susceptible, infected, recovered)` people over time
+/// - `infection_log`: Every infection of the current run with its source
 /// - `total_time`: Time series data for the x-axis of charts
+/// - `chart_interval`: Simulation time (in seconds) between two samples of the chart history
 /// - `social_distancing_radius`: Distance at which people repel each other
 /// - `social_distancing_enabled`: Determine whether social distancing is active
 /// - `quarantine_enabled`: determine whether quarantine system is active
 /// - `infection_time_before_quarantine`: Time (in seconds) before infected people are moving to quarantine area.
+/// - `superspreading_enabled`: Determine whether infectiousness varies between people
+/// - `dispersion_k`: Dispersion parameter of the gamma distributed infectiousness (applied on restart)
+/// - `ui_dispersion_k`: UI control value for the dispersion parameter (applied on restart)
+/// - `strain_set`: All strains with their cross-immunity
+/// - `variants_enabled`: Determine whether variants are seeded in addition to the original strain
+/// - `strain_charts`: Percentage of people infected with each strain over time
+/// - `elapsed_time`: Simulation time (in seconds) since the last restart
+/// - `settings`: Parameters which are not controlled by the UI (area sizes, speeds, ...)
+/// - `config_path`: UI control value for the configuration file to load or save
+/// - `config_status`: Result message of the last configuration load or save
+/// - `seed`: Seed of the random number generator for the current run
+/// - `rng`: Random number generator used for everything random in the simulation
+/// - `time_accumulator`: Frame time (in seconds) which is not yet simulated by a whole tick
+/// - `paused`: Determine whether the simulation is paused
+/// - `speed`: Number of simulated seconds for each second of real time
+/// - `fast_forward`: Determine whether the simulation runs as fast as possible, ignoring `speed`
+/// - `recording`: Recorded frames of the current run for the timeline
+/// - `recording_enabled`: Determine whether every tick is recorded
+/// - `replay_frame`: Index of the recorded frame shown instead of the live simulation, if any
+/// - `scenario`: Scenario played back on every run, if loaded
+/// - `next_event`: Index of the next scenario event to apply
+/// - `scenario_path`: UI control value for the scenario file to load
+/// - `scenario_status`: Result message of the last scenario load
+/// - `snapshot_path`: UI control value for the snapshot file to save or load
+/// - `snapshot_status`: Result message of the last snapshot save or load
+/// - `export_path`: UI control value for the file the chart data is exported to
+/// - `export_status`: Result message of the last export
+/// - `log_path`: UI control value for the file the infection log is exported to
+/// - `chart_path`: UI control value for the SVG file the chart is exported to
+/// - `tree_path`: UI control value for the file the transmission tree is exported to
+/// - `selected_person`: Index of the person highlighted in the community view and shown in
+///   the inspector panel, if any
+/// - `ensemble_runs`: UI control value for the number of runs of an ensemble
+/// - `ensemble_duration`: UI control value for the length (in seconds) of each ensemble run
+/// - `ensemble_task`: Background thread running an ensemble, if one is running
+/// - `ensemble`: Results of the last ensemble, if any
+/// - `ensemble_status`: Error message of the last ensemble
+/// - `sweep_parameters`: UI control values for the first and second swept parameter
+/// - `sweep_ranges`: UI control values for the first and last value of each swept parameter
+/// - `sweep_two_parameters`: Determine whether the second parameter is swept
+/// - `sweep_steps`: UI control value for the number of values of each swept parameter
+/// - `sweep_seeds`: UI control value for the number of seeds in each cell
+/// - `sweep_task`: Background thread running a sweep, if one is running
+/// - `sweep`: Results of the last sweep, if any
+/// - `sweep_status`: Result message of the last sweep
+/// - `ode_overlay_enabled`: Determine whether the SIR ODE solution is drawn on the chart
+/// - `ode_cache`: The SIR ODE solution drawn on the chart, kept until its parameters change
+/// - `heatmap`: Where infections happened in the community area, across runs until reset
+/// - `heatmap_enabled`: Determine whether the heatmap is drawn under the people
+/// - `chart_view`: What the SIR chart shows
+/// - `chart_counts`: Determine whether the SIR chart shows counts instead of percentages
+/// - `chart_log_scale`: Determine whether the SIR chart uses a logarithmic y-axis
+/// - `paint_tool`: What clicking or dragging in the community area does
+/// - `wall_start`: Start of the wall being drawn, while dragging with the wall tool
 pub struct Simulation {
     pub community: Vec<Person>,
     pub community_size: usize,
@@ -43,11 +134,73 @@ pub struct Simulation {
     pub infected_chart: Vec<f32>,
     pub susceptible_chart: Vec<f32>,
     pub recovered_chart: Vec<f32>,
+    pub state_counts: Vec<(usize, usize, usize)>,
+    pub infection_log: InfectionLog,
     pub total_time: Vec<f32>,
+    pub chart_interval: f32,
     pub social_distancing_radius: f32,
     pub social_distancing_enabled: bool,
     pub quarantine_enabled: bool,
     pub infection_time_before_quarantine: f32,
+    pub superspreading_enabled: bool,
+    pub dispersion_k: f32,
+    pub ui_dispersion_k: f32,
+    pub strain_set: StrainSet,
+    pub variants_enabled: bool,
+    pub strain_charts: Vec<Vec<f32>>,
+    pub elapsed_time: f32,
+    pub settings: Settings,
+    pub config_path: String,
+    pub config_status: String,
+    pub seed: u64,
+    pub rng: ChaCha8Rng,
+    pub time_accumulator: f32,
+    pub paused: bool,
+    pub speed: f32,
+    pub fast_forward: bool,
+    pub recording: Recording,
+    pub recording_enabled: bool,
+    pub replay_frame: Option<usize>,
+    pub scenario: Option<Scenario>,
+    pub next_event: usize,
+    pub scenario_path: String,
+    pub scenario_status: String,
+    pub snapshot_path: String,
+    pub snapshot_status: String,
+    pub export_path: String,
+    pub export_status: String,
+    pub log_path: String,
+    pub chart_path: String,
+    pub tree_path: String,
+    pub selected_person: Option<usize>,
+    pub ensemble_runs: usize,
+    pub ensemble_duration: f32,
+    pub ensemble_task: Option<JoinHandle<Result<Ensemble, ConfigError>>>,
+    pub ensemble: Option<Ensemble>,
+    pub ensemble_status: String,
+    pub sweep_parameters: [SweepParameter; 2],
+    pub sweep_ranges: [(f32, f32); 2],
+    pub sweep_two_parameters: bool,
+    pub sweep_steps: usize,
+    pub sweep_seeds: usize,
+    pub sweep_task: Option<JoinHandle<Result<Sweep, ConfigError>>>,
+    pub sweep: Option<Sweep>,
+    pub sweep_status: String,
+    pub ode_overlay_enabled: bool,
+    pub ode_cache: Option<SirSolution>,
+    pub heatmap: InfectionHeatmap,
+    pub heatmap_enabled: bool,
+    pub chart_view: ChartView,
+    pub chart_counts: bool,
+    pub chart_log_scale: bool,
+    pub paint_tool: PaintTool,
+    pub wall_start: Option<(f32, f32)>,
+}
+
+impl Default for Simulation {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl Simulation {
@@ -61,52 +214,366 @@ impl Simulation {
     /// # Returns
     /// A new `Simulation` instance ready to run
     pub fn new() -> Self {
-        let community_size = 80;
-        let mut community: Vec<Person> = (0..community_size).map(|_| Person::new()).collect();
-        for person in community.iter_mut().take(INITIAL_INFECTED_PEOPLE) {
-            person.state = PersonState::Infected;
-            person.infection_duration = 0.0;
-        }
-        let mut total_time = Vec::new();
-        let mut infected_chart = Vec::new();
-        let mut susceptible_chart = Vec::new();
-        let mut recovered_chart = Vec::new();
-
-        total_time.push(0.0);
-        infected_chart.push((INITIAL_INFECTED_PEOPLE as f32 / community_size as f32) * 100.0);
-        susceptible_chart.push(
-            ((community_size - INITIAL_INFECTED_PEOPLE) as f32 / community_size as f32) * 100.0,
-        );
-        recovered_chart.push(0.0);
-        Self {
-            community,
-            total_time,
-            community_size,
-            initial_infected_count: INITIAL_INFECTED_PEOPLE,
-            infected_radius: 3.5,
-            ui_infected_radius: 3.5,
-            infected_chart,
-            susceptible_chart,
-            recovered_chart,
-            social_distancing_radius: 20.0,
-            social_distancing_enabled: false,
-            quarantine_enabled: false,
-            infection_time_before_quarantine: 5.0,
+        Self::with_settings(Settings::default())
+    }
+
+    /// Creates a new simulation with the given settings.
+    ///
+    /// # Parameters
+    /// - `settings`: The simulation settings, usually loaded from a configuration file
+    ///
+    /// # Returns
+    /// A new `Simulation` instance ready to run
+    pub fn with_settings(settings: Settings) -> Self {
+        let mut simulation = Self {
+            community: Vec::new(),
+            total_time: Vec::new(),
+            chart_interval: CHART_INTERVAL,
+            community_size: settings.community_size,
+            initial_infected_count: settings.initial_infected_people,
+            infected_radius: settings.infected_radius,
+            ui_infected_radius: settings.infected_radius,
+            infected_chart: Vec::new(),
+            susceptible_chart: Vec::new(),
+            recovered_chart: Vec::new(),
+            state_counts: Vec::new(),
+            infection_log: InfectionLog::default(),
+            social_distancing_radius: settings.social_distancing_radius,
+            social_distancing_enabled: settings.social_distancing_enabled,
+            quarantine_enabled: settings.quarantine_enabled,
+            infection_time_before_quarantine: settings.infection_time_before_quarantine,
+            superspreading_enabled: settings.superspreading_enabled,
+            dispersion_k: settings.dispersion_k,
+            ui_dispersion_k: settings.dispersion_k,
+            strain_set: StrainSet::from_settings(&settings),
+            variants_enabled: settings.variants_enabled,
+            strain_charts: Vec::new(),
+            elapsed_time: 0.0,
+            settings,
+            config_path: String::from(config::DEFAULT_CONFIG_PATH),
+            config_status: String::new(),
+            seed: 0,
+            rng: ChaCha8Rng::seed_from_u64(0),
+            time_accumulator: 0.0,
+            paused: false,
+            speed: 1.0,
+            fast_forward: false,
+            recording: Recording::default(),
+            recording_enabled: false,
+            replay_frame: None,
+            scenario: None,
+            next_event: 0,
+            scenario_path: String::from(scenario::DEFAULT_SCENARIO_PATH),
+            scenario_status: String::new(),
+            snapshot_path: String::from(snapshot::DEFAULT_SNAPSHOT_PATH),
+            snapshot_status: String::new(),
+            export_path: String::from(export::DEFAULT_EXPORT_PATH),
+            export_status: String::new(),
+            log_path: String::from(infection_log::DEFAULT_LOG_PATH),
+            chart_path: String::from(chart_svg::DEFAULT_CHART_PATH),
+            tree_path: String::from(transmission_tree::DEFAULT_TREE_PATH),
+            selected_person: None,
+            ensemble_runs: ensemble::ENSEMBLE_RUNS,
+            ensemble_duration: ensemble::ENSEMBLE_DURATION,
+            ensemble_task: None,
+            ensemble: None,
+            ensemble_status: String::new(),
+            sweep_parameters: [
+                SweepParameter::InfectedRadius,
+                SweepParameter::SocialDistancingRadius,
+            ],
+            sweep_ranges: [(2.0, 10.0), (0.0, 30.0)],
+            sweep_two_parameters: false,
+            sweep_steps: sweep::SWEEP_STEPS,
+            sweep_seeds: sweep::SWEEP_SEEDS,
+            sweep_task: None,
+            sweep: None,
+            sweep_status: String::new(),
+            ode_overlay_enabled: false,
+            ode_cache: None,
+            heatmap: InfectionHeatmap::default(),
+            heatmap_enabled: false,
+            chart_view: ChartView::default(),
+            chart_counts: false,
+            chart_log_scale: false,
+            paint_tool: PaintTool::default(),
+            wall_start: None,
+        };
+        simulation.restart();
+        simulation
+    }
+
+    /// Replaces all settings and restarts the simulation.
+    ///
+    /// # Parameters
+    /// - `settings`: The new simulation settings
+    pub fn apply_settings(&mut self, settings: Settings) {
+        self.community_size = settings.community_size;
+        self.initial_infected_count = settings.initial_infected_people;
+        self.ui_infected_radius = settings.infected_radius;
+        self.social_distancing_radius = settings.social_distancing_radius;
+        self.social_distancing_enabled = settings.social_distancing_enabled;
+        self.quarantine_enabled = settings.quarantine_enabled;
+        self.infection_time_before_quarantine = settings.infection_time_before_quarantine;
+        self.superspreading_enabled = settings.superspreading_enabled;
+        self.ui_dispersion_k = settings.dispersion_k;
+        self.strain_set = StrainSet::from_settings(&settings);
+        self.variants_enabled = settings.variants_enabled;
+        self.settings = settings;
+        self.restart();
+    }
+
+    /// Collects the current UI settings.
+    ///
+    /// Values which are applied on restart are taken from the UI controls, so saving
+    /// and loading the settings gives the same simulation as clicking "Apply and Reset".
+    ///
+    /// # Returns
+    /// The settings of this simulation
+    pub fn current_settings(&self) -> Settings {
+        Settings {
+            community_size: self.community_size,
+            initial_infected_people: self.initial_infected_count,
+            infected_radius: self.ui_infected_radius,
+            social_distancing_enabled: self.social_distancing_enabled,
+            social_distancing_radius: self.social_distancing_radius,
+            quarantine_enabled: self.quarantine_enabled,
+            infection_time_before_quarantine: self.infection_time_before_quarantine,
+            superspreading_enabled: self.superspreading_enabled,
+            dispersion_k: self.ui_dispersion_k,
+            variants_enabled: self.variants_enabled,
+            variants: self.strain_set.strains[1..].to_vec(),
+            cross_immunity_matrix: self.strain_set.cross_immunity.clone(),
+            ..self.settings.clone()
+        }
+    }
+
+    /// Collects the settings of the current run.
+    ///
+    /// Unlike `current_settings`, values which are applied on restart are taken from the
+    /// run instead of the UI controls, and the community size and seed are those of the run.
+    ///
+    /// # Returns
+    /// The settings the current run uses
+    pub fn run_settings(&self) -> Settings {
+        Settings {
+            community_size: self.community.len(),
+            initial_infected_people: self.strain_set.strains[0].seed_count,
+            infected_radius: self.infected_radius,
+            dispersion_k: self.dispersion_k,
+            seed: Some(self.seed),
+            ..self.current_settings()
+        }
+    }
+
+    /// Sets the scenario played back on every run and restarts the simulation.
+    ///
+    /// The settings of the scenario replace the current settings. A scenario without
+    /// settings keeps the current settings, which are stored with the scenario so that
+    /// every run starts from the same state. Settings without a seed get the seed of the
+    /// current run, so every run of the scenario is the same.
+    ///
+    /// # Parameters
+    /// - `scenario`: The scenario to play back
+    ///
+    /// # Returns
+    /// `Ok(())` or `ConfigError::Invalid` if an event uses a strain which does not exist
+    pub fn set_scenario(&mut self, scenario: Scenario) -> Result<(), ConfigError> {
+        let strain_count = match &scenario.settings {
+            Some(settings) => settings.variants.len() + 1,
+            None => self.strain_set.strains.len(),
+        };
+        let problems: Vec<String> = scenario
+            .events
+            .iter()
+            .filter_map(|event| event.strain())
+            .filter(|&strain| strain >= strain_count)
+            .map(|strain| {
+                format!(
+                    "event strain {} does not exist ({} strains)",
+                    strain, strain_count
+                )
+            })
+            .collect();
+        if !problems.is_empty() {
+            return Err(ConfigError::Invalid(problems));
+        }
+
+        let mut scenario = scenario;
+        let settings = scenario
+            .settings
+            .get_or_insert_with(|| self.current_settings());
+        if settings.seed.is_none() {
+            settings.seed = Some(self.seed);
+        }
+        let settings = settings.clone();
+        self.scenario = Some(scenario);
+        self.apply_settings(settings);
+        Ok(())
+    }
+
+    /// Advances the simulation by the time of a frame in fixed ticks.
+    ///
+    /// The frame time multiplied by `speed` is accumulated and the simulation is stepped
+    /// by `time_step` for each whole tick, so the result does not depend on the frame rate
+    /// or the speed. At most `MAX_TICKS_PER_FRAME` ticks per unit of speed are run to keep
+    /// the UI responsive after a slow frame. When running as fast as possible, ticks are
+    /// run for `FAST_FRAME_BUDGET` of wall-clock time instead. Nothing happens while paused.
+    /// While a recorded frame is shown, the replay moves forward instead and switches back
+    /// to the live simulation at the last frame.
+    ///
+    /// # Parameters
+    /// - `frame_time`: Time (in seconds) since the last frame
+    pub fn advance(&mut self, frame_time: f32) {
+        if self.paused {
+            return;
+        }
+        let time_step = self.settings.time_step;
+        if let Some(frame) = self.replay_frame {
+            let ticks = if self.fast_forward {
+                self.time_accumulator = 0.0;
+                MAX_TICKS_PER_FRAME
+            } else {
+                self.time_accumulator += frame_time * self.speed;
+                let ticks = (self.time_accumulator / time_step) as usize;
+                self.time_accumulator -= ticks as f32 * time_step;
+                ticks
+            };
+            let next = frame + ticks;
+            if next + 1 >= self.recording.frames.len() {
+                self.replay_frame = None;
+                self.time_accumulator = 0.0;
+            } else {
+                self.replay_frame = Some(next);
+            }
+            return;
+        }
+        if self.fast_forward {
+            let start = Instant::now();
+            while start.elapsed().as_secs_f32() < FAST_FRAME_BUDGET {
+                self.tick();
+            }
+            self.time_accumulator = 0.0;
+            return;
+        }
+
+        let max_ticks = MAX_TICKS_PER_FRAME * (self.speed.ceil() as usize).max(1);
+        self.time_accumulator += frame_time * self.speed;
+        let mut ticks = 0;
+        while self.time_accumulator >= time_step && ticks < max_ticks {
+            self.tick();
+            self.time_accumulator -= time_step;
+            ticks += 1;
+        }
+        if ticks == max_ticks {
+            self.time_accumulator = 0.0;
+        }
+    }
+
+    /// Steps the simulation by exactly one tick, used while paused.
+    ///
+    /// While a recorded frame is shown, the next recorded frame is shown instead.
+    pub fn step_tick(&mut self) {
+        match self.replay_frame {
+            Some(frame) if frame + 2 < self.recording.frames.len() => {
+                self.replay_frame = Some(frame + 1)
+            }
+            Some(_) => self.replay_frame = None,
+            None => self.tick(),
+        }
+    }
+
+    /// Steps the live simulation by one tick and records it when recording is enabled.
+    fn tick(&mut self) {
+        let mut recording = mem::take(&mut self.recording);
+        if self.recording_enabled && recording.frames.is_empty() {
+            recording.record(self);
+        }
+        self.step(self.settings.time_step);
+        if self.recording_enabled {
+            recording.record(self);
         }
+        self.recording = recording;
+    }
+
+    /// Shows a recorded frame instead of the live simulation, or the live simulation again.
+    ///
+    /// Time accumulated for the previous frame is dropped, so the replay starts moving
+    /// from the new frame at the normal pace.
+    ///
+    /// # Parameters
+    /// - `frame`: Index of the recorded frame, or `None` for the live simulation
+    pub fn show_replay_frame(&mut self, frame: Option<usize>) {
+        self.replay_frame = frame;
+        self.time_accumulator = 0.0;
+    }
+
+    /// Returns the time of what the community view shows.
+    ///
+    /// # Returns
+    /// The time of the replayed frame while replaying, otherwise the current time
+    fn shown_time(&self) -> f32 {
+        self.replay_frame
+            .and_then(|frame| self.recording.frames.get(frame))
+            .map_or(self.elapsed_time, |frame| frame.time)
+    }
+
+    /// Rewinds the simulation to a recorded frame and continues live from there.
+    ///
+    /// # Parameters
+    /// - `frame`: Index of the recorded frame
+    pub fn rewind(&mut self, frame: usize) {
+        let mut recording = mem::take(&mut self.recording);
+        recording.rewind(frame, self);
+        self.recording = recording;
+        self.replay_frame = None;
+        self.time_accumulator = 0.0;
+    }
+
+    /// Steps the simulation by one tick and records the chart data.
+    ///
+    /// # Parameters
+    /// - `time_frame_per_second`: Time delta for this tick
+    pub fn step(&mut self, time_frame_per_second: f32) {
+        self.update_community(time_frame_per_second);
+        self.update_chart(time_frame_per_second);
+    }
+
+    /// Counts people in each state of the SIR model.
+    ///
+    /// # Returns
+    /// A tuple `(susceptible, infected, recovered)`
+    pub fn count_states(&self) -> (usize, usize, usize) {
+        let mut counts = (0, 0, 0);
+        for person in &self.community {
+            match person.state {
+                PersonState::Susceptible => counts.0 += 1,
+                PersonState::Infected(_) => counts.1 += 1,
+                PersonState::Recovered => counts.2 += 1,
+            }
+        }
+        counts
     }
 
     /// Updates the community state for one simulation frame (with 60 FPS equal to 1/60 seconds).
     ///
     /// Performs these features:
-    /// 1. Moves infected people to quarantine (if enabled)
-    /// 2. Applies social distancing forces (if enabled)
-    /// 3. Updates infection duration (how long a person has been infected) and recoveries
-    /// 4. Updates all people positions
-    /// 5. Spreads infection to nearby people
+    /// 1. Applies scenario events whose time is reached (if a scenario is loaded)
+    /// 2. Seeds variants whose introduction time is reached (if enabled)
+    /// 3. Moves infected people to quarantine (if enabled)
+    /// 4. Applies social distancing forces (if enabled)
+    /// 5. Updates infection duration (how long a person has been infected) and recoveries
+    /// 6. Updates all people positions
+    /// 7. Spreads infection to nearby people
     ///
     /// # Parameters
     /// - `time_frame_per_second`: Time delta for this frame
     fn update_community(&mut self, time_frame_per_second: f32) {
+        self.apply_scenario_events(self.elapsed_time + time_frame_per_second);
+        if self.variants_enabled {
+            self.seed_variants(self.elapsed_time, self.elapsed_time + time_frame_per_second);
+        }
+        self.elapsed_time += time_frame_per_second;
         self.move_infected_to_quarantine();
 
         if self.social_distancing_enabled {
@@ -118,43 +585,291 @@ impl Simulation {
         }
 
         for person in &mut self.community {
-            if person.state == PersonState::Infected {
+            if let Some(strain) = person.strain() {
                 person.infection_duration += time_frame_per_second;
-                if person.infection_duration >= RECOVERED_DAY {
-                    person.infection_duration = 0.0;
-                    person.state = PersonState::Recovered;
+                if person.infection_duration >= self.strain_set.strains[strain].recovered_day {
+                    person.recover();
                 }
             }
 
-            person.update_position(time_frame_per_second);
+            person.update_position(time_frame_per_second, &self.settings);
+        }
+        if self.settings.collisions_enabled {
+            collision::resolve_collisions(&mut self.community, &self.settings);
         }
         self.spread_infection();
     }
 
-    /// Spreads infection to nearby susceptible people based on constant INFECTION_PROBABILITY.
+    /// Spreads infection to nearby people based on the infection probability of each strain.
     ///
-    /// Finds all susceptible people within the infection radius of infected individuals,
+    /// Finds all people who are not infected within the infection radius of infected individuals,
     /// then applies the infection probability to determine who gets infected.
+    ///
+    /// The infection probability of a strain is the chance of infection from one contact with an
+    /// average infectiousness of `1.0` for a person without immunity. A contact with
+    /// infectiousness `v` and a person with susceptibility `s` to the strain has the same chance
+    /// as `v * s` such contacts, so the total chance is `1 - product((1 - p)^(v * s))`.
+    /// Without superspreading, contacts do not add up and the chance is that of the most
+    /// infectious contact, which is `p` for a person without immunity as before.
+    /// The infector is chosen among the nearby infected people weighted by their contribution,
+    /// the infected person gets the infector's strain and the infector's `secondary_cases` is increased.
     fn spread_infection(&mut self) {
-        let mut rng = rand::thread_rng();
-        let susceptibles = self.find_vulnerable_people();
-        for index in susceptibles {
-            let random = rng.gen_range(0.0..1.0);
-            if random < INFECTION_PROBABILITY {
-                self.community[index].state = PersonState::Infected;
-                self.community[index].infection_duration = 0.0;
+        let vulnerable_people = self.find_vulnerable_people();
+        let exposures: Vec<(usize, Vec<usize>, Vec<f32>)> = vulnerable_people
+            .into_iter()
+            .map(|index| {
+                let person = &self.community[index];
+                let infectors = self.find_infectors(person);
+                let weights: Vec<f32> = infectors
+                    .iter()
+                    .map(|&infector| {
+                        let member = &self.community[infector];
+                        let strain = member.strain().unwrap_or(0);
+                        let probability = self.strain_set.strains[strain].infection_probability;
+                        let susceptibility = self
+                            .strain_set
+                            .susceptibility(&person.recovered_from, strain);
+                        -(1.0 - probability.min(0.999)).ln()
+                            * member.infectiousness
+                            * susceptibility
+                    })
+                    .collect();
+                (index, infectors, weights)
+            })
+            .collect();
+
+        let mut new_infections = Vec::new();
+        for (index, infectors, weights) in exposures {
+            let total_weight: f32 = if self.superspreading_enabled {
+                weights.iter().sum()
+            } else {
+                weights.iter().copied().fold(0.0, f32::max)
+            };
+            let probability = 1.0 - (-total_weight).exp();
+            let random = self.rng.gen_range(0.0..1.0);
+            if random < probability {
+                let infector = match WeightedIndex::new(&weights) {
+                    Ok(distribution) => infectors[distribution.sample(&mut self.rng)],
+                    Err(_) => infectors[0],
+                };
+                new_infections.push((index, infector));
+            }
+        }
+
+        for (index, infector) in new_infections {
+            let strain = self.community[infector].strain().unwrap_or(0);
+            self.infect_person(index, strain, Some(infector));
+        }
+    }
+
+    /// Infects a person and records the infection in the infection log.
+    ///
+    /// The infector's `secondary_cases` is increased.
+    ///
+    /// # Parameters
+    /// - `index`: Index of the person in the community
+    /// - `strain`: Index of the strain
+    /// - `infector`: Index of the person who transmitted the infection, `None` for seeded
+    ///   and imported cases
+    fn infect_person(&mut self, index: usize, strain: usize, infector: Option<usize>) {
+        let infectiousness = self.draw_infectiousness();
+        self.community[index].infect(strain, infectiousness);
+        let location = if self.community[index].is_in_quarantine {
+            InfectionLocation::Quarantine
+        } else {
+            InfectionLocation::Community
+        };
+        let infector_infection_age = infector.map(|infector| {
+            self.community[infector].secondary_cases += 1;
+            self.community[infector].infection_duration
+        });
+        if infector.is_some() && location == InfectionLocation::Community {
+            let person = &self.community[index];
+            let (width, height) = self.settings.area_size(false);
+            self.heatmap.record(person.x, person.y, width, height);
+        }
+        self.infection_log.record(InfectionEvent {
+            person: index,
+            infector,
+            time: self.elapsed_time,
+            strain,
+            location,
+            infector_infection_age,
+        });
+    }
+
+    /// Applies a paint tool at a position in the community area.
+    ///
+    /// - `Infect` infects everyone under the brush who is not infected and not immune to
+    ///   the original strain, as seeded cases without an infector.
+    /// - `Immunise` makes the susceptible people under the brush immune to all strains.
+    /// - `Add` adds a person at the position unless someone is already there.
+    /// - `Remove` removes the people under the brush, keeping at least one person.
+    /// - `EraseWall` removes the walls under the brush.
+    ///
+    /// The latest chart sample and recorded frame are updated to show the change, and
+    /// `community_size` follows the people added or removed. Walls are drawn with
+    /// `add_wall` instead.
+    ///
+    /// # Parameters
+    /// - `tool`: The paint tool
+    /// - `x`, `y`: Position in the community area
+    pub fn paint(&mut self, tool: PaintTool, x: f32, y: f32) {
+        let targets = paint::brush_targets(&self.community, x, y, BRUSH_RADIUS);
+        let changed = match tool {
+            PaintTool::Select | PaintTool::Wall => false,
+            PaintTool::Infect => {
+                // Immune people cannot be infected with the original strain
+                let targets: Vec<usize> = targets
+                    .into_iter()
+                    .filter(|&index| {
+                        let person = &self.community[index];
+                        !person.is_infected()
+                            && self.strain_set.susceptibility(&person.recovered_from, 0) > 0.0
+                    })
+                    .collect();
+                for &index in &targets {
+                    self.infect_person(index, 0, None);
+                }
+                !targets.is_empty()
             }
+            PaintTool::Immunise => {
+                let strains: Vec<usize> = (0..self.strain_set.strains.len()).collect();
+                let mut changed = false;
+                for index in targets {
+                    let person = &mut self.community[index];
+                    if person.is_susceptible() {
+                        person.state = PersonState::Recovered;
+                        person.recovered_from = strains.clone();
+                        changed = true;
+                    }
+                }
+                changed
+            }
+            PaintTool::Add => {
+                let occupied =
+                    !paint::brush_targets(&self.community, x, y, self.settings.person_radius * 2.0)
+                        .is_empty();
+                if !occupied {
+                    let mut person = Person::new(&self.settings, &mut self.rng);
+                    person.x = x;
+                    person.y = y;
+                    self.community.push(person);
+                    self.community_size = self.community.len();
+                }
+                !occupied
+            }
+            PaintTool::Remove => {
+                let mut changed = false;
+                for index in targets.into_iter().rev() {
+                    if self.community.len() > 1 {
+                        self.remove_person(index);
+                        changed = true;
+                    }
+                }
+                self.community_size = self.community.len();
+                changed
+            }
+            PaintTool::EraseWall => {
+                let wall_count = self.settings.walls.len();
+                self.settings
+                    .walls
+                    .retain(|wall| wall.distance(x, y) > BRUSH_RADIUS);
+                self.settings.walls.len() != wall_count
+            }
+        };
+        if !changed {
+            return;
+        }
+
+        // Replace the sample of the current time, so the chart shows the change
+        if self.total_time.last() == Some(&self.elapsed_time) {
+            self.total_time.pop();
+            self.infected_chart.pop();
+            self.susceptible_chart.pop();
+            self.recovered_chart.pop();
+            self.state_counts.pop();
+            for strain_chart in &mut self.strain_charts {
+                strain_chart.pop();
+            }
+        }
+        self.push_chart_sample();
+        self.record_edit();
+    }
+
+    /// Adds a wall to the community area.
+    ///
+    /// The wall is kept in the settings, so it is saved with the configuration.
+    ///
+    /// # Parameters
+    /// - `wall`: The new wall
+    pub fn add_wall(&mut self, wall: Wall) {
+        self.settings.walls.push(wall);
+        self.record_edit();
+    }
+
+    /// Records a change made between ticks, so rewinding after it keeps the change.
+    fn record_edit(&mut self) {
+        if !self.recording_enabled {
+            return;
+        }
+        let mut recording = mem::take(&mut self.recording);
+        if recording.frames.is_empty() {
+            recording.record(self);
+        } else {
+            recording.record_edit(self);
+        }
+        self.recording = recording;
+    }
+
+    /// Removes a person from the community.
+    ///
+    /// The people after them move down by one index, in the infection log and the
+    /// selection too.
+    ///
+    /// # Parameters
+    /// - `index`: Index of the person to remove
+    fn remove_person(&mut self, index: usize) {
+        self.community.remove(index);
+        self.infection_log.remove_person(index);
+        self.selected_person = match self.selected_person {
+            Some(selected) if selected == index => None,
+            Some(selected) if selected > index => Some(selected - 1),
+            selected => selected,
+        };
+    }
+
+    /// Draws the relative infectiousness of a new infection.
+    ///
+    /// When superspreading is enabled, infectiousness follows a gamma distribution with
+    /// shape `dispersion_k` and scale `1 / dispersion_k`, so the mean is `1.0` and the
+    /// variance is `1 / dispersion_k`. Otherwise everyone has infectiousness `1.0`.
+    ///
+    /// # Returns
+    /// The relative infectiousness of a newly infected person
+    fn draw_infectiousness(&mut self) -> f32 {
+        if !self.superspreading_enabled {
+            return 1.0;
+        }
+        match Gamma::new(self.dispersion_k, 1.0 / self.dispersion_k) {
+            Ok(gamma) => gamma.sample(&mut self.rng),
+            Err(_) => 1.0,
         }
     }
 
-    /// Finds all susceptible people within the infection radius of infected individuals
+    /// Finds all people who are not infected within the infection radius of infected individuals
+    ///
+    /// Recovered people are included because they may be reinfected by a different strain.
     ///
     /// # Returns
     /// Vector of indices of vulnerable people in the community
     fn find_vulnerable_people(&self) -> Vec<usize> {
         let mut vulnerable_people = Vec::new();
         for (index, person) in self.community.iter().enumerate() {
-            if !person.is_susceptible() {
+            if person.is_infected() {
+                continue;
+            }
+            if !person.is_susceptible() && !self.variants_enabled {
                 continue;
             }
             if self.is_within_infected_radius(person) {
@@ -171,15 +886,26 @@ impl Simulation {
     /// # Returns
     /// `true` if the person is close to an infected individual
     fn is_within_infected_radius(&self, person: &Person) -> bool {
-        for member in &self.community {
+        !self.find_infectors(person).is_empty()
+    }
+
+    /// Finds all infected people who can spread disease to a given person.
+    ///
+    /// # Parameters
+    /// - `person`: The person to check
+    /// # Returns
+    /// Vector of indices of infected people within the infection radius in the same area
+    fn find_infectors(&self, person: &Person) -> Vec<usize> {
+        let mut infectors = Vec::new();
+        for (index, member) in self.community.iter().enumerate() {
             if member.is_infected() && person.is_in_quarantine == member.is_in_quarantine {
                 let distance = person.calculate_distance(member);
                 if distance <= self.infected_radius {
-                    return true;
+                    infectors.push(index);
                 }
             }
         }
-        false
+        infectors
     }
 
     /// Calculates the repulsion force on a person due to social distancing.
@@ -233,16 +959,114 @@ impl Simulation {
     /// - `forces`: Vector of force tuples `(x, y)` for each person in the community
     /// - `time_frame_per_second`: Time delta for frame-rate independent physics
     fn apply_forces(&mut self, forces: Vec<(f32, f32)>, time_frame_per_second: f32) {
+        let max_speed = self.settings.social_distancing_max_speed;
         for (person, (fx, fy)) in self.community.iter_mut().zip(forces.iter()) {
             person.velocity_x += fx * 150.0 * time_frame_per_second;
             person.velocity_y += fy * 150.0 * time_frame_per_second;
             let speed = (person.velocity_x * person.velocity_x
                 + person.velocity_y * person.velocity_y)
                 .sqrt();
-            if speed > SOCIAL_DISTANCING_MAX_SPEED {
-                person.velocity_x = (person.velocity_x / speed) * SOCIAL_DISTANCING_MAX_SPEED;
-                person.velocity_y = (person.velocity_y / speed) * SOCIAL_DISTANCING_MAX_SPEED;
+            if speed > max_speed {
+                person.velocity_x = (person.velocity_x / speed) * max_speed;
+                person.velocity_y = (person.velocity_y / speed) * max_speed;
+            }
+        }
+    }
+
+    /// Seeds the variants whose introduction time is within a time interval.
+    ///
+    /// Each variant infects `seed_count` random people in the community who are not
+    /// infected. The original strain is seeded by `restart` instead.
+    ///
+    /// # Parameters
+    /// - `start_time`: Start of the interval (inclusive)
+    /// - `end_time`: End of the interval (exclusive)
+    fn seed_variants(&mut self, start_time: f32, end_time: f32) {
+        for strain in 1..self.strain_set.strains.len() {
+            let variant = &self.strain_set.strains[strain];
+            if variant.seed_time < start_time || variant.seed_time >= end_time {
+                continue;
+            }
+            self.infect_random_people(variant.seed_count, strain);
+        }
+    }
+
+    /// Infects random people in the community who are not infected.
+    ///
+    /// People in the quarantine area are not chosen.
+    ///
+    /// # Parameters
+    /// - `count`: Number of people to infect (fewer if not enough people can be infected)
+    /// - `strain`: Index of the strain
+    fn infect_random_people(&mut self, count: usize, strain: usize) {
+        let candidates: Vec<usize> = (0..self.community.len())
+            .filter(|&index| {
+                let person = &self.community[index];
+                !person.is_infected() && !person.is_in_quarantine
+            })
+            .collect();
+        let seeds: Vec<usize> = candidates
+            .choose_multiple(&mut self.rng, count)
+            .copied()
+            .collect();
+        for index in seeds {
+            self.infect_person(index, strain, None);
+        }
+    }
+
+    /// Adds new infected people at random positions in the community.
+    ///
+    /// # Parameters
+    /// - `count`: Number of people to add
+    /// - `strain`: Index of the strain
+    fn import_cases(&mut self, count: usize, strain: usize) {
+        for _ in 0..count {
+            let person = Person::new(&self.settings, &mut self.rng);
+            self.community.push(person);
+            self.infect_person(self.community.len() - 1, strain, None);
+        }
+    }
+
+    /// Applies all scenario events which happen before a given time.
+    ///
+    /// Events are applied in order, each one once per run.
+    ///
+    /// # Parameters
+    /// - `end_time`: Events with a time before this time (in seconds) are applied
+    fn apply_scenario_events(&mut self, end_time: f32) {
+        loop {
+            let event = match &self.scenario {
+                Some(scenario) => match scenario.events.get(self.next_event) {
+                    Some(event) if event.time() < end_time => event.clone(),
+                    _ => return,
+                },
+                None => return,
+            };
+            self.next_event += 1;
+            self.apply_event(&event);
+        }
+    }
+
+    /// Applies one scenario event.
+    ///
+    /// # Parameters
+    /// - `event`: The event to apply
+    fn apply_event(&mut self, event: &ScenarioEvent) {
+        match *event {
+            ScenarioEvent::SeedInfections { count, strain, .. } => {
+                self.infect_random_people(count, strain)
+            }
+            ScenarioEvent::ImportCases { count, strain, .. } => self.import_cases(count, strain),
+            ScenarioEvent::SetQuarantine { enabled, .. } => self.quarantine_enabled = enabled,
+            ScenarioEvent::SetSocialDistancing {
+                enabled, radius, ..
+            } => {
+                self.social_distancing_enabled = enabled;
+                if let Some(radius) = radius {
+                    self.social_distancing_radius = radius;
+                }
             }
+            ScenarioEvent::SetInfectedRadius { radius, .. } => self.infected_radius = radius,
         }
     }
 
@@ -255,17 +1079,16 @@ impl Simulation {
         if !self.quarantine_enabled {
             return;
         }
-        let mut rng = rand::thread_rng();
+        let margin = self.settings.margin_from_wall;
+        let (width, height) = self.settings.area_size(true);
 
         for person in &mut self.community {
-            if person.state == PersonState::Infected
+            if person.is_infected()
                 && !person.is_in_quarantine
                 && person.infection_duration >= self.infection_time_before_quarantine
             {
-                person.x =
-                    rng.gen_range(MARGIN_FROM_WALL..(QUARANTINE_AREA_SIZE - MARGIN_FROM_WALL));
-                person.y =
-                    rng.gen_range(MARGIN_FROM_WALL..(QUARANTINE_AREA_SIZE - MARGIN_FROM_WALL));
+                person.x = self.rng.gen_range(margin..(width - margin));
+                person.y = self.rng.gen_range(margin..(height - margin));
                 person.move_to_quarantine();
             }
         }
@@ -276,299 +1099,1891 @@ impl Simulation {
     ///
     /// This method collects real-time data by counting people in each state
     /// (Susceptible, Infected, Recovered) and converting counts to percentages for visualization.
+    /// A sample is taken once every `chart_interval` and the history is halved when it has
+    /// more than `MAX_CHART_SAMPLES` samples.
     /// # Parameters
     /// - `time_frame_per_second`: Time elapsed since last update.
     fn update_chart(&mut self, time_frame_per_second: f32) {
-        let total_people = self.community.len() as f32;
-        if let Some(&last) = self.total_time.last() {
-            self.total_time.push(last + time_frame_per_second);
+        // Half a tick of tolerance, so rounding errors do not skip a sample
+        if let Some(&last) = self.total_time.last()
+            && self.elapsed_time - last + time_frame_per_second / 2.0 < self.chart_interval
+        {
+            return;
+        }
+        self.push_chart_sample();
+        if self.total_time.len() > MAX_CHART_SAMPLES {
+            self.compact_chart();
         }
+    }
 
-        let current_infected = self
-            .community
-            .iter()
-            .filter(|person| matches!(person.state, PersonState::Infected))
-            .count() as f32;
-        self.infected_chart
-            .push((current_infected / total_people) * 100.0);
+    /// Adds a chart sample with the current state counts.
+    fn push_chart_sample(&mut self) {
+        let total_people = self.community.len() as f32;
+        self.total_time.push(self.elapsed_time);
 
-        let current_susceptible = self
-            .community
-            .iter()
-            .filter(|person| matches!(person.state, PersonState::Susceptible))
-            .count() as f32;
+        let (current_susceptible, current_infected, current_recovered) = self.count_states();
+        self.infected_chart
+            .push((current_infected as f32 / total_people) * 100.0);
         self.susceptible_chart
-            .push((current_susceptible / total_people) * 100.0);
-
-        let current_recovered = self
-            .community
-            .iter()
-            .filter(|person| matches!(person.state, PersonState::Recovered))
-            .count() as f32;
+            .push((current_susceptible as f32 / total_people) * 100.0);
         self.recovered_chart
-            .push((current_recovered / total_people) * 100.0);
+            .push((current_recovered as f32 / total_people) * 100.0);
+        self.state_counts
+            .push((current_susceptible, current_infected, current_recovered));
+
+        for (strain, strain_chart) in self.strain_charts.iter_mut().enumerate() {
+            let current_strain = self
+                .community
+                .iter()
+                .filter(|person| person.strain() == Some(strain))
+                .count() as f32;
+            strain_chart.push((current_strain / total_people) * 100.0);
+        }
+    }
+
+    /// Halves the chart history and doubles the sampling interval.
+    ///
+    /// The kept samples are chosen with LTTB on the infected curve, so the epidemic peak
+    /// stays in the chart. All series keep the same samples.
+    fn compact_chart(&mut self) {
+        let kept = downsample::lttb(
+            &self.total_time,
+            &self.infected_chart,
+            self.total_time.len() / 2,
+        );
+        retain_indices(&mut self.total_time, &kept);
+        retain_indices(&mut self.infected_chart, &kept);
+        retain_indices(&mut self.susceptible_chart, &kept);
+        retain_indices(&mut self.recovered_chart, &kept);
+        retain_indices(&mut self.state_counts, &kept);
+        for strain_chart in &mut self.strain_charts {
+            retain_indices(strain_chart, &kept);
+        }
+        self.chart_interval *= 2.0;
+    }
+
+    /// Derives the SIR ODE rates and initial state from the current run.
+    ///
+    /// The community size is the number of people in the run and the initial infections
+    /// are those at time 0, so UI values waiting for a restart are not used.
+    ///
+    /// # Returns
+    /// The SIR rates and the state at time 0
+    fn ode_inputs(&self) -> (SirParameters, SirState) {
+        let community_size = self.community.len();
+        let parameters =
+            SirParameters::from_agents(&self.settings, community_size, self.infected_radius);
+        let initial_infected = self
+            .state_counts
+            .first()
+            .map_or(0, |&(_, infected, _)| infected)
+            .min(community_size);
+        let initial_infected = initial_infected as f32 / community_size.max(1) as f32;
+        let initial = SirState {
+            susceptible: 1.0 - initial_infected,
+            infected: initial_infected,
+            recovered: 0.0,
+        };
+        (parameters, initial)
+    }
+
+    /// Solves the SIR ODE with rates derived from the agent parameters.
+    ///
+    /// # Returns
+    /// The ODE solution from the start of the run until now
+    pub fn ode_solution(&self) -> Vec<(f32, SirState)> {
+        let (parameters, initial) = self.ode_inputs();
+        ode::solve_sir(&parameters, initial, self.elapsed_time)
+    }
+
+    /// Extends the cached SIR ODE solution until now.
+    ///
+    /// The solution is solved again only when its rates or initial state change.
+    fn update_ode_cache(&mut self) {
+        let (parameters, initial) = self.ode_inputs();
+        let cache = match self.ode_cache.take() {
+            Some(cache) if cache.parameters == parameters && cache.initial == initial => cache,
+            _ => SirSolution::new(parameters, initial),
+        };
+        self.ode_cache.insert(cache).extend_to(self.elapsed_time);
     }
 
     /// Restarts the simulation with current UI settings.
     /// This method resets with the new settings from UI. 
     /// All people are recreated and chart data is cleared.
+    /// The initial infected people are infected with the original strain.
+    /// The random number generator is seeded with the configured seed (or a new random seed)
+    /// and the scenario, if loaded, is played back from the start.
     fn restart(&mut self) {
+        self.seed = self.settings.seed.unwrap_or_else(rand::random);
+        self.rng = ChaCha8Rng::seed_from_u64(self.seed);
+        self.next_event = 0;
+        self.time_accumulator = 0.0;
+        if let Some(settings) = self.scenario.as_ref().and_then(|s| s.settings.as_ref()) {
+            self.quarantine_enabled = settings.quarantine_enabled;
+            self.social_distancing_enabled = settings.social_distancing_enabled;
+            self.social_distancing_radius = settings.social_distancing_radius;
+        }
         self.infected_radius = self.ui_infected_radius;
-        self.community = (0..self.community_size).map(|_| Person::new()).collect();
-        for i in 0..self.initial_infected_count {
-            self.community[i].state = PersonState::Infected;
-            self.community[i].infection_duration = 0.0;
+        self.dispersion_k = self.ui_dispersion_k;
+        self.strain_set.strains[0].infection_probability = self.settings.infection_probability;
+        self.strain_set.strains[0].recovered_day = self.settings.recovered_day;
+        // The slider allows more initial infections than people
+        let initial_infected = self.initial_infected_count.min(self.community_size);
+        self.strain_set.strains[0].seed_count = initial_infected;
+        self.elapsed_time = 0.0;
+        self.community = (0..self.community_size)
+            .map(|_| Person::new(&self.settings, &mut self.rng))
+            .collect();
+        self.infection_log.clear();
+        self.selected_person = None;
+        self.recording.clear();
+        self.replay_frame = None;
+        for i in 0..initial_infected {
+            self.infect_person(i, 0, None);
         }
 
+        self.chart_interval = CHART_INTERVAL;
         self.total_time.clear();
         self.infected_chart.clear();
         self.susceptible_chart.clear();
         self.recovered_chart.clear();
+        self.state_counts.clear();
         self.total_time.push(0.0);
+        self.state_counts.push(self.count_states());
 
         self.infected_chart
-            .push((self.initial_infected_count as f32 / self.community_size as f32) * 100.0);
+            .push((initial_infected as f32 / self.community_size as f32) * 100.0);
         self.susceptible_chart.push(
-            ((self.community_size - self.initial_infected_count) as f32
-                / self.community_size as f32)
-                * 100.0,
+            ((self.community_size - initial_infected) as f32 / self.community_size as f32) * 100.0,
         );
         self.recovered_chart.push(0.0);
+
+        self.strain_charts = vec![vec![0.0]; self.strain_set.strains.len()];
+        self.strain_charts[0][0] = self.infected_chart[0];
     }
 }
 
-impl eframe::App for Simulation {
-    /// Implements the eframe::App trait to handle UI rendering and updates
-    fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
-        let time_frame_per_second: f32 = ctx.input(|i| i.stable_dt);
-        self.update_community(time_frame_per_second);
-        self.update_chart(time_frame_per_second);
-        egui::CentralPanel::default().show(ctx, |ui| {
-            // Basic Settings section
-            ui.label(egui::RichText::new("Basic Settings").size(18.0).strong());
-            ui.horizontal(|ui| {
-                ui.vertical(|ui| {
-                    ui.horizontal(|ui| {
-                        ui.label(egui::RichText::new("Community size:").size(15.0));
-                        ui.add(egui::Slider::new(&mut self.community_size, 20..=150));
-                    });
-
-                    ui.horizontal(|ui| {
-                        ui.label(egui::RichText::new("Initial Infected:").size(15.0));
-                        ui.add(egui::Slider::new(&mut self.initial_infected_count, 1..=30));
-                    });
-
-                    ui.horizontal(|ui| {
-                        ui.label(egui::RichText::new("Infected Radius:").size(15.0));
-                        ui.add(egui::Slider::new(&mut self.ui_infected_radius, 1.0..=16.0));
-                    });
-                });
-
-                let reset_button = ui.button(egui::RichText::new("Apply and Reset").size(15.0));
-                if reset_button.clicked() {
-                    self.restart();
+impl Simulation {
+    /// Shows the controls to load and save the settings from a configuration file.
+    ///
+    /// Loading replaces all settings and restarts the simulation. Saving writes the
+    /// current UI settings. The result of the last action is shown next to the buttons.
+    ///
+    /// # Parameters
+    /// - `ui`: The UI to draw into
+    fn show_config_controls(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            ui.label(egui::RichText::new("Config file:").size(15.0));
+            ui.text_edit_singleline(&mut self.config_path);
+
+            if ui.button(egui::RichText::new("Load").size(15.0)).clicked() {
+                match config::load_settings(&self.config_path) {
+                    Ok(settings) => {
+                        self.apply_settings(settings);
+                        self.config_status = format!("Loaded {}", self.config_path);
+                    }
+                    Err(error) => self.config_status = error.to_string(),
                 }
-            });
+            }
 
-            ui.separator();
-            // Prevention section
-            ui.label(
-                egui::RichText::new("Prevention Methods")
-                    .size(18.0)
-                    .strong(),
-            );
+            if ui.button(egui::RichText::new("Save").size(15.0)).clicked() {
+                match config::save_settings(&self.config_path, &self.current_settings()) {
+                    Ok(()) => self.config_status = format!("Saved {}", self.config_path),
+                    Err(error) => self.config_status = error.to_string(),
+                }
+            }
+        });
+        if !self.config_status.is_empty() {
+            ui.label(egui::RichText::new(&self.config_status).size(15.0));
+        }
+    }
 
-            // social distancing
+    /// Shows the width and height of the community and quarantine areas.
+    ///
+    /// Changes apply immediately: people and walls outside a smaller area are moved to
+    /// its edge. The density of the community is shown next to the sliders.
+    ///
+    /// # Parameters
+    /// - `ui`: The UI to draw into
+    fn show_area_controls(&mut self, ui: &mut egui::Ui) {
+        let mut changed = false;
+        ui.horizontal_wrapped(|ui| {
+            ui.label(egui::RichText::new("Community area:").size(15.0));
+            changed |= ui
+                .add(
+                    egui::Slider::new(&mut self.settings.simulation_area_width, 100.0..=800.0)
+                        .text("width"),
+                )
+                .changed();
+            changed |= ui
+                .add(
+                    egui::Slider::new(&mut self.settings.simulation_area_height, 100.0..=800.0)
+                        .text("height"),
+                )
+                .changed();
+            let area = self.settings.simulation_area_width * self.settings.simulation_area_height;
             ui.label(
-                egui::RichText::new("Social Distancing")
-                    .size(16.0)
-                    .underline(),
+                egui::RichText::new(format!(
+                    "{:.1} people per 100×100",
+                    self.community.len() as f32 / area * 10_000.0
+                ))
+                .size(15.0),
             );
+        });
+        ui.horizontal_wrapped(|ui| {
+            ui.label(egui::RichText::new("Quarantine area:").size(15.0));
+            changed |= ui
+                .add(
+                    egui::Slider::new(&mut self.settings.quarantine_area_width, 100.0..=500.0)
+                        .text("width"),
+                )
+                .changed();
+            changed |= ui
+                .add(
+                    egui::Slider::new(&mut self.settings.quarantine_area_height, 100.0..=500.0)
+                        .text("height"),
+                )
+                .changed();
+        });
+        if changed {
+            self.fit_to_areas();
+        }
+    }
 
-            ui.horizontal(|ui| {
-                ui.label(egui::RichText::new("Enable:").size(15.0));
-                ui.checkbox(&mut self.social_distancing_enabled, "");
+    /// Moves people and walls which are outside their area to its edge.
+    ///
+    /// This is used after the areas are resized.
+    fn fit_to_areas(&mut self) {
+        let margin = self.settings.margin_from_wall;
+        for person in &mut self.community {
+            let (width, height) = self.settings.area_size(person.is_in_quarantine);
+            person.x = person.x.min(width - margin).max(margin);
+            person.y = person.y.min(height - margin).max(margin);
+        }
+        let (width, height) = self.settings.area_size(false);
+        for wall in &mut self.settings.walls {
+            wall.x1 = wall.x1.clamp(0.0, width);
+            wall.x2 = wall.x2.clamp(0.0, width);
+            wall.y1 = wall.y1.clamp(0.0, height);
+            wall.y2 = wall.y2.clamp(0.0, height);
+        }
+        self.record_edit();
+    }
+
+    /// Shows the pause, single-step and speed controls with the current simulation time.
+    ///
+    /// # Parameters
+    /// - `ui`: The UI to draw into
+    fn show_playback_controls(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            let pause_text = if self.paused { "Resume" } else { "Pause" };
+            if ui
+                .button(egui::RichText::new(pause_text).size(15.0))
+                .clicked()
+            {
+                self.paused = !self.paused;
+            }
+
+            let step_button = egui::Button::new(egui::RichText::new("Step").size(15.0));
+            if ui.add_enabled(self.paused, step_button).clicked() {
+                self.step_tick();
+            }
+
+            ui.label(egui::RichText::new("Speed:").size(15.0));
+            ui.add_enabled(
+                !self.fast_forward,
+                egui::Slider::new(&mut self.speed, SPEED_RANGE.0..=SPEED_RANGE.1)
+                    .logarithmic(true)
+                    .suffix("×"),
+            );
+            ui.checkbox(
+                &mut self.fast_forward,
+                egui::RichText::new("As fast as possible").size(15.0),
+            );
+
+            ui.label(egui::RichText::new(format!("t = {:.2} s", self.elapsed_time)).size(15.0));
+        });
+        self.show_timeline(ui);
+    }
+
+    /// Shows the recording controls and the timeline to scrub back through the run.
+    ///
+    /// Moving the slider pauses the simulation and shows the recorded frame in the community
+    /// view, with a cursor on the chart. "Live" returns to the current state and "Resume
+    /// from here" rewinds the simulation to the shown frame, unless people were removed
+    /// after it.
+    ///
+    /// # Parameters
+    /// - `ui`: The UI to draw into
+    fn show_timeline(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            let record = ui.checkbox(
+                &mut self.recording_enabled,
+                egui::RichText::new("Record").size(15.0),
+            );
+            if record.changed() && !self.recording_enabled {
+                self.recording.clear();
+                self.replay_frame = None;
+            }
+
+            let frame_count = self.recording.frames.len();
+            if frame_count == 0 {
+                return;
+            }
+            let last_frame = frame_count - 1;
+            let mut frame = self.replay_frame.unwrap_or(last_frame);
+            let time = format!("{:.2} s", self.recording.frames[frame].time);
+            let slider = egui::Slider::new(&mut frame, 0..=last_frame)
+                .show_value(false)
+                .text(time);
+            if ui.add(slider).changed() {
+                self.paused = true;
+                self.show_replay_frame((frame < last_frame).then_some(frame));
+            }
+
+            if let Some(frame) = self.replay_frame {
+                if ui.button(egui::RichText::new("Live").size(15.0)).clicked() {
+                    self.show_replay_frame(None);
+                }
+                let resume = ui
+                    .add_enabled(
+                        self.recording.can_rewind(frame),
+                        egui::Button::new(egui::RichText::new("Resume from here").size(15.0)),
+                    )
+                    .on_disabled_hover_text("People were removed after this moment");
+                if resume.clicked() {
+                    self.rewind(frame);
+                }
+            }
+            if self.recording.is_full() {
+                ui.label(egui::RichText::new("Recording is full").size(15.0));
+            }
+        });
+    }
+
+    /// Shows the controls to save the current state to a snapshot file and resume from it.
+    ///
+    /// # Parameters
+    /// - `ui`: The UI to draw into
+    fn show_snapshot_controls(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            ui.label(egui::RichText::new("Snapshot file:").size(15.0));
+            ui.text_edit_singleline(&mut self.snapshot_path);
+
+            if ui.button(egui::RichText::new("Save").size(15.0)).clicked() {
+                self.snapshot_status = match snapshot::save_snapshot(&self.snapshot_path, self) {
+                    Ok(()) => format!("Saved {} at {:.1} s", self.snapshot_path, self.elapsed_time),
+                    Err(error) => error.to_string(),
+                };
+            }
+
+            if ui.button(egui::RichText::new("Load").size(15.0)).clicked() {
+                self.snapshot_status = match snapshot::load_snapshot(&self.snapshot_path) {
+                    Ok(snapshot) => {
+                        snapshot.restore(self);
+                        format!(
+                            "Resumed {} at {:.1} s",
+                            self.snapshot_path, self.elapsed_time
+                        )
+                    }
+                    Err(error) => error.to_string(),
+                };
+            }
+        });
+        if !self.snapshot_status.is_empty() {
+            ui.label(egui::RichText::new(&self.snapshot_status).size(15.0));
+        }
+    }
+
+    /// Shows the controls to load and clear a scenario file.
+    ///
+    /// Loading a scenario restarts the simulation with its settings and plays its events
+    /// back on every run until the scenario is cleared.
+    ///
+    /// # Parameters
+    /// - `ui`: The UI to draw into
+    fn show_scenario_controls(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            ui.label(egui::RichText::new("Scenario file:").size(15.0));
+            ui.text_edit_singleline(&mut self.scenario_path);
+
+            if ui.button(egui::RichText::new("Load").size(15.0)).clicked() {
+                let result = scenario::load_scenario(&self.scenario_path)
+                    .and_then(|scenario| self.set_scenario(scenario));
+                self.scenario_status = match result {
+                    Ok(()) => format!("Loaded {}", self.scenario_path),
+                    Err(error) => error.to_string(),
+                };
+            }
+
+            if ui.button(egui::RichText::new("Clear").size(15.0)).clicked() {
+                self.scenario = None;
+                self.scenario_status.clear();
+            }
+        });
+        if let Some(scenario) = &self.scenario {
+            let next = match scenario.events.get(self.next_event) {
+                Some(event) => format!("next event at {:.1} s", event.time()),
+                None => String::from("no more events"),
+            };
+            ui.label(
+                egui::RichText::new(format!(
+                    "Seed {}, {} events, {}",
+                    self.seed,
+                    scenario.events.len(),
+                    next
+                ))
+                .size(15.0),
+            );
+        }
+        if !self.scenario_status.is_empty() {
+            ui.label(egui::RichText::new(&self.scenario_status).size(15.0));
+        }
+    }
+
+    /// Shows the controls to export the chart data and infection log of the current run.
+    ///
+    /// The S/I/R counts, percentages and run parameters, or every infection with its
+    /// source, are written to a `.csv` or `.json` file.
+    ///
+    /// # Parameters
+    /// - `ui`: The UI to draw into
+    fn show_export_controls(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            ui.label(egui::RichText::new("Export file:").size(15.0));
+            ui.text_edit_singleline(&mut self.export_path);
+
+            if ui
+                .button(egui::RichText::new("Export data").size(15.0))
+                .clicked()
+            {
+                self.export_status = match TimeSeries::from_simulation(self).save(&self.export_path)
+                {
+                    Ok(()) => format!("Exported {}", self.export_path),
+                    Err(error) => error.to_string(),
+                };
+            }
+        });
+        ui.horizontal(|ui| {
+            ui.label(egui::RichText::new("Infection log file:").size(15.0));
+            ui.text_edit_singleline(&mut self.log_path);
+
+            if ui
+                .button(egui::RichText::new("Export log").size(15.0))
+                .clicked()
+            {
+                self.export_status = match self.infection_log.save(&self.log_path) {
+                    Ok(()) => format!(
+                        "Exported {} infections to {}",
+                        self.infection_log.events.len(),
+                        self.log_path
+                    ),
+                    Err(error) => error.to_string(),
+                };
+            }
+        });
+        ui.horizontal(|ui| {
+            ui.label(egui::RichText::new("Chart file:").size(15.0));
+            ui.text_edit_singleline(&mut self.chart_path);
+
+            if ui
+                .button(egui::RichText::new("Export chart").size(15.0))
+                .clicked()
+            {
+                self.export_status = match chart_svg::save_svg(&self.chart_path, self) {
+                    Ok(()) => format!("Exported {}", self.chart_path),
+                    Err(error) => error.to_string(),
+                };
+            }
+        });
+        if !self.export_status.is_empty() {
+            ui.label(egui::RichText::new(&self.export_status).size(15.0));
+        }
+    }
+
+    /// Shows the transmission tree of the current run.
+    ///
+    /// Time runs from left to right and each infection is a dot colored by the current
+    /// state of the person. Clicking a dot highlights the person in the community view.
+    /// The tree can be exported to a Newick or GraphML file.
+    ///
+    /// # Parameters
+    /// - `ui`: The UI to draw into
+    fn show_transmission_tree(&mut self, ui: &mut egui::Ui) {
+        let tree = TransmissionTree::from_log(&self.infection_log);
+
+        ui.horizontal(|ui| {
+            ui.label(egui::RichText::new("Tree file:").size(15.0));
+            ui.text_edit_singleline(&mut self.tree_path);
+
+            if ui
+                .button(egui::RichText::new("Export tree").size(15.0))
+                .clicked()
+            {
+                self.export_status = match tree.save(&self.tree_path) {
+                    Ok(()) => format!("Exported {}", self.tree_path),
+                    Err(error) => error.to_string(),
+                };
+            }
+        });
+
+        let (rows, row_count) = tree.rows();
+        let row_height = 12.0;
+        let padding = 10.0;
+        let width = ui.available_width();
+        let height = row_count as f32 * row_height + 2.0 * padding;
+        egui::ScrollArea::vertical()
+            .id_salt("transmission tree")
+            .max_height(300.0)
+            .show(ui, |ui| {
+                let (response, painter) =
+                    ui.allocate_painter(egui::vec2(width, height), egui::Sense::click());
+                let rect = response.rect;
+                painter.rect_filled(rect, 0.0, egui::Color32::BLACK);
+
+                let max_time = self.elapsed_time.max(1.0);
+                let position = |node: usize| {
+                    egui::pos2(
+                        rect.left()
+                            + padding
+                            + tree.nodes[node].time / max_time * (width - 2.0 * padding),
+                        rect.top() + padding + rows[node] * row_height + row_height / 2.0,
+                    )
+                };
+
+                let stroke = egui::Stroke::new(1.0, egui::Color32::LIGHT_GRAY);
+                for (index, node) in tree.nodes.iter().enumerate() {
+                    if let Some(parent) = node.parent {
+                        let start = position(parent);
+                        let end = position(index);
+                        painter.line_segment([start, egui::pos2(start.x, end.y)], stroke);
+                        painter.line_segment([egui::pos2(start.x, end.y), end], stroke);
+                    }
+                }
+
+                let mut clicked_person = None;
+                let pointer = response
+                    .interact_pointer_pos()
+                    .filter(|_| response.clicked());
+                for (index, node) in tree.nodes.iter().enumerate() {
+                    let center = position(index);
+                    let color = match self.community.get(node.person) {
+                        Some(person) => person.state.person_colors(),
+                        None => egui::Color32::WHITE,
+                    };
+                    painter.circle_filled(center, 4.0, color);
+                    if self.selected_person == Some(node.person) {
+                        painter.circle_stroke(
+                            center,
+                            6.0,
+                            egui::Stroke::new(2.0, egui::Color32::YELLOW),
+                        );
+                    }
+                    if let Some(pointer) = pointer
+                        && pointer.distance(center) <= 6.0
+                    {
+                        clicked_person = Some(node.person);
+                    }
+                }
+                if response.clicked() {
+                    self.selected_person = clicked_person;
+                }
+            });
+    }
+
+    /// Shows the details of the selected person.
+    ///
+    /// The panel shows the state, infection clock and quarantine status of the person,
+    /// who infected them and whom they infected. While replaying, the state and history
+    /// are those of the replayed moment. Clicking another person in the panel selects them.
+    ///
+    /// # Parameters
+    /// - `ui`: The UI to draw into
+    /// - `index`: Index of the selected person in the community
+    fn show_person_inspector(&mut self, ui: &mut egui::Ui, index: usize) {
+        ui.label(
+            egui::RichText::new(format!("Person {}", index))
+                .size(18.0)
+                .strong(),
+        );
+        let Some(person) = self.community.get(index) else {
+            self.selected_person = None;
+            return;
+        };
+        let history = PersonHistory::from_log(&self.infection_log, index, self.shown_time());
+        // While replaying, the state is that of the replayed frame
+        let recorded = self
+            .replay_frame
+            .and_then(|frame| self.recording.frames.get(frame))
+            .and_then(|frame| frame.people.get(index));
+        let (person_state, is_in_quarantine) = match recorded {
+            Some(recorded) => (recorded.state, recorded.is_in_quarantine),
+            None => (person.state, person.is_in_quarantine),
+        };
+
+        let state = match person_state {
+            PersonState::Susceptible => "Susceptible".to_string(),
+            PersonState::Infected(strain) => match self.strain_set.strains.get(strain) {
+                Some(strain) => format!("Infected ({})", strain.name),
+                None => "Infected".to_string(),
+            },
+            PersonState::Recovered => "Recovered".to_string(),
+        };
+        let mut lines = vec![format!("State: {}", state)];
+        if recorded.is_none() && person.is_infected() {
+            lines.push(format!("Infected for: {:.1} s", person.infection_duration));
+            lines.push(format!("Infectiousness: {:.2}", person.infectiousness));
+        }
+        lines.push(format!(
+            "Quarantine: {}",
+            if is_in_quarantine { "yes" } else { "no" }
+        ));
+        lines.push(format!("Times infected: {}", history.infection_count));
+        if let Some(time) = history.infected_at {
+            lines.push(format!("Last infected at: {:.1} s", time));
+        }
+        for line in lines {
+            ui.label(egui::RichText::new(line).size(15.0));
+        }
+
+        let mut selected = None;
+        ui.horizontal(|ui| {
+            ui.label(egui::RichText::new("Infected by:").size(15.0));
+            match history.infector {
+                Some(infector) => {
+                    if ui.link(format!("Person {}", infector)).clicked() {
+                        selected = Some(infector);
+                    }
+                }
+                None if history.infection_count > 0 => {
+                    ui.label(egui::RichText::new("seeded or imported").size(15.0));
+                }
+                None => {
+                    ui.label(egui::RichText::new("nobody").size(15.0));
+                }
+            }
+        });
+        ui.label(
+            egui::RichText::new(format!("Infected {} people:", history.infectees.len())).size(15.0),
+        );
+        egui::ScrollArea::vertical()
+            .id_salt("infectees")
+            .max_height(200.0)
+            .show(ui, |ui| {
+                for &infectee in &history.infectees {
+                    if ui.link(format!("Person {}", infectee)).clicked() {
+                        selected = Some(infectee);
+                    }
+                }
             });
 
-            ui.horizontal(|ui| {
-                ui.label(egui::RichText::new("Radius:").size(15.0));
-                ui.add_enabled(
-                    self.social_distancing_enabled,
-                    egui::Slider::new(&mut self.social_distancing_radius, 0.0..=50.0),
+        if ui.button(egui::RichText::new("Close").size(15.0)).clicked() {
+            self.selected_person = None;
+        }
+        if selected.is_some() {
+            self.selected_person = selected;
+        }
+    }
+
+    /// Shows the settings and file controls of the side panel.
+    ///
+    /// # Parameters
+    /// - `ui`: The UI to draw into
+    fn show_controls(&mut self, ui: &mut egui::Ui) {
+        // Basic Settings section
+        ui.label(egui::RichText::new("Basic Settings").size(18.0).strong());
+        ui.horizontal(|ui| {
+            ui.label(egui::RichText::new("Community size:").size(15.0));
+            // People added or removed with the mouse can go beyond the slider range
+            ui.add(
+                egui::Slider::new(&mut self.community_size, 20..=150)
+                    .clamping(egui::SliderClamping::Never),
+            );
+        });
+
+        ui.horizontal(|ui| {
+            ui.label(egui::RichText::new("Initial Infected:").size(15.0));
+            ui.add(egui::Slider::new(&mut self.initial_infected_count, 0..=30));
+        });
+
+        ui.horizontal(|ui| {
+            ui.label(egui::RichText::new("Infected Radius:").size(15.0));
+            ui.add(egui::Slider::new(&mut self.ui_infected_radius, 1.0..=16.0));
+        });
+
+        ui.horizontal_wrapped(|ui| {
+            let reset_button = ui.button(egui::RichText::new("Apply and Reset").size(15.0));
+            if reset_button.clicked() {
+                self.restart();
+            }
+
+            self.show_ensemble_controls(ui);
+        });
+
+        self.show_area_controls(ui);
+        self.show_playback_controls(ui);
+
+        // Configuration file
+        self.show_config_controls(ui);
+        self.show_scenario_controls(ui);
+        self.show_snapshot_controls(ui);
+
+        ui.separator();
+        // Prevention section
+        ui.label(
+            egui::RichText::new("Prevention Methods")
+                .size(18.0)
+                .strong(),
+        );
+
+        // social distancing
+        ui.label(
+            egui::RichText::new("Social Distancing")
+                .size(16.0)
+                .underline(),
+        );
+
+        ui.horizontal(|ui| {
+            ui.label(egui::RichText::new("Enable:").size(15.0));
+            ui.checkbox(&mut self.social_distancing_enabled, "");
+        });
+
+        ui.horizontal(|ui| {
+            ui.label(egui::RichText::new("Radius:").size(15.0));
+            ui.add_enabled(
+                self.social_distancing_enabled,
+                egui::Slider::new(&mut self.social_distancing_radius, 0.0..=50.0),
+            );
+        });
+
+        // quarantine
+        ui.label(egui::RichText::new("Quarantine").size(16.0).underline());
+
+        ui.horizontal(|ui| {
+            ui.label(egui::RichText::new("Enable:").size(15.0));
+            let quarantine_changed = ui.checkbox(&mut self.quarantine_enabled, "");
+            if quarantine_changed.changed() && self.quarantine_enabled {
+                self.restart();
+            }
+        });
+
+        ui.horizontal(|ui| {
+            ui.label(egui::RichText::new("Time before quarantine:").size(15.0));
+            ui.add_enabled(
+                self.quarantine_enabled,
+                egui::Slider::new(&mut self.infection_time_before_quarantine, 1.0..=5.5),
+            );
+        });
+
+        ui.separator();
+        // Superspreading section
+        ui.label(egui::RichText::new("Superspreading").size(18.0).strong());
+
+        ui.horizontal(|ui| {
+            ui.label(egui::RichText::new("Enable:").size(15.0));
+            let superspreading_changed = ui.checkbox(&mut self.superspreading_enabled, "");
+            if superspreading_changed.changed() {
+                self.restart();
+            }
+        });
+
+        ui.horizontal(|ui| {
+            ui.label(egui::RichText::new("Dispersion k:").size(15.0));
+            ui.add_enabled(
+                self.superspreading_enabled,
+                egui::Slider::new(&mut self.ui_dispersion_k, 0.05..=10.0).logarithmic(true),
+            );
+        });
+
+        ui.separator();
+        // Variants section
+        self.show_variant_settings(ui);
+
+        ui.separator();
+        // Parameter sweep section
+        egui::CollapsingHeader::new(egui::RichText::new("Parameter Sweep").size(18.0).strong())
+            .show(ui, |ui| self.show_sweep(ui));
+
+        ui.separator();
+        // Export section
+        self.show_export_controls(ui);
+    }
+
+    /// Shows the community and quarantine areas with the people, scaled to the window.
+    ///
+    /// The world is drawn through a view transform which fits it into the available
+    /// width and a share of the window height. Clicking or dragging uses the selected
+    /// mouse tool.
+    ///
+    /// # Parameters
+    /// - `ui`: The UI to draw into
+    fn show_community_view(&mut self, ui: &mut egui::Ui) {
+        ui.heading("Community Simulation");
+        ui.horizontal_wrapped(|ui| {
+            ui.checkbox(
+                &mut self.heatmap_enabled,
+                egui::RichText::new("Infection heatmap").size(15.0),
+            );
+            ui.checkbox(
+                &mut self.settings.collisions_enabled,
+                egui::RichText::new("Collisions").size(15.0),
+            );
+            if ui
+                .button(egui::RichText::new("Reset heatmap").size(15.0))
+                .clicked()
+            {
+                self.heatmap.clear();
+            }
+        });
+        ui.horizontal_wrapped(|ui| {
+            ui.label(egui::RichText::new("Mouse tool:").size(15.0));
+            for tool in PaintTool::ALL {
+                ui.selectable_value(&mut self.paint_tool, tool, tool.name());
+            }
+            if ui
+                .add_enabled(
+                    !self.settings.walls.is_empty(),
+                    egui::Button::new(egui::RichText::new("Clear walls").size(15.0)),
+                )
+                .clicked()
+            {
+                self.settings.walls.clear();
+                self.record_edit();
+            }
+        });
+
+        // World layout: the quarantine area is to the right of the community
+        let padding = self.settings.border_padding;
+        let (community_width, community_height) = self.settings.area_size(false);
+        let (quarantine_width, quarantine_height) = self.settings.area_size(true);
+        let quarantine_x = community_width + self.settings.gap_community_quarantine;
+        let world_size = egui::vec2(
+            quarantine_x + quarantine_width + padding * 2.0,
+            community_height.max(quarantine_height) + padding * 2.0,
+        );
+        let available = egui::vec2(
+            ui.available_width(),
+            ui.clip_rect().height() * COMMUNITY_VIEW_HEIGHT,
+        );
+        let scale = ViewTransform::fit_scale(world_size, available);
+
+        let (response, painter) =
+            ui.allocate_painter(world_size * scale, egui::Sense::click_and_drag());
+        let view = ViewTransform {
+            origin: response.rect.min + egui::vec2(padding, padding) * scale,
+            scale,
+        };
+        let line_width = (3.0 * scale).max(1.0);
+        let person_radius = self.settings.person_radius * scale;
+
+        ui.visuals_mut().panel_fill = egui::Color32::BLACK;
+
+        // Community
+        let main_rect = view.rect(0.0, 0.0, community_width, community_height);
+        painter.rect_filled(main_rect, 0.0, egui::Color32::BLACK);
+        painter.rect_stroke(
+            main_rect,
+            0.0,
+            egui::Stroke::new(line_width, egui::Color32::WHITE),
+        );
+
+        // infection heatmap, under the people
+        let max_count = self.heatmap.max_count();
+        if self.heatmap_enabled && max_count > 0 {
+            let cell_width = community_width / self.heatmap.cells as f32;
+            let cell_height = community_height / self.heatmap.cells as f32;
+            for (index, &count) in self.heatmap.counts.iter().enumerate() {
+                if count == 0 {
+                    continue;
+                }
+                let column = (index % self.heatmap.cells) as f32;
+                let row = (index / self.heatmap.cells) as f32;
+                let cell_rect = view.rect(
+                    column * cell_width,
+                    row * cell_height,
+                    cell_width,
+                    cell_height,
+                );
+                let alpha = (count as f32 / max_count as f32 * 170.0) as u8;
+                painter.rect_filled(
+                    cell_rect,
+                    0.0,
+                    egui::Color32::from_rgba_unmultiplied(255, 120, 0, alpha),
+                );
+            }
+        }
+
+        // walls
+        for wall in &self.settings.walls {
+            painter.line_segment(
+                [
+                    view.to_screen(wall.x1, wall.y1),
+                    view.to_screen(wall.x2, wall.y2),
+                ],
+                egui::Stroke::new(line_width, egui::Color32::WHITE),
+            );
+        }
+
+        let label_offset = egui::vec2(0.0, -padding * scale / 2.0);
+        painter.text(
+            main_rect.left_top() + label_offset,
+            egui::Align2::LEFT_CENTER,
+            "Community",
+            egui::FontId::proportional(15.0),
+            egui::Color32::WHITE,
+        );
+
+        // quarantine
+        let quarantine_rect = view.rect(quarantine_x, 0.0, quarantine_width, quarantine_height);
+        painter.rect_filled(quarantine_rect, 0.0, egui::Color32::BLACK);
+        painter.rect_stroke(
+            quarantine_rect,
+            0.0,
+            egui::Stroke::new(line_width, egui::Color32::WHITE),
+        );
+
+        painter.text(
+            quarantine_rect.left_top() + label_offset,
+            egui::Align2::LEFT_CENTER,
+            "Quarantine Zone",
+            egui::FontId::proportional(15.0),
+            egui::Color32::WHITE,
+        );
+
+        // draw a wall by dragging from one end to the other
+        let to_community = |pointer: egui::Pos2| {
+            let (x, y) = view.to_world(pointer);
+            (
+                x.clamp(0.0, community_width),
+                y.clamp(0.0, community_height),
+            )
+        };
+        if self.paint_tool == PaintTool::Wall && self.replay_frame.is_none() {
+            if response.drag_started()
+                && let Some(pointer) = response.interact_pointer_pos()
+                && main_rect.contains(pointer)
+            {
+                self.wall_start = Some(to_community(pointer));
+            }
+            if let Some((x1, y1)) = self.wall_start
+                && let Some(pointer) = response.interact_pointer_pos()
+            {
+                let (x2, y2) = to_community(pointer);
+                if response.drag_stopped() {
+                    self.wall_start = None;
+                    if (x2 - x1).hypot(y2 - y1) > self.settings.person_radius * 2.0 {
+                        self.add_wall(Wall { x1, y1, x2, y2 });
+                    }
+                } else {
+                    painter.line_segment(
+                        [view.to_screen(x1, y1), view.to_screen(x2, y2)],
+                        egui::Stroke::new(line_width, egui::Color32::LIGHT_GRAY),
+                    );
+                }
+            }
+        } else {
+            self.wall_start = None;
+        }
+
+        // paint with the mouse tool, only on the live simulation
+        if !matches!(self.paint_tool, PaintTool::Select | PaintTool::Wall)
+            && self.replay_frame.is_none()
+            && (response.clicked() || response.dragged())
+            && let Some(pointer) = response.interact_pointer_pos()
+            && main_rect.contains(pointer)
+        {
+            let (x, y) = view.to_world(pointer);
+            self.paint(self.paint_tool, x, y);
+        }
+        if self.paint_tool.uses_brush()
+            && let Some(hover) = response.hover_pos()
+            && main_rect.contains(hover)
+        {
+            painter.circle_stroke(
+                hover,
+                BRUSH_RADIUS * scale,
+                egui::Stroke::new(1.0, egui::Color32::LIGHT_GRAY),
+            );
+        }
+
+        // people, from the recorded frame while replaying
+        let people: Vec<RecordedPerson> =
+            match self.replay_frame.and_then(|i| self.recording.frames.get(i)) {
+                Some(frame) => frame.people.clone(),
+                None => self.community.iter().map(RecordedPerson::from).collect(),
+            };
+        let dot_positions: Vec<(f32, f32)> = people
+            .iter()
+            .map(|person| {
+                let offset_x = if person.is_in_quarantine {
+                    quarantine_x
+                } else {
+                    0.0
+                };
+                let position = view.to_screen(offset_x + person.x, person.y);
+                (position.x, position.y)
+            })
+            .collect();
+        for (person, &(x, y)) in people.iter().zip(&dot_positions) {
+            painter.circle_filled(
+                egui::pos2(x, y),
+                person_radius,
+                person.state.person_colors(),
+            );
+        }
+
+        // select the clicked person, or clear the selection on empty space
+        if self.paint_tool == PaintTool::Select
+            && response.clicked()
+            && let Some(pointer) = response.interact_pointer_pos()
+        {
+            self.selected_person = inspector::nearest_person(
+                &dot_positions,
+                pointer.x,
+                pointer.y,
+                person_radius + 4.0,
+            );
+        }
+
+        // highlighted person, linked to their infector and infectees
+        if let Some(index) = self.selected_person
+            && let Some(&(x, y)) = dot_positions.get(index)
+        {
+            let center = egui::pos2(x, y);
+            let history = PersonHistory::from_log(&self.infection_log, index, self.shown_time());
+            let infector = history.infector.into_iter().map(|person| (person, true));
+            let infectees = history.infectees.iter().map(|&person| (person, false));
+            for (person, is_infector) in infector.chain(infectees) {
+                if let Some(&(other_x, other_y)) = dot_positions.get(person) {
+                    let color = if is_infector {
+                        egui::Color32::from_rgb(255, 140, 0)
+                    } else {
+                        egui::Color32::LIGHT_YELLOW
+                    };
+                    painter.line_segment(
+                        [center, egui::pos2(other_x, other_y)],
+                        egui::Stroke::new(1.0, color),
+                    );
+                }
+            }
+            painter.circle_stroke(
+                center,
+                person_radius + 3.0,
+                egui::Stroke::new(2.0, egui::Color32::YELLOW),
+            );
+        }
+    }
+
+    /// Shows the settings of the co-circulating variants.
+    ///
+    /// Each variant has sliders for its transmissibility, duration, seeding time and
+    /// number of seeded cases, and the cross-immunity matrix can be edited.
+    /// Enabling, adding or removing variants restarts the simulation.
+    ///
+    /// # Parameters
+    /// - `ui`: The UI to draw into
+    fn show_variant_settings(&mut self, ui: &mut egui::Ui) {
+        ui.label(egui::RichText::new("Variants").size(18.0).strong());
+
+        ui.horizontal(|ui| {
+            ui.label(egui::RichText::new("Enable:").size(15.0));
+            let variants_changed = ui.checkbox(&mut self.variants_enabled, "");
+            if variants_changed.changed() {
+                self.restart();
+            }
+        });
+        if !self.variants_enabled {
+            return;
+        }
+
+        let mut removed_variant = None;
+        for (index, strain) in self.strain_set.strains.iter_mut().enumerate().skip(1) {
+            ui.horizontal(|ui| {
+                ui.label(
+                    egui::RichText::new(&strain.name)
+                        .size(16.0)
+                        .color(strain_color(index)),
+                );
+                if ui.button("Remove").clicked() {
+                    removed_variant = Some(index);
+                }
+            });
+            ui.horizontal(|ui| {
+                ui.label(egui::RichText::new("Infection probability:").size(15.0));
+                ui.add(egui::Slider::new(
+                    &mut strain.infection_probability,
+                    0.0..=1.0,
+                ));
+                ui.label(egui::RichText::new("Recovered day:").size(15.0));
+                ui.add(egui::Slider::new(&mut strain.recovered_day, 1.0..=20.0));
+            });
+            ui.horizontal(|ui| {
+                ui.label(egui::RichText::new("Seed time:").size(15.0));
+                ui.add(egui::Slider::new(&mut strain.seed_time, 0.0..=60.0));
+                ui.label(egui::RichText::new("Seed cases:").size(15.0));
+                ui.add(egui::Slider::new(&mut strain.seed_count, 1..=30));
+            });
+        }
+        if let Some(index) = removed_variant {
+            self.strain_set.remove_variant(index);
+            self.restart();
+        }
+        if ui
+            .button(egui::RichText::new("Add variant").size(15.0))
+            .clicked()
+        {
+            self.strain_set.add_variant(&self.settings);
+            self.restart();
+        }
+
+        ui.label(
+            egui::RichText::new("Cross-immunity (row: recovered from, column: exposed to)")
+                .size(15.0),
+        );
+        let strain_count = self.strain_set.strains.len();
+        egui::Grid::new("Cross-immunity grid").show(ui, |ui| {
+            ui.label("");
+            for strain in &self.strain_set.strains {
+                ui.label(&strain.name);
+            }
+            ui.end_row();
+            for row in 0..strain_count {
+                ui.label(&self.strain_set.strains[row].name);
+                for column in 0..strain_count {
+                    ui.add(
+                        egui::DragValue::new(&mut self.strain_set.cross_immunity[row][column])
+                            .speed(0.01)
+                            .range(0.0..=1.0),
+                    );
+                }
+                ui.end_row();
+            }
+        });
+    }
+
+    /// Shows the percentage of people infected with each strain over time.
+    ///
+    /// # Parameters
+    /// - `ui`: The UI to draw into
+    fn show_strain_chart(&self, ui: &mut egui::Ui) {
+        Plot::new("Strain chart")
+            .height(chart_height(ui, 0.25))
+            .x_axis_label("Time")
+            .y_axis_label("Infected percentage")
+            .include_y(0.0)
+            .legend(
+                egui_plot::Legend::default()
+                    .position(egui_plot::Corner::RightTop)
+                    .background_alpha(0.8),
+            )
+            .show(ui, |plot_ui| {
+                for (strain, strain_chart) in self.strain_charts.iter().enumerate() {
+                    let last_percentage = strain_chart.last().copied().unwrap_or(0.0);
+                    let points: PlotPoints = self
+                        .total_time
+                        .iter()
+                        .zip(strain_chart.iter())
+                        .map(|(&time, &percentage)| [time as f64, percentage as f64])
+                        .collect();
+                    plot_ui.line(Line::new(points).color(strain_color(strain)).name(format!(
+                        "{:.1}% {}",
+                        last_percentage, self.strain_set.strains[strain].name
+                    )));
+                }
+            });
+    }
+
+    /// Shows the checkbox for the SIR ODE overlay and the derived rates.
+    ///
+    /// The cached ODE solution is extended until now if the overlay is enabled.
+    ///
+    /// # Parameters
+    /// - `ui`: The UI to draw into
+    fn show_ode_controls(&mut self, ui: &mut egui::Ui) {
+        let (parameters, _) = self.ode_inputs();
+        ui.horizontal(|ui| {
+            ui.label(egui::RichText::new("ODE SIR overlay:").size(15.0));
+            ui.checkbox(&mut self.ode_overlay_enabled, "");
+            if self.ode_overlay_enabled {
+                ui.label(
+                    egui::RichText::new(format!(
+                        "β = {:.3}/s, γ = {:.3}/s, R0 = {:.2}",
+                        parameters.beta,
+                        parameters.gamma,
+                        parameters.basic_reproduction_number()
+                    ))
+                    .size(15.0),
+                );
+            }
+        });
+        if self.ode_overlay_enabled {
+            self.update_ode_cache();
+        }
+    }
+
+    /// Shows the selection of the SIR chart view and its scale.
+    ///
+    /// # Parameters
+    /// - `ui`: The UI to draw into
+    fn show_chart_view_controls(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            ui.label(egui::RichText::new("Chart view:").size(15.0));
+            for view in ChartView::ALL {
+                ui.selectable_value(&mut self.chart_view, view, view.name());
+            }
+        });
+        ui.horizontal(|ui| {
+            ui.label(egui::RichText::new("Counts:").size(15.0));
+            ui.checkbox(&mut self.chart_counts, "");
+            ui.label(egui::RichText::new("Log scale:").size(15.0));
+            ui.add_enabled(
+                self.chart_view.supports_log_scale(),
+                egui::Checkbox::without_text(&mut self.chart_log_scale),
+            );
+        });
+    }
+
+    /// Shows the SIR chart in the selected view.
+    ///
+    /// A yellow line marks the replayed time while scrubbing through a recording.
+    ///
+    /// # Parameters
+    /// - `ui`: The UI to draw into
+    /// - `ode_solution`: The SIR ODE solution to overlay, if enabled
+    fn show_sir_chart(&self, ui: &mut egui::Ui, ode_solution: Option<&[(f32, SirState)]>) {
+        let log_scale = self.chart_log_scale && self.chart_view.supports_log_scale();
+        let mut plot = Plot::new("SIR chart")
+            .height(chart_height(ui, 0.4))
+            .x_axis_label("Time")
+            .y_axis_label(chart_view::y_axis_label(self))
+            .legend(
+                egui_plot::Legend::default()
+                    .position(egui_plot::Corner::RightTop)
+                    .background_alpha(0.8),
+            );
+        if log_scale {
+            plot = plot.y_axis_formatter(|mark, _range| chart_view::log_axis_label(mark.value));
+        } else {
+            plot = plot.include_y(0.0);
+            if self.chart_view == ChartView::Stacked {
+                let top = if self.chart_counts {
+                    self.community.len() as f64
+                } else {
+                    100.0
+                };
+                plot = plot.include_y(top);
+            }
+        }
+        let series = chart_view::chart_series(self, ode_solution);
+        plot.show(ui, |plot_ui| {
+            for line in series {
+                let mut plot_line = Line::new(PlotPoints::from(line.points))
+                    .color(line.color)
+                    .name(line.name);
+                // Areas down to zero cannot be drawn on a logarithmic axis
+                if line.fill && !log_scale {
+                    plot_line = plot_line.fill(0.0);
+                }
+                if line.dashed {
+                    plot_line = plot_line.style(LineStyle::dashed_loose()).width(2.0);
+                }
+                plot_ui.line(plot_line);
+            }
+
+            if let Some(frame) = self.replay_frame.and_then(|i| self.recording.frames.get(i)) {
+                plot_ui.vline(
+                    VLine::new(frame.time as f64)
+                        .color(egui::Color32::YELLOW)
+                        .width(2.0),
+                );
+            }
+        });
+    }
+
+    /// Shows the effective reproduction number (Rt) over time.
+    ///
+    /// The true cohort Rt comes from the realised secondary cases of the infections in
+    /// each second, and the estimate from the incidence alone. A dashed line marks Rt = 1.
+    ///
+    /// # Parameters
+    /// - `ui`: The UI to draw into
+    fn show_rt_chart(&self, ui: &mut egui::Ui) {
+        let series = RtSeries::from_log(
+            &self.infection_log,
+            self.elapsed_time,
+            self.strain_set.strains[0].recovered_day,
+        );
+        let points = |values: &[Option<f32>]| -> PlotPoints {
+            series
+                .times
+                .iter()
+                .zip(values)
+                .filter_map(|(&time, value)| {
+                    value.map(|value| [(time + RT_BIN / 2.0) as f64, value as f64])
+                })
+                .collect()
+        };
+        let latest = |values: &[Option<f32>]| match values.iter().rev().flatten().next() {
+            Some(value) => format!("{:.2}", value),
+            None => String::from("-"),
+        };
+
+        Plot::new("Rt chart")
+            .height(chart_height(ui, 0.25))
+            .x_axis_label("Time")
+            .y_axis_label("Rt")
+            .include_y(0.0)
+            .include_y(2.0)
+            .legend(
+                egui_plot::Legend::default()
+                    .position(egui_plot::Corner::RightTop)
+                    .background_alpha(0.8),
+            )
+            .show(ui, |plot_ui| {
+                plot_ui.hline(
+                    HLine::new(1.0)
+                        .color(egui::Color32::GRAY)
+                        .style(LineStyle::dashed_dense()),
+                );
+                plot_ui.line(
+                    Line::new(points(&series.cohort))
+                        .color(egui::Color32::from_rgb(255, 165, 0))
+                        .name(format!("Cohort Rt {}", latest(&series.cohort))),
+                );
+                plot_ui.line(
+                    Line::new(points(&series.estimated))
+                        .color(egui::Color32::LIGHT_BLUE)
+                        .name(format!("Estimated Rt {}", latest(&series.estimated))),
                 );
             });
+    }
+
+    /// Starts an ensemble of runs with the current UI settings in a background thread.
+    ///
+    /// The runs use the seeds following the seed of the current run, and the loaded
+    /// scenario, if any, is played back in every run.
+    pub fn start_ensemble(&mut self) {
+        if self.ensemble_task.is_some() {
+            return;
+        }
+        let settings = self.current_settings();
+        let scenario = self.scenario.clone();
+        let base_seed = self.seed.wrapping_add(1);
+        let runs = self.ensemble_runs;
+        let duration = self.ensemble_duration;
+        self.ensemble_status.clear();
+        self.ensemble_task = Some(thread::spawn(move || {
+            ensemble::run_ensemble(&settings, scenario.as_ref(), base_seed, runs, duration)
+        }));
+    }
 
-            // quarantine
-            ui.label(egui::RichText::new("Quarantine").size(16.0).underline());
+    /// Takes the results of the ensemble running in the background once it is finished.
+    fn poll_ensemble(&mut self) {
+        let finished = match &self.ensemble_task {
+            Some(task) => task.is_finished(),
+            None => false,
+        };
+        if !finished {
+            return;
+        }
+        if let Some(task) = self.ensemble_task.take() {
+            match task.join() {
+                Ok(Ok(ensemble)) => self.ensemble = Some(ensemble),
+                Ok(Err(error)) => self.ensemble_status = error.to_string(),
+                Err(_) => self.ensemble_status = String::from("the ensemble stopped unexpectedly"),
+            }
+        }
+    }
 
+    /// Shows the button and settings to run an ensemble.
+    ///
+    /// # Parameters
+    /// - `ui`: The UI to draw into
+    fn show_ensemble_controls(&mut self, ui: &mut egui::Ui) {
+        ui.vertical(|ui| {
+            let running = self.ensemble_task.is_some();
             ui.horizontal(|ui| {
-                ui.label(egui::RichText::new("Enable:").size(15.0));
-                let quarantine_changed = ui.checkbox(&mut self.quarantine_enabled, "");
-                if quarantine_changed.changed() && self.quarantine_enabled {
-                    self.restart();
+                let ensemble_button = ui.add_enabled(
+                    !running,
+                    egui::Button::new(egui::RichText::new("Run ensemble").size(15.0)),
+                );
+                if ensemble_button.clicked() {
+                    self.start_ensemble();
+                }
+                if running {
+                    ui.spinner();
                 }
             });
-
             ui.horizontal(|ui| {
-                ui.label(egui::RichText::new("Time before quarantine:").size(15.0));
-                ui.add_enabled(
-                    self.quarantine_enabled,
-                    egui::Slider::new(&mut self.infection_time_before_quarantine, 1.0..=5.5),
+                ui.label(egui::RichText::new("Runs:").size(15.0));
+                ui.add(egui::DragValue::new(&mut self.ensemble_runs).range(2..=500));
+                ui.label(egui::RichText::new("Duration:").size(15.0));
+                ui.add(
+                    egui::DragValue::new(&mut self.ensemble_duration)
+                        .range(1.0..=600.0)
+                        .suffix(" s"),
                 );
             });
+            if !self.ensemble_status.is_empty() {
+                ui.label(egui::RichText::new(&self.ensemble_status).size(15.0));
+            }
+        });
+    }
 
-            ui.separator();
-            // Chart
-            if !self.total_time.is_empty() {
-                Plot::new("SIR chart")
-                    .height(350.0)
-                    .x_axis_label("Time")
-                    .y_axis_label("Percentage")
-                    .include_y(0.0)
-                    .include_y(100.0)
-                    .legend(
-                        egui_plot::Legend::default()
-                            .position(egui_plot::Corner::RightTop)
-                            .background_alpha(0.8),
-                    )
-                    .show(ui, |plot_ui| {
-                        // Infected chart
-                        let last_infected_percentage = match self.infected_chart.last() {
-                            Some(&percentage) => percentage,
-                            None => 0.0,
-                        };
-                        let infected_points: PlotPoints = (0..self.total_time.len())
-                            .map(|i| {
-                                let time = self.total_time[i];
-                                let infected_percentage = self.infected_chart[i];
-                                [time as f64, infected_percentage as f64]
-                            })
-                            .collect();
-
-                        plot_ui.line(
-                            Line::new(infected_points)
-                                .color(egui::Color32::RED)
-                                .name(format!("{:.1}% infected", last_infected_percentage))
-                                .fill(0.0),
-                        );
+    /// Shows the results of the last ensemble.
+    ///
+    /// The median infected curve is drawn with its 50% and 90% bands, followed by
+    /// histograms of the peak size, peak time and final attack rate of the runs.
+    ///
+    /// # Parameters
+    /// - `ui`: The UI to draw into
+    fn show_ensemble(&self, ui: &mut egui::Ui) {
+        let Some(ensemble) = &self.ensemble else {
+            return;
+        };
+        ui.label(
+            egui::RichText::new(format!("Ensemble of {} runs", ensemble.runs.len()))
+                .size(18.0)
+                .strong(),
+        );
 
-                        // Susceptible chart
-                        let last_susceptible_percentage = match self.susceptible_chart.last() {
-                            Some(&percentage) => percentage,
-                            None => 0.0,
-                        };
-                        let susceptible_points: PlotPoints = (0..self.total_time.len())
-                            .map(|i| {
-                                let time = self.total_time[i];
-                                let susceptible_percentage = self.susceptible_chart[i];
-                                let infected_percentage = self.infected_chart[i];
-                                [
-                                    time as f64,
-                                    (infected_percentage + susceptible_percentage) as f64,
-                                ]
-                            })
-                            .collect();
-
-                        plot_ui.line(
-                            Line::new(susceptible_points)
-                                .color(egui::Color32::BLUE)
-                                .name(format!("{:.1}% Susceptible", last_susceptible_percentage))
-                                .fill(0.0),
-                        );
+        let band = |band: &[(f32, f32)]| -> PlotPoints {
+            let upper = ensemble
+                .times
+                .iter()
+                .zip(band)
+                .map(|(&time, &(_, high))| [time as f64, high as f64]);
+            let lower = ensemble
+                .times
+                .iter()
+                .zip(band)
+                .rev()
+                .map(|(&time, &(low, _))| [time as f64, low as f64]);
+            upper.chain(lower).collect()
+        };
+        let red = egui::Color32::RED;
+        Plot::new("Ensemble chart")
+            .height(chart_height(ui, 0.3))
+            .x_axis_label("Time")
+            .y_axis_label("Infected percentage")
+            .include_y(0.0)
+            .legend(
+                egui_plot::Legend::default()
+                    .position(egui_plot::Corner::RightTop)
+                    .background_alpha(0.8),
+            )
+            .show(ui, |plot_ui| {
+                plot_ui.polygon(
+                    Polygon::new(band(&ensemble.band_90))
+                        .fill_color(red.gamma_multiply(0.15))
+                        .stroke(egui::Stroke::NONE)
+                        .name("90% band"),
+                );
+                plot_ui.polygon(
+                    Polygon::new(band(&ensemble.band_50))
+                        .fill_color(red.gamma_multiply(0.3))
+                        .stroke(egui::Stroke::NONE)
+                        .name("50% band"),
+                );
+                let median: PlotPoints = ensemble
+                    .times
+                    .iter()
+                    .zip(&ensemble.median)
+                    .map(|(&time, &value)| [time as f64, value as f64])
+                    .collect();
+                plot_ui.line(Line::new(median).color(red).name("Median infected"));
+            });
 
-                        // Recovered chart
-                        let last_recovered_percentage = match self.recovered_chart.last() {
-                            Some(&percentage) => percentage,
-                            None => 0.0,
-                        };
-                        let recovered_points: PlotPoints = (0..self.total_time.len())
-                            .map(|i| {
-                                let time = self.total_time[i];
-                                let recovered_percentage = self.recovered_chart[i];
-                                let susceptible_percentage = self.susceptible_chart[i];
-                                let infected_percentage = self.infected_chart[i];
-                                [
-                                    time as f64,
-                                    (infected_percentage
-                                        + susceptible_percentage
-                                        + recovered_percentage)
-                                        as f64,
-                                ]
-                            })
-                            .collect();
-
-                        plot_ui.line(
-                            Line::new(recovered_points)
-                                .color(egui::Color32::GRAY)
-                                .name(format!("{:.1}% Recovered", last_recovered_percentage))
-                                .fill(0.0),
-                        );
+        let peak_sizes: Vec<f32> = ensemble.runs.iter().map(|run| run.peak_size).collect();
+        let peak_times: Vec<f32> = ensemble.runs.iter().map(|run| run.peak_time).collect();
+        let attack_rates: Vec<f32> = ensemble.runs.iter().map(|run| run.attack_rate).collect();
+        ui.columns(3, |columns| {
+            show_histogram(&mut columns[0], "Peak size (%)", &peak_sizes);
+            show_histogram(&mut columns[1], "Peak time (s)", &peak_times);
+            show_histogram(&mut columns[2], "Attack rate (%)", &attack_rates);
+        });
+    }
+
+    /// Starts a sweep with the current UI settings in a background thread.
+    ///
+    /// Every cell runs the seeds following the seed of the current run for the ensemble
+    /// duration, and the loaded scenario, if any, is played back in every run.
+    pub fn start_sweep(&mut self) {
+        if self.sweep_task.is_some() {
+            return;
+        }
+        let parameter_count = if self.sweep_two_parameters { 2 } else { 1 };
+        let axes: Vec<SweepAxis> = (0..parameter_count)
+            .map(|i| {
+                let (start, end) = self.sweep_ranges[i];
+                SweepAxis::linear(self.sweep_parameters[i], start, end, self.sweep_steps)
+            })
+            .collect();
+        let settings = self.current_settings();
+        let scenario = self.scenario.clone();
+        let base_seed = self.seed.wrapping_add(1);
+        let seeds = self.sweep_seeds;
+        let duration = self.ensemble_duration;
+        self.sweep_status = String::from("Running sweep...");
+        self.sweep_task = Some(thread::spawn(move || {
+            sweep::run_sweep(
+                &settings,
+                scenario.as_ref(),
+                &axes,
+                base_seed,
+                seeds,
+                duration,
+            )
+        }));
+    }
+
+    /// Takes the results of the sweep running in the background once it is finished.
+    fn poll_sweep(&mut self) {
+        let finished = match &self.sweep_task {
+            Some(task) => task.is_finished(),
+            None => false,
+        };
+        if !finished {
+            return;
+        }
+        if let Some(task) = self.sweep_task.take() {
+            match task.join() {
+                Ok(Ok(sweep)) => {
+                    self.sweep_status = format!("Finished {} cells", sweep.cells.len());
+                    self.sweep = Some(sweep);
+                }
+                Ok(Err(error)) => self.sweep_status = error.to_string(),
+                Err(_) => self.sweep_status = String::from("the sweep stopped unexpectedly"),
+            }
+        }
+    }
+
+    /// Shows the sweep controls and the results of the last sweep.
+    ///
+    /// One or two parameters are chosen with their range, and the results are shown
+    /// as a table and as heatmaps of the final attack rate and peak prevalence.
+    ///
+    /// # Parameters
+    /// - `ui`: The UI to draw into
+    fn show_sweep(&mut self, ui: &mut egui::Ui) {
+        let settings = self.current_settings();
+        for i in 0..2 {
+            if i == 1 {
+                ui.checkbox(&mut self.sweep_two_parameters, "Sweep a second parameter");
+                if !self.sweep_two_parameters {
+                    break;
+                }
+            }
+            ui.horizontal(|ui| {
+                let previous = self.sweep_parameters[i];
+                egui::ComboBox::from_id_salt(("sweep parameter", i))
+                    .selected_text(previous.name())
+                    .show_ui(ui, |ui| {
+                        for parameter in SweepParameter::ALL {
+                            ui.selectable_value(
+                                &mut self.sweep_parameters[i],
+                                parameter,
+                                parameter.name(),
+                            );
+                        }
                     });
+                if self.sweep_parameters[i] != previous {
+                    let current = self.sweep_parameters[i].get(&settings);
+                    self.sweep_ranges[i] = (current * 0.5, current * 1.5);
+                }
+                ui.label(egui::RichText::new("from").size(15.0));
+                ui.add(egui::DragValue::new(&mut self.sweep_ranges[i].0).speed(0.1));
+                ui.label(egui::RichText::new("to").size(15.0));
+                ui.add(egui::DragValue::new(&mut self.sweep_ranges[i].1).speed(0.1));
+            });
+        }
+
+        ui.horizontal(|ui| {
+            ui.label(egui::RichText::new("Steps:").size(15.0));
+            ui.add(egui::DragValue::new(&mut self.sweep_steps).range(1..=20));
+            ui.label(egui::RichText::new("Seeds per cell:").size(15.0));
+            ui.add(egui::DragValue::new(&mut self.sweep_seeds).range(1..=100));
+            let running = self.sweep_task.is_some();
+            let sweep_button = ui.add_enabled(
+                !running,
+                egui::Button::new(egui::RichText::new("Run sweep").size(15.0)),
+            );
+            if sweep_button.clicked() {
+                self.start_sweep();
+            }
+            if running {
+                ui.spinner();
             }
+        });
+        if !self.sweep_status.is_empty() {
+            ui.label(egui::RichText::new(&self.sweep_status).size(15.0));
+        }
 
-            ui.separator();
-            ui.heading("Community Simulation");
-            let padding = 80.0;
-            let width =
-                SIMULATION_AREA_SIZE + GAP_COMMUNITY_QUARANTINE + QUARANTINE_AREA_SIZE + padding;
-            let height = SIMULATION_AREA_SIZE + padding;
+        let Some(sweep) = &self.sweep else {
+            return;
+        };
+        egui::Grid::new("sweep table").striped(true).show(ui, |ui| {
+            for axis in &sweep.axes {
+                ui.label(axis.parameter.name());
+            }
+            ui.label("Attack rate (%)");
+            ui.label("Peak prevalence (%)");
+            ui.end_row();
+            for cell in &sweep.cells {
+                ui.label(format!("{:.2}", cell.x));
+                if let Some(y) = cell.y {
+                    ui.label(format!("{:.2}", y));
+                }
+                ui.label(format!("{:.1}", cell.attack_rate));
+                ui.label(format!("{:.1}", cell.peak_prevalence));
+                ui.end_row();
+            }
+        });
+        ui.columns(2, |columns| {
+            show_heatmap(&mut columns[0], "Attack rate (%)", sweep, |cell| {
+                cell.attack_rate
+            });
+            show_heatmap(&mut columns[1], "Peak prevalence (%)", sweep, |cell| {
+                cell.peak_prevalence
+            });
+        });
+    }
 
-            let (response, painter) =
-                ui.allocate_painter(egui::vec2(width, height), egui::Sense::hover());
+    /// Shows the offspring (secondary case) distribution of a finished run.
+    ///
+    /// Displays the mean and variance of the number of secondary cases, the share of
+    /// transmission caused by the top 20% of cases, and a bar chart of the distribution.
+    ///
+    /// # Parameters
+    /// - `ui`: The UI to draw into
+    fn show_offspring_distribution(&self, ui: &mut egui::Ui) {
+        let distribution = OffspringDistribution::from_community(&self.community);
+        ui.label(
+            egui::RichText::new("Offspring Distribution")
+                .size(18.0)
+                .strong(),
+        );
+        ui.label(
+            egui::RichText::new(format!(
+                "{} cases, mean {:.2}, variance {:.2}, top 20% of cases caused {:.0}% of transmission",
+                distribution.total_cases,
+                distribution.mean,
+                distribution.variance,
+                distribution.top_20_percent_share * 100.0
+            ))
+            .size(15.0),
+        );
 
-            let rect = response.rect;
-            let border_offset_x = rect.left() + BORDER_PADDING;
-            let border_offset_y = rect.top() + BORDER_PADDING;
+        let bars: Vec<Bar> = distribution
+            .frequencies
+            .iter()
+            .enumerate()
+            .map(|(cases, &count)| Bar::new(cases as f64, count as f64).width(0.8))
+            .collect();
+        Plot::new("Offspring chart")
+            .height(150.0)
+            .x_axis_label("Secondary cases")
+            .y_axis_label("People")
+            .show(ui, |plot_ui| {
+                plot_ui.bar_chart(BarChart::new(bars).color(egui::Color32::RED));
+            });
+    }
+}
 
-            ui.visuals_mut().panel_fill = egui::Color32::BLACK;
+/// Returns the height of a chart which takes a share of the window height.
+///
+/// # Parameters
+/// - `ui`: The UI the chart is drawn into
+/// - `share`: Share of the visible height, for example `0.4` for 40%
+///
+/// # Returns
+/// The height in pixels, at least `MIN_CHART_HEIGHT`
+fn chart_height(ui: &egui::Ui, share: f32) -> f32 {
+    (ui.clip_rect().height() * share).max(MIN_CHART_HEIGHT)
+}
 
-            // Community
-            let main_rect = egui::Rect::from_min_size(
-                egui::pos2(border_offset_x, border_offset_y),
-                egui::vec2(SIMULATION_AREA_SIZE, SIMULATION_AREA_SIZE),
-            );
-            painter.rect_filled(main_rect, 0.0, egui::Color32::BLACK);
-            painter.rect_stroke(main_rect, 0.0, egui::Stroke::new(3.0, egui::Color32::WHITE));
+/// Shows a histogram of values with their median.
+///
+/// # Parameters
+/// - `ui`: The UI to draw into
+/// - `title`: Name of the values
+/// - `values`: The values to count in 10 bins
+fn show_histogram(ui: &mut egui::Ui, title: &str, values: &[f32]) {
+    let mut sorted = values.to_vec();
+    sorted.sort_by(f32::total_cmp);
+    ui.label(
+        egui::RichText::new(format!(
+            "{}: median {:.1}",
+            title,
+            ensemble::quantile(&sorted, 0.5)
+        ))
+        .size(15.0),
+    );
+
+    let bin_count = 10;
+    let min = sorted.first().copied().unwrap_or(0.0);
+    let max = sorted.last().copied().unwrap_or(0.0);
+    let width = ((max - min) / bin_count as f32).max(f32::EPSILON);
+    let mut counts = vec![0usize; bin_count];
+    for &value in &sorted {
+        let bin = (((value - min) / width) as usize).min(bin_count - 1);
+        counts[bin] += 1;
+    }
+    let bars = counts
+        .iter()
+        .enumerate()
+        .map(|(bin, &count)| {
+            Bar::new((min + (bin as f32 + 0.5) * width) as f64, count as f64)
+                .width(width as f64 * 0.9)
+        })
+        .collect();
+    Plot::new(title)
+        .height(150.0)
+        .y_axis_label("Runs")
+        .include_y(0.0)
+        .show(ui, |plot_ui| {
+            plot_ui.bar_chart(BarChart::new(bars).color(egui::Color32::LIGHT_BLUE));
+        });
+}
 
+/// Shows a heatmap of one result of a sweep.
+///
+/// The first parameter runs from left to right and the second from top to bottom.
+/// Cells go from blue (0%) to red (100%) and show their value.
+///
+/// # Parameters
+/// - `ui`: The UI to draw into
+/// - `title`: Name of the result
+/// - `sweep`: The sweep to show
+/// - `value`: Reads the result (a percentage) from a cell
+fn show_heatmap(ui: &mut egui::Ui, title: &str, sweep: &Sweep, value: impl Fn(&SweepCell) -> f32) {
+    ui.label(egui::RichText::new(title).size(15.0));
+    let columns = sweep.axes[0].values.len().max(1);
+    let rows = sweep.cells.len().div_ceil(columns);
+    let label_width = 50.0;
+    let cell_height = 28.0;
+    let width = ui.available_width();
+    let cell_width = (width - label_width) / columns as f32;
+    let (response, painter) = ui.allocate_painter(
+        egui::vec2(width, (rows + 1) as f32 * cell_height),
+        egui::Sense::hover(),
+    );
+    let rect = response.rect;
+    let font = egui::FontId::proportional(12.0);
+
+    for (index, cell) in sweep.cells.iter().enumerate() {
+        let (column, row) = (index % columns, index / columns);
+        let cell_rect = egui::Rect::from_min_size(
+            egui::pos2(
+                rect.left() + label_width + column as f32 * cell_width,
+                rect.top() + row as f32 * cell_height,
+            ),
+            egui::vec2(cell_width, cell_height),
+        );
+        let fraction = (value(cell) / 100.0).clamp(0.0, 1.0);
+        let color = egui::Color32::from_rgb(
+            (255.0 * fraction) as u8,
+            40,
+            (255.0 * (1.0 - fraction)) as u8,
+        );
+        painter.rect_filled(cell_rect.shrink(1.0), 0.0, color);
+        painter.text(
+            cell_rect.center(),
+            egui::Align2::CENTER_CENTER,
+            format!("{:.0}", value(cell)),
+            font.clone(),
+            egui::Color32::WHITE,
+        );
+        if column == 0
+            && let Some(y) = cell.y
+        {
             painter.text(
-                egui::pos2(border_offset_x, border_offset_y - 20.0),
+                egui::pos2(rect.left(), cell_rect.center().y),
                 egui::Align2::LEFT_CENTER,
-                "Community",
-                egui::FontId::proportional(15.0),
-                egui::Color32::WHITE,
-            );
-
-            // quarantine
-            let quarantine_offset_x =
-                border_offset_x + SIMULATION_AREA_SIZE + GAP_COMMUNITY_QUARANTINE;
-            let quarantine_rect = egui::Rect::from_min_size(
-                egui::pos2(quarantine_offset_x, border_offset_y),
-                egui::vec2(QUARANTINE_AREA_SIZE, QUARANTINE_AREA_SIZE),
-            );
-            painter.rect_filled(quarantine_rect, 0.0, egui::Color32::BLACK);
-            painter.rect_stroke(
-                quarantine_rect,
-                0.0,
-                egui::Stroke::new(3.0, egui::Color32::WHITE),
+                format!("{:.2}", y),
+                font.clone(),
+                egui::Color32::GRAY,
             );
-
+        }
+        if row == 0 {
             painter.text(
-                egui::pos2(quarantine_offset_x, border_offset_y - 20.0),
-                egui::Align2::LEFT_CENTER,
-                "Quarantine Zone",
-                egui::FontId::proportional(15.0),
-                egui::Color32::WHITE,
+                egui::pos2(
+                    cell_rect.center().x,
+                    rect.top() + (rows as f32 + 0.5) * cell_height,
+                ),
+                egui::Align2::CENTER_CENTER,
+                format!("{:.2}", cell.x),
+                font.clone(),
+                egui::Color32::GRAY,
             );
+        }
+    }
+}
 
-            // people
-            for person in &self.community {
-                let dot_postion = if person.is_in_quarantine {
-                    egui::pos2(quarantine_offset_x + person.x, border_offset_y + person.y)
-                } else {
-                    egui::pos2(border_offset_x + person.x, border_offset_y + person.y)
-                };
-                painter.circle_filled(dot_postion, PERSON_RADIUS, person.state.person_colors());
-            }
+impl eframe::App for Simulation {
+    /// Implements the eframe::App trait to handle UI rendering and updates
+    fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
+        let time_frame_per_second: f32 = ctx.input(|i| i.stable_dt);
+        self.advance(time_frame_per_second);
+        self.poll_ensemble();
+        self.poll_sweep();
+        egui::SidePanel::left("Controls")
+            .resizable(true)
+            .default_width(CONTROL_PANEL_WIDTH)
+            .show(ctx, |ui| {
+                egui::ScrollArea::vertical().show(ui, |ui| self.show_controls(ui));
+            });
+        if let Some(person) = self.selected_person {
+            egui::SidePanel::right("Person inspector")
+                .resizable(false)
+                .show(ctx, |ui| self.show_person_inspector(ui, person));
+        }
+        egui::CentralPanel::default().show(ctx, |ui| {
+            egui::ScrollArea::vertical().show(ui, |ui| {
+                self.show_community_view(ui);
+
+                ui.separator();
+                // Chart
+                self.show_ode_controls(ui);
+                self.show_chart_view_controls(ui);
+                if !self.total_time.is_empty() {
+                    let ode_solution = match &self.ode_cache {
+                        Some(cache) if self.ode_overlay_enabled => {
+                            Some(cache.until(self.elapsed_time))
+                        }
+                        _ => None,
+                    };
+                    self.show_sir_chart(ui, ode_solution);
+                }
+
+                // Rt chart
+                self.show_rt_chart(ui);
+
+                // Ensemble results
+                if self.ensemble.is_some() {
+                    ui.separator();
+                    self.show_ensemble(ui);
+                }
+
+                // Strain chart
+                if self.variants_enabled {
+                    self.show_strain_chart(ui);
+                }
+
+                // Offspring distribution
+                let epidemic_over = !self.community.iter().any(|person| person.is_infected());
+                if epidemic_over {
+                    ui.separator();
+                    self.show_offspring_distribution(ui);
+                }
+
+                ui.separator();
+                egui::CollapsingHeader::new(
+                    egui::RichText::new("Transmission Tree").size(18.0).strong(),
+                )
+                .show(ui, |ui| self.show_transmission_tree(ui));
+            });
         });
 
         ctx.request_repaint();
@@ -579,6 +2994,7 @@ impl eframe::App for Simulation {
 mod tests {
     use super::*;
     use crate::person::PersonState;
+    use crate::settings::*;
 
     /// Tests that a new app is created with default INITIAL_INFECTED_PEOPLE
     #[test]
@@ -589,7 +3005,7 @@ mod tests {
         let infected = app
             .community
             .iter()
-            .filter(|p| matches!(p.state, PersonState::Infected))
+            .filter(|p| matches!(p.state, PersonState::Infected(_)))
             .count();
         assert_eq!(infected, INITIAL_INFECTED_PEOPLE);
     }
@@ -628,7 +3044,7 @@ mod tests {
         let count = app
             .community
             .iter()
-            .filter(|person| matches!(person.state, PersonState::Infected))
+            .filter(|person| matches!(person.state, PersonState::Infected(_)))
             .count();
         assert_eq!(count, 5);
         assert_eq!(app.community.len(), 80);
@@ -684,12 +3100,368 @@ mod tests {
         assert!(vulnerable.contains(&1));
     }
 
+    /// Tests that a person infected by nearby infected people is attributed to one of them.
+    #[test]
+    fn test_spread_infection_records_secondary_cases() {
+        let mut app = Simulation::new();
+        app.community.truncate(2);
+        app.community[0].x = 20.0;
+        app.community[0].y = 20.0;
+        app.community[0].infectiousness = 100.0;
+        app.community[1].state = PersonState::Susceptible;
+        app.community[1].x = 21.0;
+        app.community[1].y = 22.0;
+        app.community[0].infection_duration = 1.5;
+
+        app.spread_infection();
+        assert!(app.community[1].is_infected());
+        assert_eq!(app.community[0].secondary_cases, 1);
+        assert_eq!(app.community[1].secondary_cases, 0);
+
+        let event = app.infection_log.events.last().unwrap();
+        assert_eq!(event.person, 1);
+        assert_eq!(event.infector, Some(0));
+        assert_eq!(event.location, InfectionLocation::Community);
+        assert_eq!(event.infector_infection_age, Some(1.5));
+        assert_eq!(app.heatmap.counts.iter().sum::<u32>(), 1);
+    }
+
+    /// Tests that several nearby infected people do not add up without superspreading.
+    #[test]
+    fn test_spread_infection_without_superspreading() {
+        let mut app = Simulation::new();
+        app.settings.infection_probability = 0.2;
+        app.strain_set.strains[0].infection_probability = 0.2;
+        let trials = 4000;
+        let mut infected = 0;
+        for _ in 0..trials {
+            app.community.truncate(6);
+            for (i, person) in app.community.iter_mut().enumerate() {
+                person.x = 20.0 + i as f32;
+                person.y = 20.0;
+                person.is_in_quarantine = false;
+                person.state = PersonState::Infected(0);
+                person.infectiousness = 1.0;
+            }
+            app.community[0].state = PersonState::Susceptible;
+            app.community[0].recovered_from.clear();
+            app.spread_infection();
+            if app.community[0].is_infected() {
+                infected += 1;
+            }
+        }
+        let rate = infected as f32 / trials as f32;
+        assert!((rate - 0.2).abs() < 0.03, "infection rate {rate}");
+    }
+
+    /// Tests that the initial infections are logged without an infector on restart.
+    #[test]
+    fn test_restart_logs_initial_infections() {
+        let app = Simulation::new();
+        assert_eq!(app.infection_log.events.len(), INITIAL_INFECTED_PEOPLE);
+        assert!(app.infection_log.events.iter().all(|event| {
+            event.infector.is_none() && event.time == 0.0 && event.infector_infection_age.is_none()
+        }));
+    }
+
+    /// Tests that the ODE starts from the run, not from UI values waiting for a restart.
+    #[test]
+    fn test_ode_inputs() {
+        let mut app = Simulation::new();
+        app.community_size = 150;
+        app.initial_infected_count = 10;
+        let (parameters, initial) = app.ode_inputs();
+        assert_eq!(
+            parameters,
+            SirParameters::from_agents(&app.settings, 80, app.infected_radius)
+        );
+        assert_eq!(initial.infected, 3.0 / 80.0);
+
+        // More initial infections than people left are clamped
+        app.community.truncate(2);
+        let (_, initial) = app.ode_inputs();
+        assert_eq!((initial.susceptible, initial.infected), (0.0, 1.0));
+    }
+
+    /// Tests that the ODE solution is extended while its parameters stay the same.
+    #[test]
+    fn test_update_ode_cache() {
+        let mut app = Simulation::new();
+        app.elapsed_time = 1.0;
+        app.update_ode_cache();
+        assert_eq!(app.ode_cache.as_ref().unwrap().points.len(), 21);
+        app.elapsed_time = 2.0;
+        app.update_ode_cache();
+        let cache = app.ode_cache.as_ref().unwrap();
+        assert_eq!(cache.points.len(), 41);
+        assert_eq!(cache.points, app.ode_solution());
+
+        app.infected_radius *= 2.0;
+        app.elapsed_time = 0.5;
+        app.update_ode_cache();
+        let cache = app.ode_cache.as_ref().unwrap();
+        assert_eq!(cache.points.len(), 11);
+        assert_eq!(cache.points, app.ode_solution());
+    }
+
+    /// Tests that infectiousness is constant without superspreading and averages 1.0 with it.
+    #[test]
+    fn test_draw_infectiousness() {
+        let mut app = Simulation::new();
+        assert_eq!(app.draw_infectiousness(), 1.0);
+
+        app.superspreading_enabled = true;
+        app.dispersion_k = 0.5;
+        let samples = 20000;
+        let mean = (0..samples).map(|_| app.draw_infectiousness()).sum::<f32>() / samples as f32;
+        assert!((mean - 1.0).abs() < 0.1);
+    }
+
+    /// Tests that a recovered person is reinfected only by a strain without full cross-immunity.
+    #[test]
+    fn test_spread_infection_with_variant() {
+        let mut app = Simulation::new();
+        app.variants_enabled = true;
+        app.strain_set.strains[1].infection_probability = 1.0;
+        app.strain_set.cross_immunity[0][1] = 0.0;
+        app.community.truncate(3);
+        app.community[0].infect(1, 100.0);
+        app.community[0].x = 20.0;
+        app.community[0].y = 20.0;
+        app.community[1].recover();
+        app.community[1].recovered_from = vec![0];
+        app.community[1].x = 21.0;
+        app.community[1].y = 22.0;
+        app.community[2].recover();
+        app.community[2].recovered_from = vec![1];
+        app.community[2].x = 20.0;
+        app.community[2].y = 21.0;
+
+        app.spread_infection();
+        assert_eq!(app.community[1].strain(), Some(1));
+        assert!(matches!(app.community[2].state, PersonState::Recovered));
+    }
+
+    /// Tests that a variant is seeded once when its introduction time is reached.
+    #[test]
+    fn test_seed_variants() {
+        let mut app = Simulation::new();
+        app.strain_set.strains[1].seed_time = 1.0;
+        app.strain_set.strains[1].seed_count = 4;
+        app.seed_variants(0.0, 0.5);
+        assert!(
+            app.community
+                .iter()
+                .all(|person| person.strain() != Some(1))
+        );
+        app.seed_variants(0.5, 1.5);
+        let seeded = app
+            .community
+            .iter()
+            .filter(|person| person.strain() == Some(1))
+            .count();
+        assert_eq!(seeded, 4);
+    }
+
+    /// Tests that the current UI settings can be applied to a new simulation.
+    #[test]
+    fn test_current_settings_round_trip() {
+        let mut app = Simulation::new();
+        app.community_size = 50;
+        app.ui_infected_radius = 6.0;
+        app.quarantine_enabled = true;
+        let settings = app.current_settings();
+        assert_eq!(settings.community_size, 50);
+        assert_eq!(settings.infected_radius, 6.0);
+
+        let other = Simulation::with_settings(settings.clone());
+        assert_eq!(other.community.len(), 50);
+        assert_eq!(other.infected_radius, 6.0);
+        assert!(other.quarantine_enabled);
+        assert_eq!(other.current_settings(), settings);
+    }
+
+    /// Tests that the speed multiplier runs more ticks per frame and pausing stops them.
+    #[test]
+    fn test_advance_speed_and_pause() {
+        let mut simulation = Simulation::new();
+        let ticks = |simulation: &Simulation| (simulation.elapsed_time / TIME_STEP).round();
+        simulation.speed = 2.0;
+        simulation.advance(TIME_STEP);
+        assert_eq!(ticks(&simulation), 2.0);
+
+        simulation.speed = 0.5;
+        simulation.advance(TIME_STEP);
+        assert_eq!(ticks(&simulation), 2.0);
+        simulation.advance(TIME_STEP);
+        assert_eq!(ticks(&simulation), 3.0);
+
+        simulation.paused = true;
+        simulation.advance(1.0);
+        assert_eq!(ticks(&simulation), 3.0);
+        simulation.step_tick();
+        assert_eq!(ticks(&simulation), 4.0);
+    }
+
+    /// Tests that the replay keeps moving after fast-forward, scrubbing back and normal speed.
+    #[test]
+    fn test_replay_after_fast_forward() {
+        let mut simulation = Simulation::new();
+        simulation.recording_enabled = true;
+        for _ in 0..100 {
+            simulation.step_tick();
+        }
+
+        simulation.show_replay_frame(Some(10));
+        simulation.fast_forward = true;
+        for _ in 0..5 {
+            simulation.advance(TIME_STEP);
+        }
+        assert_eq!(simulation.replay_frame, Some(10 + 5 * MAX_TICKS_PER_FRAME));
+
+        simulation.show_replay_frame(Some(10));
+        simulation.fast_forward = false;
+        simulation.advance(TIME_STEP);
+        assert_eq!(simulation.replay_frame, Some(11));
+        simulation.advance(TIME_STEP);
+        assert_eq!(simulation.replay_frame, Some(12));
+    }
+
+    /// Tests that the chart history is sampled at a fixed interval and halved when full.
+    #[test]
+    fn test_chart_history_bounded() {
+        let mut simulation = Simulation::new();
+        for _ in 0..6 {
+            simulation.step(TIME_STEP);
+        }
+        assert_eq!(simulation.total_time.len(), 2);
+
+        for _ in 0..MAX_CHART_SAMPLES {
+            simulation.step(CHART_INTERVAL);
+        }
+        assert!(simulation.total_time.len() <= MAX_CHART_SAMPLES);
+        assert_eq!(simulation.chart_interval, 2.0 * CHART_INTERVAL);
+        assert_eq!(simulation.total_time[0], 0.0);
+        let last = simulation.total_time.last().unwrap();
+        assert!(simulation.elapsed_time - last < simulation.chart_interval);
+        assert_eq!(simulation.state_counts.len(), simulation.total_time.len());
+    }
+
+    /// Tests that two runs with the same seed give the same result.
+    #[test]
+    fn test_same_seed_same_run() {
+        let settings = Settings {
+            seed: Some(42),
+            superspreading_enabled: true,
+            ..Settings::default()
+        };
+        let mut first = Simulation::with_settings(settings.clone());
+        let mut second = Simulation::with_settings(settings);
+        for _ in 0..300 {
+            first.advance(TIME_STEP);
+            second.advance(TIME_STEP);
+        }
+        assert_eq!(first.seed, 42);
+        assert_eq!(first.infected_chart, second.infected_chart);
+        for (a, b) in first.community.iter().zip(&second.community) {
+            assert_eq!((a.x, a.y, &a.state), (b.x, b.y, &b.state));
+        }
+    }
+
+    /// Tests that scenario events are applied once their time is reached.
+    #[test]
+    fn test_scenario_events_applied() {
+        let mut app = Simulation::new();
+        let scenario = Scenario {
+            events: vec![
+                ScenarioEvent::SetQuarantine {
+                    time: 0.5,
+                    enabled: true,
+                },
+                ScenarioEvent::ImportCases {
+                    time: 1.0,
+                    count: 2,
+                    strain: 0,
+                },
+            ],
+            ..Scenario::default()
+        };
+        app.set_scenario(scenario).unwrap();
+        let size = app.community.len();
+
+        app.step(0.25);
+        app.step(0.25);
+        assert!(!app.quarantine_enabled);
+        app.step(0.25);
+        assert!(app.quarantine_enabled);
+        assert_eq!(app.next_event, 1);
+        app.step(0.5);
+        assert_eq!(app.community.len(), size + 2);
+        assert!(app.community[size].is_infected());
+
+        app.restart();
+        assert!(!app.quarantine_enabled);
+        assert_eq!(app.next_event, 0);
+    }
+
+    /// Tests that a scenario without a seed plays back the same run every time.
+    #[test]
+    fn test_set_scenario_without_seed_is_deterministic() {
+        let mut app = Simulation::new();
+        let scenario = Scenario {
+            events: vec![ScenarioEvent::ImportCases {
+                time: 0.5,
+                count: 3,
+                strain: 0,
+            }],
+            ..Scenario::default()
+        };
+        app.set_scenario(scenario).unwrap();
+        assert_eq!(
+            app.scenario
+                .as_ref()
+                .unwrap()
+                .settings
+                .as_ref()
+                .unwrap()
+                .seed,
+            Some(app.seed)
+        );
+
+        let mut runs = Vec::new();
+        for _ in 0..2 {
+            app.restart();
+            for _ in 0..60 {
+                app.step(0.1);
+            }
+            let positions: Vec<(f32, f32)> = app.community.iter().map(|p| (p.x, p.y)).collect();
+            runs.push((app.infected_chart.clone(), positions));
+        }
+        assert_eq!(runs[0], runs[1]);
+    }
+
+    /// Tests that a scenario with a strain which does not exist is rejected.
+    #[test]
+    fn test_set_scenario_unknown_strain() {
+        let mut app = Simulation::new();
+        let scenario = Scenario {
+            events: vec![ScenarioEvent::SeedInfections {
+                time: 1.0,
+                count: 1,
+                strain: 3,
+            }],
+            ..Scenario::default()
+        };
+        assert!(app.set_scenario(scenario).is_err());
+        assert!(app.scenario.is_none());
+    }
+
     /// Tests that a infected person will be recorved after 7 days
     #[test]
     fn test_community_update_recovered_person() {
         let mut app = Simulation::new();
         app.update_community(6.0);
-        assert!(matches!(app.community[0].state, PersonState::Infected));
+        assert!(matches!(app.community[0].state, PersonState::Infected(_)));
         app.update_community(2.0);
         assert!(matches!(app.community[0].state, PersonState::Recovered));
     }
@@ -708,6 +3480,9 @@ mod tests {
             state: PersonState::Susceptible,
             infection_duration: 0.0,
             is_in_quarantine: false,
+            infectiousness: 1.0,
+            secondary_cases: 0,
+            recovered_from: Vec::new(),
         });
         app.community.push(Person {
             x: 70.0,
@@ -718,6 +3493,9 @@ mod tests {
             infection_duration: 0.0,
 
             is_in_quarantine: false,
+            infectiousness: 1.0,
+            secondary_cases: 0,
+            recovered_from: Vec::new(),
         });
         let (fx, fy) = app.calculate_social_distancing_force(0);
         assert!(fx < 0.0);
@@ -737,6 +3515,9 @@ mod tests {
             state: PersonState::Susceptible,
             infection_duration: 0.0,
             is_in_quarantine: false,
+            infectiousness: 1.0,
+            secondary_cases: 0,
+            recovered_from: Vec::new(),
         });
         let forces = vec![(1.0, -1.0)];
         let time_delta = 1.0 / 60.0;
@@ -744,4 +3525,86 @@ mod tests {
         assert_eq!(app.community[0].velocity_x, 1.0 * 150.0 * time_delta);
         assert_eq!(app.community[0].velocity_y, -1.0 * 150.0 * time_delta);
     }
+
+    /// Tests that the paint tools change the people under the brush and the chart.
+    #[test]
+    fn test_paint_tools() {
+        let mut simulation = Simulation::with_settings(Settings {
+            seed: Some(9),
+            ..Settings::default()
+        });
+        simulation.recording_enabled = true;
+        for _ in 0..10 {
+            simulation.step_tick();
+        }
+        for person in &mut simulation.community {
+            person.is_in_quarantine = false;
+            person.state = PersonState::Susceptible;
+            person.recovered_from.clear();
+        }
+        simulation.community[5].x = 100.0;
+        simulation.community[5].y = 100.0;
+        simulation.community[6].x = 110.0;
+        simulation.community[6].y = 100.0;
+        let log_length = simulation.infection_log.events.len();
+
+        simulation.paint(PaintTool::Immunise, 120.0, 100.0);
+        assert_eq!(simulation.community[6].state, PersonState::Recovered);
+        simulation.paint(PaintTool::Infect, 100.0, 100.0);
+        assert!(simulation.community[5].is_infected());
+        // The immune person next to them is not infected
+        assert_eq!(simulation.community[6].state, PersonState::Recovered);
+        assert!(
+            simulation.infection_log.events[log_length..]
+                .iter()
+                .any(|event| event.person == 5 && event.infector.is_none())
+        );
+        let (_, infected, _) = *simulation.state_counts.last().unwrap();
+        assert_eq!(infected, simulation.count_states().1);
+        assert_eq!(
+            simulation.recording.frames.last().unwrap().people[5].state,
+            simulation.community[5].state
+        );
+
+        let size = simulation.community.len();
+        simulation.paint(PaintTool::Add, 100.0, 100.0);
+        assert_eq!(simulation.community.len(), size);
+        simulation.paint(PaintTool::Add, 300.0, 20.0);
+        assert_eq!(simulation.community.len(), size + 1);
+        assert_eq!(simulation.community_size, size + 1);
+
+        simulation.selected_person = Some(size);
+        simulation.paint(PaintTool::Remove, 100.0, 100.0);
+        assert!(simulation.community.len() < size);
+        assert_eq!(simulation.community_size, simulation.community.len());
+        assert_eq!(
+            simulation.selected_person,
+            Some(simulation.community.len() - 1)
+        );
+        assert!(
+            simulation
+                .infection_log
+                .events
+                .iter()
+                .all(|event| event.person < simulation.community.len())
+        );
+
+        // The frames survive the removal, but the run only resumes from after it
+        assert_eq!(simulation.recording.frames.len(), 11);
+        assert_eq!(simulation.recording.frames[5].people.len(), size);
+        assert!(!simulation.recording.can_rewind(5));
+        assert!(simulation.recording.can_rewind(10));
+    }
+
+    /// Tests that more initial infections than people infect everyone instead of panicking.
+    #[test]
+    fn test_restart_more_initial_infected_than_people() {
+        let mut simulation = Simulation::new();
+        simulation.community_size = 20;
+        simulation.initial_infected_count = 30;
+        simulation.restart();
+        assert_eq!(simulation.community.len(), 20);
+        assert_eq!(simulation.count_states(), (0, 20, 0));
+        assert_eq!(simulation.susceptible_chart[0], 0.0);
+    }
 }
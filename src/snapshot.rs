@@ -0,0 +1,377 @@
+//! Snapshot module for saving and resuming a simulation.
+//!
+//! A snapshot holds the complete state of a run: the people with their positions,
+//! velocities, infection clocks and quarantine flags, the chart history, the
+//! infection log, the scenario playback position, the settings and the state of the
+//! random number generator. A resumed run continues exactly like the original one.
+//! Snapshots are stored as JSON (`.json`) files.
+
+use std::fs;
+use std::path::Path;
+
+use rand_chacha::ChaCha8Rng;
+use serde::{Deserialize, Serialize};
+
+use crate::config::{self, ConfigError};
+use crate::infection_log::InfectionLog;
+use crate::person::Person;
+use crate::scenario::Scenario;
+use crate::settings::Settings;
+use crate::simulation::Simulation;
+use crate::strain::StrainSet;
+
+/// Default snapshot file shown in the UI.
+pub const DEFAULT_SNAPSHOT_PATH: &str = "snapshot.json";
+
+/// Version of the snapshot format, increased when the format changes.
+pub const SNAPSHOT_VERSION: u32 = 2;
+
+/// The complete state of a simulation.
+///
+/// The fields are the simulation fields with the same name; UI-only state such as
+/// file paths and ensemble results is not part of a snapshot.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SimulationSnapshot {
+    pub version: u32,
+    pub settings: Settings,
+    pub seed: u64,
+    pub rng: ChaCha8Rng,
+    pub community: Vec<Person>,
+    pub community_size: usize,
+    pub initial_infected_count: usize,
+    pub infected_radius: f32,
+    pub ui_infected_radius: f32,
+    pub social_distancing_radius: f32,
+    pub social_distancing_enabled: bool,
+    pub quarantine_enabled: bool,
+    pub infection_time_before_quarantine: f32,
+    pub superspreading_enabled: bool,
+    pub dispersion_k: f32,
+    pub ui_dispersion_k: f32,
+    pub strain_set: StrainSet,
+    pub variants_enabled: bool,
+    pub elapsed_time: f32,
+    pub time_accumulator: f32,
+    pub total_time: Vec<f32>,
+    pub chart_interval: f32,
+    pub infected_chart: Vec<f32>,
+    pub susceptible_chart: Vec<f32>,
+    pub recovered_chart: Vec<f32>,
+    pub state_counts: Vec<(usize, usize, usize)>,
+    pub strain_charts: Vec<Vec<f32>>,
+    pub infection_log: InfectionLog,
+    pub scenario: Option<Scenario>,
+    pub next_event: usize,
+}
+
+impl SimulationSnapshot {
+    /// Takes a snapshot of a simulation.
+    ///
+    /// # Parameters
+    /// - `simulation`: The simulation to save
+    ///
+    /// # Returns
+    /// The snapshot of the current state
+    pub fn from_simulation(simulation: &Simulation) -> Self {
+        SimulationSnapshot {
+            version: SNAPSHOT_VERSION,
+            settings: simulation.settings.clone(),
+            seed: simulation.seed,
+            rng: simulation.rng.clone(),
+            community: simulation.community.clone(),
+            community_size: simulation.community_size,
+            initial_infected_count: simulation.initial_infected_count,
+            infected_radius: simulation.infected_radius,
+            ui_infected_radius: simulation.ui_infected_radius,
+            social_distancing_radius: simulation.social_distancing_radius,
+            social_distancing_enabled: simulation.social_distancing_enabled,
+            quarantine_enabled: simulation.quarantine_enabled,
+            infection_time_before_quarantine: simulation.infection_time_before_quarantine,
+            superspreading_enabled: simulation.superspreading_enabled,
+            dispersion_k: simulation.dispersion_k,
+            ui_dispersion_k: simulation.ui_dispersion_k,
+            strain_set: simulation.strain_set.clone(),
+            variants_enabled: simulation.variants_enabled,
+            elapsed_time: simulation.elapsed_time,
+            time_accumulator: simulation.time_accumulator,
+            total_time: simulation.total_time.clone(),
+            chart_interval: simulation.chart_interval,
+            infected_chart: simulation.infected_chart.clone(),
+            susceptible_chart: simulation.susceptible_chart.clone(),
+            recovered_chart: simulation.recovered_chart.clone(),
+            state_counts: simulation.state_counts.clone(),
+            strain_charts: simulation.strain_charts.clone(),
+            infection_log: simulation.infection_log.clone(),
+            scenario: simulation.scenario.clone(),
+            next_event: simulation.next_event,
+        }
+    }
+
+    /// Replaces the state of a simulation with this snapshot.
+    ///
+    /// UI-only state of the simulation is kept, except the selected person and the
+    /// recording of the replaced run.
+    ///
+    /// # Parameters
+    /// - `simulation`: The simulation to resume
+    pub fn restore(self, simulation: &mut Simulation) {
+        simulation.settings = self.settings;
+        simulation.seed = self.seed;
+        simulation.rng = self.rng;
+        simulation.community = self.community;
+        simulation.community_size = self.community_size;
+        simulation.initial_infected_count = self.initial_infected_count;
+        simulation.infected_radius = self.infected_radius;
+        simulation.ui_infected_radius = self.ui_infected_radius;
+        simulation.social_distancing_radius = self.social_distancing_radius;
+        simulation.social_distancing_enabled = self.social_distancing_enabled;
+        simulation.quarantine_enabled = self.quarantine_enabled;
+        simulation.infection_time_before_quarantine = self.infection_time_before_quarantine;
+        simulation.superspreading_enabled = self.superspreading_enabled;
+        simulation.dispersion_k = self.dispersion_k;
+        simulation.ui_dispersion_k = self.ui_dispersion_k;
+        simulation.strain_set = self.strain_set;
+        simulation.variants_enabled = self.variants_enabled;
+        simulation.elapsed_time = self.elapsed_time;
+        simulation.time_accumulator = self.time_accumulator;
+        simulation.total_time = self.total_time;
+        simulation.chart_interval = self.chart_interval;
+        simulation.infected_chart = self.infected_chart;
+        simulation.susceptible_chart = self.susceptible_chart;
+        simulation.recovered_chart = self.recovered_chart;
+        simulation.state_counts = self.state_counts;
+        simulation.strain_charts = self.strain_charts;
+        simulation.infection_log = self.infection_log;
+        simulation.scenario = self.scenario;
+        simulation.next_event = self.next_event;
+        simulation.selected_person = None;
+        simulation.recording.clear();
+        simulation.replay_frame = None;
+    }
+
+    /// Checks that the snapshot is consistent, so it cannot break the simulation.
+    ///
+    /// # Returns
+    /// `Ok(())` or `ConfigError::Invalid` with one message per problem
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        let mut problems = match config::validate_settings(&self.settings) {
+            Err(ConfigError::Invalid(problems)) => problems,
+            Err(error) => vec![error.to_string()],
+            Ok(()) => Vec::new(),
+        };
+        if self.version != SNAPSHOT_VERSION {
+            problems.push(format!(
+                "snapshot version {} is not supported (expected {})",
+                self.version, SNAPSHOT_VERSION
+            ));
+        }
+        if self.community.is_empty() {
+            problems.push(String::from("the community is empty"));
+        }
+        let samples = self.total_time.len();
+        let chart_lengths = [
+            self.infected_chart.len(),
+            self.susceptible_chart.len(),
+            self.recovered_chart.len(),
+            self.state_counts.len(),
+        ];
+        if samples == 0
+            || chart_lengths.iter().any(|&length| length != samples)
+            || self
+                .strain_charts
+                .iter()
+                .any(|chart| chart.len() != samples)
+        {
+            problems.push(String::from("the chart history has different lengths"));
+        }
+        if self.chart_interval.is_nan() || self.chart_interval <= 0.0 {
+            problems.push(String::from("the chart interval must be positive"));
+        }
+        let strain_count = self.strain_set.strains.len();
+        if self.strain_charts.len() != strain_count {
+            problems.push(String::from("the strain charts do not match the strains"));
+        }
+        let unknown_strain = self.community.iter().any(|person| {
+            person.strain().is_some_and(|strain| strain >= strain_count)
+                || person
+                    .recovered_from
+                    .iter()
+                    .any(|&strain| strain >= strain_count)
+        });
+        if unknown_strain {
+            problems.push(String::from("a person has a strain which does not exist"));
+        }
+        let cross_immunity = &self.strain_set.cross_immunity;
+        if cross_immunity.len() != strain_count
+            || cross_immunity.iter().any(|row| row.len() != strain_count)
+        {
+            problems.push(format!(
+                "the cross-immunity matrix must be {}×{} for {} strains",
+                strain_count, strain_count, strain_count
+            ));
+        }
+        let people = self.community.len();
+        let unknown_person = self.infection_log.events.iter().any(|event| {
+            event.person >= people || event.infector.is_some_and(|infector| infector >= people)
+        });
+        if unknown_person {
+            problems.push(String::from(
+                "the infection log has a person who does not exist",
+            ));
+        }
+        let event_count = self
+            .scenario
+            .as_ref()
+            .map_or(0, |scenario| scenario.events.len());
+        if self.next_event > event_count {
+            problems.push(format!(
+                "the next scenario event ({}) is after the last event ({})",
+                self.next_event, event_count
+            ));
+        }
+
+        if problems.is_empty() {
+            Ok(())
+        } else {
+            Err(ConfigError::Invalid(problems))
+        }
+    }
+}
+
+/// Saves a snapshot of a simulation to a JSON file.
+///
+/// # Parameters
+/// - `path`: Path of a `.json` file
+/// - `simulation`: The simulation to save
+pub fn save_snapshot(path: impl AsRef<Path>, simulation: &Simulation) -> Result<(), ConfigError> {
+    let path = path.as_ref();
+    let snapshot = SimulationSnapshot::from_simulation(simulation);
+    let text = serde_json::to_string(&snapshot)
+        .map_err(|error| ConfigError::Serialize(error.to_string()))?;
+    fs::write(path, text).map_err(|error| ConfigError::Io(path.display().to_string(), error))
+}
+
+/// Loads and validates a snapshot from a JSON file.
+///
+/// # Parameters
+/// - `path`: Path of a `.json` file
+///
+/// # Returns
+/// The snapshot, or the error explaining what is wrong
+pub fn load_snapshot(path: impl AsRef<Path>) -> Result<SimulationSnapshot, ConfigError> {
+    let path = path.as_ref();
+    let text = fs::read_to_string(path)
+        .map_err(|error| ConfigError::Io(path.display().to_string(), error))?;
+    let snapshot: SimulationSnapshot =
+        serde_json::from_str(&text).map_err(|error| ConfigError::Parse(error.to_string()))?;
+    snapshot.validate()?;
+    Ok(snapshot)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scenario::ScenarioEvent;
+
+    /// Tests that a resumed snapshot continues exactly like the original run.
+    #[test]
+    fn test_resume_same_run() {
+        let mut original = Simulation::with_settings(Settings {
+            seed: Some(9),
+            superspreading_enabled: true,
+            ..Settings::default()
+        });
+        for _ in 0..120 {
+            original.step(original.settings.time_step);
+        }
+
+        let text = serde_json::to_string(&SimulationSnapshot::from_simulation(&original)).unwrap();
+        let snapshot: SimulationSnapshot = serde_json::from_str(&text).unwrap();
+        snapshot.validate().unwrap();
+        let mut resumed = Simulation::new();
+        snapshot.restore(&mut resumed);
+
+        for _ in 0..120 {
+            original.step(original.settings.time_step);
+            resumed.step(resumed.settings.time_step);
+        }
+        assert_eq!(
+            SimulationSnapshot::from_simulation(&resumed),
+            SimulationSnapshot::from_simulation(&original)
+        );
+    }
+
+    /// Tests that inconsistent snapshots are rejected.
+    #[test]
+    fn test_validate_inconsistent() {
+        let simulation = Simulation::new();
+        let mut snapshot = SimulationSnapshot::from_simulation(&simulation);
+        snapshot.infected_chart.push(1.0);
+        snapshot.community[0].recovered_from.push(7);
+        match snapshot.validate() {
+            Err(ConfigError::Invalid(problems)) => assert_eq!(problems.len(), 2),
+            _ => panic!("snapshot should be invalid"),
+        }
+    }
+
+    /// Tests that a cross-immunity matrix which does not match the strains is rejected.
+    #[test]
+    fn test_validate_cross_immunity() {
+        let simulation = Simulation::new();
+        let mut snapshot = SimulationSnapshot::from_simulation(&simulation);
+        snapshot.strain_set.cross_immunity = vec![vec![1.0, 0.5]];
+        match snapshot.validate() {
+            Err(ConfigError::Invalid(problems)) => {
+                assert_eq!(problems.len(), 1);
+                assert!(problems[0].contains("cross-immunity"));
+            }
+            _ => panic!("snapshot should be invalid"),
+        }
+    }
+
+    /// Tests that an infection log with people outside the community is rejected.
+    #[test]
+    fn test_validate_infection_log() {
+        let simulation = Simulation::new();
+        let people = simulation.community.len();
+        let mut snapshot = SimulationSnapshot::from_simulation(&simulation);
+        snapshot.infection_log.events[0].infector = Some(people);
+        assert!(matches!(snapshot.validate(), Err(ConfigError::Invalid(_))));
+
+        let mut snapshot = SimulationSnapshot::from_simulation(&simulation);
+        snapshot.infection_log.events[0].person = people;
+        match snapshot.validate() {
+            Err(ConfigError::Invalid(problems)) => {
+                assert_eq!(problems.len(), 1);
+                assert!(problems[0].contains("infection log"));
+            }
+            _ => panic!("snapshot should be invalid"),
+        }
+    }
+
+    /// Tests that a scenario position after the last event is rejected.
+    #[test]
+    fn test_validate_next_event() {
+        let simulation = Simulation::new();
+        let mut snapshot = SimulationSnapshot::from_simulation(&simulation);
+        snapshot.next_event = 1;
+        match snapshot.validate() {
+            Err(ConfigError::Invalid(problems)) => {
+                assert_eq!(problems.len(), 1);
+                assert!(problems[0].contains("scenario event"));
+            }
+            _ => panic!("snapshot should be invalid"),
+        }
+
+        snapshot.scenario = Some(Scenario {
+            settings: None,
+            duration: None,
+            events: vec![ScenarioEvent::SetQuarantine {
+                time: 1.0,
+                enabled: true,
+            }],
+        });
+        snapshot.validate().unwrap();
+    }
+}
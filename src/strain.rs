@@ -0,0 +1,206 @@
+//! Strain module for co-circulating pathogen strains.
+//!
+//! Each strain has its own transmissibility, infection duration and seeding time.
+//! A cross-immunity matrix describes how much protection recovering from one strain
+//! gives against another, which is what drives variant replacement.
+
+use eframe::egui;
+use serde::{Deserialize, Serialize};
+
+use crate::settings::Settings;
+
+/// Colors used for infected people of each strain, repeated when there are more strains.
+pub const STRAIN_COLORS: [egui::Color32; 6] = [
+    egui::Color32::RED,
+    egui::Color32::from_rgb(255, 140, 0),
+    egui::Color32::from_rgb(255, 0, 255),
+    egui::Color32::YELLOW,
+    egui::Color32::GREEN,
+    egui::Color32::from_rgb(0, 255, 255),
+];
+
+/// Returns the color that represents infected people of a strain in the UI.
+///
+/// The first strain is red like the single strain model.
+///
+/// # Parameters
+/// - `strain`: Index of the strain
+///
+/// # Returns
+/// An `egui::Color32` value for the strain
+pub fn strain_color(strain: usize) -> egui::Color32 {
+    STRAIN_COLORS[strain % STRAIN_COLORS.len()]
+}
+
+/// It represents one pathogen strain.
+///
+/// # Fields
+/// - `name`: Name shown in the UI and the chart legend
+/// - `infection_probability`: Probability of infection from one contact with an average infected person
+/// - `recovered_day`: Time (in seconds) before an infected person recovers
+/// - `seed_time`: Simulation time (in seconds) when the strain is introduced
+/// - `seed_count`: Number of people infected with the strain when it is introduced
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Strain {
+    pub name: String,
+    pub infection_probability: f32,
+    pub recovered_day: f32,
+    pub seed_time: f32,
+    pub seed_count: usize,
+}
+
+/// All strains of a simulation with their cross-immunity.
+///
+/// The first strain is the original strain which is seeded at the start with the
+/// initial infected people. The other strains are variants seeded later.
+///
+/// # Fields
+/// - `strains`: All strains, the original strain first
+/// - `cross_immunity`: `cross_immunity[i][j]` is the protection (0.0 to 1.0) against strain `j`
+///   after recovering from strain `i`
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct StrainSet {
+    pub strains: Vec<Strain>,
+    pub cross_immunity: Vec<Vec<f32>>,
+}
+
+impl StrainSet {
+    /// Creates the strains described by the settings.
+    ///
+    /// The original strain uses `infection_probability`, `recovered_day` and
+    /// `initial_infected_people` of the settings, followed by the configured variants.
+    ///
+    /// # Parameters
+    /// - `settings`: The simulation settings
+    ///
+    /// # Returns
+    /// A new `StrainSet` with the original strain and the variants
+    pub fn from_settings(settings: &Settings) -> Self {
+        let mut strains = vec![Strain {
+            name: String::from("Original"),
+            infection_probability: settings.infection_probability,
+            recovered_day: settings.recovered_day,
+            seed_time: 0.0,
+            seed_count: settings.initial_infected_people,
+        }];
+        strains.extend(settings.variants.iter().cloned());
+        Self {
+            strains,
+            cross_immunity: settings.cross_immunity_matrix.clone(),
+        }
+    }
+
+    /// Adds a new variant which is more transmissible and shorter than the original strain.
+    ///
+    /// The new variant is seeded `variant_seed_time` seconds after the previous strain, and has
+    /// `cross_immunity` protection with every existing strain.
+    ///
+    /// # Parameters
+    /// - `settings`: The simulation settings
+    pub fn add_variant(&mut self, settings: &Settings) {
+        let count = self.strains.len();
+        let previous_seed_time = self.strains.last().map_or(0.0, |strain| strain.seed_time);
+        self.strains.push(Strain {
+            name: format!("Variant {}", count),
+            infection_probability: (settings.infection_probability * 1.5).min(1.0),
+            recovered_day: settings.recovered_day * 0.75,
+            seed_time: previous_seed_time + settings.variant_seed_time,
+            seed_count: settings.initial_infected_people,
+        });
+        for row in &mut self.cross_immunity {
+            row.push(settings.cross_immunity);
+        }
+        let mut new_row = vec![settings.cross_immunity; count + 1];
+        new_row[count] = 1.0;
+        self.cross_immunity.push(new_row);
+    }
+
+    /// Removes a variant and its cross-immunity.
+    ///
+    /// The original strain cannot be removed.
+    ///
+    /// # Parameters
+    /// - `strain`: Index of the variant to remove
+    pub fn remove_variant(&mut self, strain: usize) {
+        if strain == 0 || strain >= self.strains.len() {
+            return;
+        }
+        self.strains.remove(strain);
+        self.cross_immunity.remove(strain);
+        for row in &mut self.cross_immunity {
+            row.remove(strain);
+        }
+    }
+
+    /// Calculates how susceptible a person is to a strain given past infections.
+    ///
+    /// The best protection from all past infections is used.
+    ///
+    /// # Parameters
+    /// - `recovered_from`: Strains the person has recovered from
+    /// - `strain`: Strain the person is exposed to
+    ///
+    /// # Returns
+    /// `1.0` for a person without immunity down to `0.0` for a fully immune person
+    pub fn susceptibility(&self, recovered_from: &[usize], strain: usize) -> f32 {
+        let protection = recovered_from
+            .iter()
+            .map(|&past| self.cross_immunity[past][strain])
+            .fold(0.0, f32::max);
+        1.0 - protection
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::settings::*;
+
+    /// Tests that the default strains have a square cross-immunity matrix with full self-immunity.
+    #[test]
+    fn test_strain_set_from_settings() {
+        let strain_set = StrainSet::from_settings(&Settings::default());
+        assert_eq!(strain_set.strains.len(), 2);
+        assert_eq!(
+            strain_set.strains[0].infection_probability,
+            INFECTION_PROBABILITY
+        );
+        assert_eq!(strain_set.cross_immunity.len(), 2);
+        assert_eq!(strain_set.cross_immunity[0], vec![1.0, CROSS_IMMUNITY]);
+        assert_eq!(strain_set.cross_immunity[1], vec![CROSS_IMMUNITY, 1.0]);
+    }
+
+    /// Tests that removing a variant keeps the matrix square and the original strain stays.
+    #[test]
+    fn test_remove_variant() {
+        let settings = Settings::default();
+        let mut strain_set = StrainSet::from_settings(&settings);
+        strain_set.add_variant(&settings);
+        strain_set.remove_variant(1);
+        assert_eq!(strain_set.strains.len(), 2);
+        assert_eq!(strain_set.strains[1].name, "Variant 2");
+        assert!(strain_set.cross_immunity.iter().all(|row| row.len() == 2));
+
+        strain_set.remove_variant(0);
+        assert_eq!(strain_set.strains.len(), 2);
+    }
+
+    /// Tests that susceptibility uses the best protection from past infections.
+    #[test]
+    fn test_susceptibility() {
+        let mut strain_set = StrainSet::from_settings(&Settings::default());
+        strain_set.cross_immunity[0][1] = 0.25;
+        assert_eq!(strain_set.susceptibility(&[], 1), 1.0);
+        assert_eq!(strain_set.susceptibility(&[0], 0), 0.0);
+        assert_eq!(strain_set.susceptibility(&[0], 1), 0.75);
+        assert_eq!(strain_set.susceptibility(&[0, 1], 1), 0.0);
+    }
+
+    /// Tests that strain colors start with red and repeat.
+    #[test]
+    fn test_strain_color() {
+        assert_eq!(strain_color(0), egui::Color32::RED);
+        assert_eq!(strain_color(STRAIN_COLORS.len()), egui::Color32::RED);
+    }
+}
@@ -0,0 +1,381 @@
+//! Parameter sweep module.
+//!
+//! A sweep varies one or two settings over a grid of values and runs several seeds
+//! in each cell. Every cell uses the same seeds, so differences between cells come
+//! from the parameters and not from the random numbers. The result is a table of
+//! the final attack rate and peak prevalence of each cell.
+
+use std::fmt;
+use std::io::Write;
+use std::path::Path;
+
+use serde::Serialize;
+
+use crate::config::{self, ConfigError};
+use crate::ensemble::{self, quantile};
+use crate::export::{self, ExportError, ExportFormat};
+use crate::scenario::Scenario;
+use crate::settings::Settings;
+
+/// Default number of seeds run in each cell of a sweep.
+pub const SWEEP_SEEDS: usize = 5;
+
+/// Default number of values of each swept parameter.
+pub const SWEEP_STEPS: usize = 5;
+
+/// A setting which can be swept.
+///
+/// Sweeping a prevention parameter only has an effect when the prevention method is
+/// enabled in the settings.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SweepParameter {
+    InfectedRadius,
+    InfectionProbability,
+    RecoveredDay,
+    SocialDistancingRadius,
+    InfectionTimeBeforeQuarantine,
+    CommunitySize,
+    InitialInfectedPeople,
+    DispersionK,
+}
+
+impl SweepParameter {
+    /// All parameters which can be swept.
+    pub const ALL: [SweepParameter; 8] = [
+        SweepParameter::InfectedRadius,
+        SweepParameter::InfectionProbability,
+        SweepParameter::RecoveredDay,
+        SweepParameter::SocialDistancingRadius,
+        SweepParameter::InfectionTimeBeforeQuarantine,
+        SweepParameter::CommunitySize,
+        SweepParameter::InitialInfectedPeople,
+        SweepParameter::DispersionK,
+    ];
+
+    /// Returns the name of the setting.
+    pub fn name(self) -> &'static str {
+        match self {
+            SweepParameter::InfectedRadius => "infected_radius",
+            SweepParameter::InfectionProbability => "infection_probability",
+            SweepParameter::RecoveredDay => "recovered_day",
+            SweepParameter::SocialDistancingRadius => "social_distancing_radius",
+            SweepParameter::InfectionTimeBeforeQuarantine => "infection_time_before_quarantine",
+            SweepParameter::CommunitySize => "community_size",
+            SweepParameter::InitialInfectedPeople => "initial_infected_people",
+            SweepParameter::DispersionK => "dispersion_k",
+        }
+    }
+
+    /// Finds a parameter by the name of its setting.
+    ///
+    /// # Parameters
+    /// - `name`: Name of the setting
+    ///
+    /// # Returns
+    /// The parameter, or `None` if it cannot be swept
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|parameter| parameter.name() == name)
+    }
+
+    /// Returns the value of the parameter in some settings.
+    ///
+    /// # Parameters
+    /// - `settings`: The settings to read
+    pub fn get(self, settings: &Settings) -> f32 {
+        match self {
+            SweepParameter::InfectedRadius => settings.infected_radius,
+            SweepParameter::InfectionProbability => settings.infection_probability,
+            SweepParameter::RecoveredDay => settings.recovered_day,
+            SweepParameter::SocialDistancingRadius => settings.social_distancing_radius,
+            SweepParameter::InfectionTimeBeforeQuarantine => {
+                settings.infection_time_before_quarantine
+            }
+            SweepParameter::CommunitySize => settings.community_size as f32,
+            SweepParameter::InitialInfectedPeople => settings.initial_infected_people as f32,
+            SweepParameter::DispersionK => settings.dispersion_k,
+        }
+    }
+
+    /// Sets the parameter in some settings.
+    ///
+    /// Counts of people are rounded to the nearest whole number.
+    ///
+    /// # Parameters
+    /// - `settings`: The settings to change
+    /// - `value`: The new value
+    pub fn set(self, settings: &mut Settings, value: f32) {
+        match self {
+            SweepParameter::InfectedRadius => settings.infected_radius = value,
+            SweepParameter::InfectionProbability => settings.infection_probability = value,
+            SweepParameter::RecoveredDay => settings.recovered_day = value,
+            SweepParameter::SocialDistancingRadius => settings.social_distancing_radius = value,
+            SweepParameter::InfectionTimeBeforeQuarantine => {
+                settings.infection_time_before_quarantine = value
+            }
+            SweepParameter::CommunitySize => settings.community_size = value.round() as usize,
+            SweepParameter::InitialInfectedPeople => {
+                settings.initial_infected_people = value.round() as usize
+            }
+            SweepParameter::DispersionK => settings.dispersion_k = value,
+        }
+    }
+}
+
+impl fmt::Display for SweepParameter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+/// One swept parameter with its values.
+///
+/// # Fields
+/// - `parameter`: The swept setting
+/// - `values`: The values of the grid
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct SweepAxis {
+    pub parameter: SweepParameter,
+    pub values: Vec<f32>,
+}
+
+impl SweepAxis {
+    /// Creates an axis with evenly spaced values.
+    ///
+    /// # Parameters
+    /// - `parameter`: The swept setting
+    /// - `start`: First value
+    /// - `end`: Last value
+    /// - `steps`: Number of values (one value is `start`)
+    pub fn linear(parameter: SweepParameter, start: f32, end: f32, steps: usize) -> Self {
+        let values = match steps {
+            0 => Vec::new(),
+            1 => vec![start],
+            _ => (0..steps)
+                .map(|i| start + (end - start) * i as f32 / (steps - 1) as f32)
+                .collect(),
+        };
+        SweepAxis { parameter, values }
+    }
+
+    /// Parses an axis written as `name=start:end:steps`.
+    ///
+    /// # Parameters
+    /// - `text`: The axis, for example `infected_radius=2:10:5`
+    ///
+    /// # Returns
+    /// The axis, or an error message
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let (name, range) = text
+            .split_once('=')
+            .ok_or_else(|| format!("sweep '{}' must look like name=start:end:steps", text))?;
+        let parameter = SweepParameter::from_name(name).ok_or_else(|| {
+            let names: Vec<&str> = SweepParameter::ALL.iter().map(|p| p.name()).collect();
+            format!("cannot sweep '{}', use one of: {}", name, names.join(", "))
+        })?;
+        let parts: Vec<&str> = range.split(':').collect();
+        let invalid = || format!("sweep range '{}' must look like start:end:steps", range);
+        if parts.len() != 3 {
+            return Err(invalid());
+        }
+        let start: f32 = parts[0].parse().map_err(|_| invalid())?;
+        let end: f32 = parts[1].parse().map_err(|_| invalid())?;
+        let steps: usize = parts[2].parse().map_err(|_| invalid())?;
+        if steps == 0 {
+            return Err(invalid());
+        }
+        Ok(SweepAxis::linear(parameter, start, end, steps))
+    }
+}
+
+/// Results of one cell of a sweep.
+///
+/// # Fields
+/// - `x`: Value of the first parameter
+/// - `y`: Value of the second parameter, if two parameters are swept
+/// - `attack_rate`: Median final attack rate (percentage) of the seeds
+/// - `peak_prevalence`: Median peak percentage of infected people of the seeds
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct SweepCell {
+    pub x: f32,
+    pub y: Option<f32>,
+    pub attack_rate: f32,
+    pub peak_prevalence: f32,
+}
+
+/// Results of a sweep.
+///
+/// # Fields
+/// - `axes`: The swept parameters (one or two)
+/// - `seeds`: Number of seeds run in each cell
+/// - `cells`: One cell per combination of values, the first parameter changing fastest
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct Sweep {
+    pub axes: Vec<SweepAxis>,
+    pub seeds: usize,
+    pub cells: Vec<SweepCell>,
+}
+
+impl Sweep {
+    /// Writes the table of cells as CSV.
+    ///
+    /// # Parameters
+    /// - `out`: Where the CSV is written
+    pub fn write_csv(&self, out: &mut impl Write) -> Result<(), ExportError> {
+        let write_error = |error: std::io::Error| ExportError::Serialize(error.to_string());
+        let names: Vec<&str> = self.axes.iter().map(|axis| axis.parameter.name()).collect();
+        writeln!(out, "{},attack_rate,peak_prevalence", names.join(",")).map_err(write_error)?;
+        for cell in &self.cells {
+            let y = cell.y.map(|y| format!("{},", y)).unwrap_or_default();
+            writeln!(
+                out,
+                "{},{}{:.3},{:.3}",
+                cell.x, y, cell.attack_rate, cell.peak_prevalence
+            )
+            .map_err(write_error)?;
+        }
+        Ok(())
+    }
+
+    /// Saves the table to a CSV or JSON file.
+    ///
+    /// # Parameters
+    /// - `path`: Path of a `.csv` or `.json` file
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), ExportError> {
+        export::save_as(path, |format, out| match format {
+            ExportFormat::Csv => self.write_csv(out),
+            ExportFormat::Json => serde_json::to_writer_pretty(out, self)
+                .map_err(|error| ExportError::Serialize(error.to_string())),
+        })
+    }
+}
+
+/// Runs a sweep over one or two parameters.
+///
+/// All cells are validated first, then each cell runs an ensemble of `seeds` runs with
+/// the seeds `base_seed..base_seed + seeds`.
+///
+/// # Parameters
+/// - `settings`: Settings of every run except the swept parameters
+/// - `scenario`: Scenario played back in every run, if any
+/// - `axes`: The swept parameters (one or two)
+/// - `base_seed`: Seed of the first run of each cell
+/// - `seeds`: Number of runs in each cell
+/// - `duration`: Length (in seconds) of each run
+///
+/// # Returns
+/// The sweep, or the error if a cell has invalid settings
+pub fn run_sweep(
+    settings: &Settings,
+    scenario: Option<&Scenario>,
+    axes: &[SweepAxis],
+    base_seed: u64,
+    seeds: usize,
+    duration: f32,
+) -> Result<Sweep, ConfigError> {
+    let (x_axis, y_axis) = match axes {
+        [x] => (x, None),
+        [x, y] => (x, Some(y)),
+        _ => {
+            return Err(ConfigError::Invalid(vec![String::from(
+                "a sweep needs one or two parameters",
+            )]));
+        }
+    };
+    let y_values: Vec<Option<f32>> = match y_axis {
+        Some(axis) => axis.values.iter().copied().map(Some).collect(),
+        None => vec![None],
+    };
+
+    let mut grid = Vec::new();
+    for &y in &y_values {
+        for &x in &x_axis.values {
+            let mut cell_settings = settings.clone();
+            x_axis.parameter.set(&mut cell_settings, x);
+            if let (Some(axis), Some(y)) = (y_axis, y) {
+                axis.parameter.set(&mut cell_settings, y);
+            }
+            config::validate_settings(&cell_settings)?;
+            grid.push((x, y, cell_settings));
+        }
+    }
+
+    let median = |mut values: Vec<f32>| {
+        values.sort_by(f32::total_cmp);
+        quantile(&values, 0.5)
+    };
+    let mut cells = Vec::new();
+    for (x, y, cell_settings) in grid {
+        let ensemble =
+            ensemble::run_ensemble(&cell_settings, scenario, base_seed, seeds, duration)?;
+        cells.push(SweepCell {
+            x,
+            y,
+            attack_rate: median(ensemble.runs.iter().map(|run| run.attack_rate).collect()),
+            peak_prevalence: median(ensemble.runs.iter().map(|run| run.peak_size).collect()),
+        });
+    }
+    Ok(Sweep {
+        axes: axes.to_vec(),
+        seeds,
+        cells,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Tests that axes are parsed from `name=start:end:steps`.
+    #[test]
+    fn test_parse_axis() {
+        let axis = SweepAxis::parse("infected_radius=2:10:5").unwrap();
+        assert_eq!(axis.parameter, SweepParameter::InfectedRadius);
+        assert_eq!(axis.values, vec![2.0, 4.0, 6.0, 8.0, 10.0]);
+        assert!(SweepAxis::parse("speed=1:2:3").is_err());
+        assert!(SweepAxis::parse("infected_radius=1:2").is_err());
+        assert!(SweepAxis::parse("infected_radius=1:2:0").is_err());
+    }
+
+    /// Tests that every parameter is written to and read from the settings.
+    #[test]
+    fn test_parameter_set_get() {
+        for parameter in SweepParameter::ALL {
+            let mut settings = Settings::default();
+            parameter.set(&mut settings, 4.0);
+            assert_eq!(parameter.get(&settings), 4.0, "{}", parameter);
+            assert_eq!(SweepParameter::from_name(parameter.name()), Some(parameter));
+        }
+    }
+
+    /// Tests that a two-parameter sweep has one cell per combination.
+    #[test]
+    fn test_run_sweep_grid() {
+        let axes = [
+            SweepAxis::linear(SweepParameter::InfectedRadius, 2.0, 8.0, 2),
+            SweepAxis::linear(SweepParameter::InfectionProbability, 0.1, 0.5, 3),
+        ];
+        let sweep = run_sweep(&Settings::default(), None, &axes, 1, 2, 1.0).unwrap();
+        assert_eq!(sweep.cells.len(), 6);
+        assert_eq!((sweep.cells[1].x, sweep.cells[1].y), (8.0, Some(0.1)));
+        let mut out = Vec::new();
+        sweep.write_csv(&mut out).unwrap();
+        let text = String::from_utf8(out).unwrap();
+        assert!(text.starts_with("infected_radius,infection_probability,attack_rate,"));
+        assert_eq!(text.lines().count(), 7);
+    }
+
+    /// Tests that invalid values in a cell are reported.
+    #[test]
+    fn test_run_sweep_invalid_value() {
+        let axes = [SweepAxis::linear(
+            SweepParameter::InfectionProbability,
+            0.5,
+            1.5,
+            2,
+        )];
+        assert!(run_sweep(&Settings::default(), None, &axes, 1, 1, 1.0).is_err());
+    }
+}
@@ -0,0 +1,285 @@
+//! Transmission tree module built from the infection log.
+//!
+//! Each infection is a node whose parent is the infection of its infector, so
+//! initial, seeded and imported cases are the roots. A person infected more than once
+//! (with variants) has one node per infection. The tree can be exported as Newick
+//! (`.nwk`, `.newick`) or GraphML (`.graphml`) for external tools.
+
+use std::collections::HashMap;
+use std::fmt::Write as _;
+use std::fs;
+use std::path::Path;
+
+use crate::export::ExportError;
+use crate::infection_log::InfectionLog;
+
+/// Default transmission tree file shown in the UI.
+pub const DEFAULT_TREE_PATH: &str = "transmission_tree.nwk";
+
+/// One infection in the transmission tree.
+///
+/// # Fields
+/// - `person`: Index of the infected person in the community
+/// - `parent`: Index of the infector's node, `None` for roots
+/// - `children`: Indexes of the nodes infected by this infection
+/// - `time`: Simulation time (in seconds) of the infection
+/// - `strain`: Index of the strain
+#[derive(Clone, Debug, PartialEq)]
+pub struct TreeNode {
+    pub person: usize,
+    pub parent: Option<usize>,
+    pub children: Vec<usize>,
+    pub time: f32,
+    pub strain: usize,
+}
+
+/// Transmission tree of a run.
+///
+/// # Fields
+/// - `nodes`: One node per infection, in the order of the infection log
+/// - `roots`: Indexes of the nodes without an infector
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct TransmissionTree {
+    pub nodes: Vec<TreeNode>,
+    pub roots: Vec<usize>,
+}
+
+impl TransmissionTree {
+    /// Builds the tree from an infection log.
+    ///
+    /// The parent of an infection is the latest infection of its infector, which is
+    /// the infection the infector had at the time of transmission.
+    ///
+    /// # Parameters
+    /// - `log`: The infection log in the order infections happened
+    ///
+    /// # Returns
+    /// The transmission tree
+    pub fn from_log(log: &InfectionLog) -> Self {
+        let mut tree = TransmissionTree::default();
+        let mut latest_infection: HashMap<usize, usize> = HashMap::new();
+        for event in &log.events {
+            let index = tree.nodes.len();
+            let parent = event
+                .infector
+                .and_then(|infector| latest_infection.get(&infector).copied());
+            match parent {
+                Some(parent) => tree.nodes[parent].children.push(index),
+                None => tree.roots.push(index),
+            }
+            tree.nodes.push(TreeNode {
+                person: event.person,
+                parent,
+                children: Vec::new(),
+                time: event.time,
+                strain: event.strain,
+            });
+            latest_infection.insert(event.person, index);
+        }
+        tree
+    }
+
+    /// Finds the row of each node for drawing the tree.
+    ///
+    /// Leaves get consecutive rows in depth-first order and each parent is placed in the
+    /// middle of its first and last child, so branches never cross.
+    ///
+    /// # Returns
+    /// The row of each node and the number of rows
+    pub fn rows(&self) -> (Vec<f32>, usize) {
+        let mut rows = vec![0.0; self.nodes.len()];
+        let mut next_row = 0;
+        for &root in &self.roots {
+            self.assign_rows(root, &mut rows, &mut next_row);
+        }
+        (rows, next_row)
+    }
+
+    /// Assigns the rows of a node and all nodes below it.
+    ///
+    /// # Parameters
+    /// - `node`: Index of the node
+    /// - `rows`: Rows of all nodes
+    /// - `next_row`: Next free row for a leaf
+    fn assign_rows(&self, node: usize, rows: &mut [f32], next_row: &mut usize) {
+        let children = &self.nodes[node].children;
+        if children.is_empty() {
+            rows[node] = *next_row as f32;
+            *next_row += 1;
+            return;
+        }
+        for &child in children {
+            self.assign_rows(child, rows, next_row);
+        }
+        rows[node] = (rows[children[0]] + rows[children[children.len() - 1]]) / 2.0;
+    }
+
+    /// Formats the tree in the Newick format.
+    ///
+    /// Nodes are labelled `P<person>` and branch lengths are the time (in seconds)
+    /// between an infection and its infector's infection. Several roots are joined
+    /// under an unlabelled root at time 0.
+    ///
+    /// # Returns
+    /// The Newick text ending with `;`
+    pub fn to_newick(&self) -> String {
+        let mut text = String::new();
+        match self.roots.as_slice() {
+            [] => {}
+            [root] => self.write_newick(*root, 0.0, &mut text),
+            roots => {
+                text.push('(');
+                for (i, &root) in roots.iter().enumerate() {
+                    if i > 0 {
+                        text.push(',');
+                    }
+                    self.write_newick(root, 0.0, &mut text);
+                }
+                text.push(')');
+            }
+        }
+        text.push(';');
+        text
+    }
+
+    /// Writes a node and all nodes below it in the Newick format.
+    ///
+    /// # Parameters
+    /// - `node`: Index of the node
+    /// - `parent_time`: Time of the parent's infection
+    /// - `text`: Where the Newick text is written
+    fn write_newick(&self, node: usize, parent_time: f32, text: &mut String) {
+        let current = &self.nodes[node];
+        if !current.children.is_empty() {
+            text.push('(');
+            for (i, &child) in current.children.iter().enumerate() {
+                if i > 0 {
+                    text.push(',');
+                }
+                self.write_newick(child, current.time, text);
+            }
+            text.push(')');
+        }
+        let _ = write!(
+            text,
+            "P{}:{:.3}",
+            current.person,
+            current.time - parent_time
+        );
+    }
+
+    /// Formats the tree in the GraphML format.
+    ///
+    /// Each infection is a node with the person, time and strain as data, and each
+    /// transmission is a directed edge from the infector's node.
+    ///
+    /// # Returns
+    /// The GraphML document
+    pub fn to_graphml(&self) -> String {
+        let mut text = String::from(
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+             <graphml xmlns=\"http://graphml.graphdrawing.org/xmlns\">\n  \
+             <key id=\"person\" for=\"node\" attr.name=\"person\" attr.type=\"int\"/>\n  \
+             <key id=\"time\" for=\"node\" attr.name=\"time\" attr.type=\"double\"/>\n  \
+             <key id=\"strain\" for=\"node\" attr.name=\"strain\" attr.type=\"int\"/>\n  \
+             <graph id=\"transmission_tree\" edgedefault=\"directed\">\n",
+        );
+        for (index, node) in self.nodes.iter().enumerate() {
+            let _ = writeln!(
+                text,
+                "    <node id=\"n{}\"><data key=\"person\">{}</data><data key=\"time\">{:.4}</data><data key=\"strain\">{}</data></node>",
+                index, node.person, node.time, node.strain
+            );
+        }
+        for (index, node) in self.nodes.iter().enumerate() {
+            if let Some(parent) = node.parent {
+                let _ = writeln!(
+                    text,
+                    "    <edge source=\"n{}\" target=\"n{}\"/>",
+                    parent, index
+                );
+            }
+        }
+        text.push_str("  </graph>\n</graphml>\n");
+        text
+    }
+
+    /// Saves the tree to a Newick or GraphML file.
+    ///
+    /// # Parameters
+    /// - `path`: Path of a `.nwk`, `.newick` or `.graphml` file
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), ExportError> {
+        let path = path.as_ref();
+        let text = match path.extension().and_then(|extension| extension.to_str()) {
+            Some("nwk") | Some("newick") => self.to_newick(),
+            Some("graphml") => self.to_graphml(),
+            _ => {
+                return Err(ExportError::UnsupportedFormat(
+                    path.display().to_string(),
+                    ".nwk, .newick or .graphml",
+                ));
+            }
+        };
+        fs::write(path, text).map_err(|error| ExportError::Io(path.display().to_string(), error))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::infection_log::{InfectionEvent, InfectionLocation};
+
+    /// Creates a log where person 0 infects 1 and 2, 1 infects 3, and 5 is imported.
+    fn log() -> InfectionLog {
+        let mut log = InfectionLog::default();
+        for (person, infector, time) in [
+            (0, None, 0.0),
+            (1, Some(0), 1.0),
+            (2, Some(0), 2.0),
+            (3, Some(1), 2.5),
+            (5, None, 3.0),
+        ] {
+            log.record(InfectionEvent {
+                person,
+                infector,
+                time,
+                strain: 0,
+                location: InfectionLocation::Community,
+                infector_infection_age: None,
+            });
+        }
+        log
+    }
+
+    /// Tests that infections are linked to their infector's infection.
+    #[test]
+    fn test_from_log() {
+        let tree = TransmissionTree::from_log(&log());
+        assert_eq!(tree.roots, vec![0, 4]);
+        assert_eq!(tree.nodes[0].children, vec![1, 2]);
+        assert_eq!(tree.nodes[1].children, vec![3]);
+        assert_eq!(tree.nodes[3].parent, Some(1));
+    }
+
+    /// Tests that leaves get their own rows and parents are centered on their children.
+    #[test]
+    fn test_rows() {
+        let tree = TransmissionTree::from_log(&log());
+        let (rows, count) = tree.rows();
+        assert_eq!(count, 3);
+        assert_eq!(rows, vec![0.5, 0.0, 1.0, 0.0, 2.0]);
+    }
+
+    /// Tests the Newick and GraphML output.
+    #[test]
+    fn test_export_formats() {
+        let tree = TransmissionTree::from_log(&log());
+        assert_eq!(
+            tree.to_newick(),
+            "(((P3:1.500)P1:1.000,P2:2.000)P0:0.000,P5:3.000);"
+        );
+        let graphml = tree.to_graphml();
+        assert_eq!(graphml.matches("<node ").count(), 5);
+        assert!(graphml.contains("<edge source=\"n1\" target=\"n3\"/>"));
+    }
+}
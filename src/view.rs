@@ -0,0 +1,103 @@
+//! View module for drawing the simulation at any window size.
+//!
+//! The simulation works in world coordinates, where the community area starts at the
+//! origin and the quarantine area is to the right of it. The view transform scales the
+//! world uniformly to fit the space the window gives it and moves it to the screen,
+//! so the arena grows on a 4K monitor and shrinks on a small laptop. Mouse positions
+//! are mapped back to world coordinates with the inverse transform.
+
+use eframe::egui;
+
+use crate::settings::{MAX_VIEW_SCALE, MIN_VIEW_SCALE};
+
+/// Maps world coordinates to screen pixels.
+///
+/// # Fields
+/// - `origin`: Screen position of the world origin (the top-left corner of the community)
+/// - `scale`: Screen pixels per world unit
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ViewTransform {
+    pub origin: egui::Pos2,
+    pub scale: f32,
+}
+
+impl ViewTransform {
+    /// Finds the largest scale at which a world region fits into the available space.
+    ///
+    /// The scale is kept between `MIN_VIEW_SCALE` and `MAX_VIEW_SCALE`.
+    ///
+    /// # Parameters
+    /// - `world_size`: Size of the world region in world units
+    /// - `available`: Space available on the screen in pixels
+    ///
+    /// # Returns
+    /// The number of screen pixels per world unit
+    pub fn fit_scale(world_size: egui::Vec2, available: egui::Vec2) -> f32 {
+        (available.x / world_size.x)
+            .min(available.y / world_size.y)
+            .clamp(MIN_VIEW_SCALE, MAX_VIEW_SCALE)
+    }
+
+    /// Converts a world position to a screen position.
+    ///
+    /// # Parameters
+    /// - `x`, `y`: World position
+    pub fn to_screen(self, x: f32, y: f32) -> egui::Pos2 {
+        self.origin + egui::vec2(x, y) * self.scale
+    }
+
+    /// Converts a screen position to a world position.
+    ///
+    /// # Parameters
+    /// - `position`: Screen position, such as the pointer
+    ///
+    /// # Returns
+    /// The world position `(x, y)`
+    pub fn to_world(self, position: egui::Pos2) -> (f32, f32) {
+        let world = (position - self.origin) / self.scale;
+        (world.x, world.y)
+    }
+
+    /// Converts a rectangle in world coordinates to a screen rectangle.
+    ///
+    /// # Parameters
+    /// - `x`, `y`: World position of the top-left corner
+    /// - `width`, `height`: Size in world units
+    pub fn rect(self, x: f32, y: f32, width: f32, height: f32) -> egui::Rect {
+        egui::Rect::from_min_size(self.to_screen(x, y), egui::vec2(width, height) * self.scale)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Tests that the scale fits the tighter side and positions map back and forth.
+    #[test]
+    fn test_view_transform() {
+        let world = egui::vec2(600.0, 400.0);
+        assert_eq!(
+            ViewTransform::fit_scale(world, egui::vec2(1200.0, 2000.0)),
+            2.0
+        );
+        assert_eq!(
+            ViewTransform::fit_scale(world, egui::vec2(1200.0, 200.0)),
+            0.5
+        );
+        assert_eq!(
+            ViewTransform::fit_scale(world, egui::vec2(60.0, 40.0)),
+            MIN_VIEW_SCALE
+        );
+
+        let view = ViewTransform {
+            origin: egui::pos2(100.0, 50.0),
+            scale: 2.0,
+        };
+        assert_eq!(view.to_screen(10.0, 20.0), egui::pos2(120.0, 90.0));
+        assert_eq!(view.to_world(egui::pos2(120.0, 90.0)), (10.0, 20.0));
+        assert_eq!(
+            view.rect(0.0, 0.0, 30.0, 10.0),
+            egui::Rect::from_min_max(egui::pos2(100.0, 50.0), egui::pos2(160.0, 70.0))
+        );
+    }
+}
@@ -0,0 +1,176 @@
+//! Wall module for obstacles inside the community area.
+//!
+//! Walls are straight line segments, which are enough to build rooms, corridors and
+//! partitioned spaces. People bounce off them like off the border of the area: their
+//! velocity is mirrored at the wall and they are pushed out to their own radius, also
+//! at the ends of a wall. Walls are part of the settings, so they are saved in
+//! configuration files and snapshots.
+
+use serde::{Deserialize, Serialize};
+
+/// A wall from one point of the community area to another.
+///
+/// # Fields
+/// - `x1`, `y1`: Start of the wall
+/// - `x2`, `y2`: End of the wall
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Wall {
+    pub x1: f32,
+    pub y1: f32,
+    pub x2: f32,
+    pub y2: f32,
+}
+
+impl Wall {
+    /// Finds the point of the wall nearest to a position.
+    ///
+    /// # Parameters
+    /// - `x`, `y`: The position
+    ///
+    /// # Returns
+    /// The nearest point `(x, y)` on the wall
+    pub fn closest_point(&self, x: f32, y: f32) -> (f32, f32) {
+        let (dx, dy) = (self.x2 - self.x1, self.y2 - self.y1);
+        let length_squared = dx * dx + dy * dy;
+        if length_squared == 0.0 {
+            return (self.x1, self.y1);
+        }
+        let t = (((x - self.x1) * dx + (y - self.y1) * dy) / length_squared).clamp(0.0, 1.0);
+        (self.x1 + t * dx, self.y1 + t * dy)
+    }
+
+    /// Calculates the distance from a position to the wall.
+    ///
+    /// # Parameters
+    /// - `x`, `y`: The position
+    pub fn distance(&self, x: f32, y: f32) -> f32 {
+        let (closest_x, closest_y) = self.closest_point(x, y);
+        (x - closest_x).hypot(y - closest_y)
+    }
+
+    /// Checks if a movement from one position to another crosses the wall.
+    ///
+    /// # Parameters
+    /// - `from`: Position before the movement
+    /// - `to`: Position after the movement
+    ///
+    /// # Returns
+    /// `true` if the movement passes through the wall
+    pub fn crosses(&self, from: (f32, f32), to: (f32, f32)) -> bool {
+        let side = |ax: f32, ay: f32, bx: f32, by: f32, px: f32, py: f32| {
+            (bx - ax) * (py - ay) - (by - ay) * (px - ax)
+        };
+        let from_side = side(self.x1, self.y1, self.x2, self.y2, from.0, from.1);
+        let to_side = side(self.x1, self.y1, self.x2, self.y2, to.0, to.1);
+        let start_side = side(from.0, from.1, to.0, to.1, self.x1, self.y1);
+        let end_side = side(from.0, from.1, to.0, to.1, self.x2, self.y2);
+        from_side * to_side < 0.0 && start_side * end_side < 0.0
+    }
+
+    /// Bounces a moving circle off the wall.
+    ///
+    /// A circle which moved through the wall is put back to where it came from and its
+    /// velocity is mirrored. A circle overlapping the wall is pushed out along the line
+    /// from the nearest point of the wall, and its velocity is mirrored if it moves into
+    /// the wall.
+    ///
+    /// # Parameters
+    /// - `previous`: Position before the movement
+    /// - `position`: Position after the movement, changed to the resolved position
+    /// - `velocity`: Velocity, changed to the velocity after the bounce
+    /// - `radius`: Radius of the circle
+    pub fn collide(
+        &self,
+        previous: (f32, f32),
+        position: &mut (f32, f32),
+        velocity: &mut (f32, f32),
+        radius: f32,
+    ) {
+        if self.crosses(previous, *position) {
+            *position = previous;
+            let (dx, dy) = (self.x2 - self.x1, self.y2 - self.y1);
+            let length = dx.hypot(dy);
+            let (normal_x, normal_y) = (-dy / length, dx / length);
+            let normal_speed = velocity.0 * normal_x + velocity.1 * normal_y;
+            velocity.0 -= 2.0 * normal_speed * normal_x;
+            velocity.1 -= 2.0 * normal_speed * normal_y;
+        }
+        let (closest_x, closest_y) = self.closest_point(position.0, position.1);
+        let (mut normal_x, mut normal_y) = (position.0 - closest_x, position.1 - closest_y);
+        let distance = normal_x.hypot(normal_y);
+        if distance >= radius {
+            return;
+        }
+        if distance > 0.0 {
+            normal_x /= distance;
+            normal_y /= distance;
+        } else {
+            // Exactly on the wall: push out sideways, against the velocity
+            let (dx, dy) = (self.x2 - self.x1, self.y2 - self.y1);
+            let length = dx.hypot(dy).max(f32::EPSILON);
+            (normal_x, normal_y) = (-dy / length, dx / length);
+            if normal_x * velocity.0 + normal_y * velocity.1 > 0.0 {
+                (normal_x, normal_y) = (-normal_x, -normal_y);
+            }
+        }
+        *position = (closest_x + normal_x * radius, closest_y + normal_y * radius);
+        let speed_into_wall = velocity.0 * normal_x + velocity.1 * normal_y;
+        if speed_into_wall < 0.0 {
+            velocity.0 -= 2.0 * speed_into_wall * normal_x;
+            velocity.1 -= 2.0 * speed_into_wall * normal_y;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Tests the nearest point and distance, also beyond the ends of the wall.
+    #[test]
+    fn test_closest_point() {
+        let wall = Wall {
+            x1: 0.0,
+            y1: 0.0,
+            x2: 10.0,
+            y2: 0.0,
+        };
+        assert_eq!(wall.closest_point(4.0, 3.0), (4.0, 0.0));
+        assert_eq!(wall.closest_point(-5.0, 1.0), (0.0, 0.0));
+        assert_eq!(wall.distance(13.0, 4.0), 5.0);
+    }
+
+    /// Tests that a circle bounces off the wall and cannot pass through it.
+    #[test]
+    fn test_collide() {
+        let wall = Wall {
+            x1: 50.0,
+            y1: 0.0,
+            x2: 50.0,
+            y2: 100.0,
+        };
+
+        // Overlapping: pushed out and mirrored
+        let mut position = (48.0, 20.0);
+        let mut velocity = (3.0, 1.0);
+        wall.collide((46.0, 20.0), &mut position, &mut velocity, 4.0);
+        assert_eq!(position, (46.0, 20.0));
+        assert_eq!(velocity, (-3.0, 1.0));
+
+        // Moving through the wall in one step: put back on its side and mirrored
+        let mut position = (70.0, 50.0);
+        let mut velocity = (40.0, 0.0);
+        wall.collide((30.0, 50.0), &mut position, &mut velocity, 4.0);
+        assert_eq!(position, (30.0, 50.0));
+        assert_eq!(velocity, (-40.0, 0.0));
+        assert!(!wall.crosses((30.0, 120.0), (70.0, 120.0)));
+
+        // Moving away from the wall keeps the velocity
+        let mut position = (52.0, 20.0);
+        let mut velocity = (3.0, 0.0);
+        wall.collide((51.0, 20.0), &mut position, &mut velocity, 4.0);
+        assert_eq!(position, (54.0, 20.0));
+        assert_eq!(velocity, (3.0, 0.0));
+    }
+}